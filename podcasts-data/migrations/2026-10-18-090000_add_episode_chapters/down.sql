@@ -0,0 +1,3 @@
+DROP TABLE episode_chapters;
+
+ALTER TABLE episodes DROP COLUMN chapters_uri;
@@ -0,0 +1,11 @@
+ALTER TABLE episodes ADD COLUMN chapters_uri TEXT;
+
+CREATE TABLE episode_chapters (
+        episode_id      INTEGER NOT NULL,
+        start_time      INTEGER NOT NULL,
+        end_time        INTEGER,
+        title   TEXT,
+        image_uri       TEXT,
+        link    TEXT,
+        PRIMARY KEY (episode_id, start_time)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE episodes DROP COLUMN transcript_type;
+ALTER TABLE episodes DROP COLUMN transcript_uri;
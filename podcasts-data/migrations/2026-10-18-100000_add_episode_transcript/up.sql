@@ -0,0 +1,2 @@
+ALTER TABLE episodes ADD COLUMN transcript_uri TEXT;
+ALTER TABLE episodes ADD COLUMN transcript_type TEXT;
@@ -0,0 +1,10 @@
+CREATE TABLE download_queue (
+        episode_id      INTEGER NOT NULL,
+        download_dir    TEXT NOT NULL,
+        state   TEXT NOT NULL DEFAULT 'queued',
+        attempts        INTEGER NOT NULL DEFAULT 0,
+        next_attempt    INTEGER NOT NULL DEFAULT 0,
+        error   TEXT,
+        queued_at       INTEGER NOT NULL,
+        PRIMARY KEY (episode_id)
+);
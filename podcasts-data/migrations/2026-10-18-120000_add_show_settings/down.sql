@@ -0,0 +1 @@
+DROP TABLE show_settings;
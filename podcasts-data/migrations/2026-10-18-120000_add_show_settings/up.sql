@@ -0,0 +1,6 @@
+CREATE TABLE show_settings (
+        show_id INTEGER NOT NULL,
+        auto_download   TEXT NOT NULL DEFAULT 'off',
+        auto_download_limit     INTEGER NOT NULL DEFAULT 1,
+        PRIMARY KEY (show_id)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE show_settings DROP COLUMN playback_rate;
+ALTER TABLE show_settings DROP COLUMN skip_intro;
+ALTER TABLE show_settings DROP COLUMN skip_outro;
+ALTER TABLE show_settings DROP COLUMN retention;
+ALTER TABLE show_settings DROP COLUMN retention_days;
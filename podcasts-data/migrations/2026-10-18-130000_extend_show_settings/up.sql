@@ -0,0 +1,5 @@
+ALTER TABLE show_settings ADD COLUMN playback_rate REAL;
+ALTER TABLE show_settings ADD COLUMN skip_intro INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE show_settings ADD COLUMN skip_outro INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE show_settings ADD COLUMN retention TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE show_settings ADD COLUMN retention_days INTEGER NOT NULL DEFAULT 7;
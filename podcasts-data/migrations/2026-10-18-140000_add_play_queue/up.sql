@@ -0,0 +1,5 @@
+CREATE TABLE play_queue (
+        episode_id      INTEGER NOT NULL,
+        position        INTEGER NOT NULL,
+        PRIMARY KEY (episode_id)
+);
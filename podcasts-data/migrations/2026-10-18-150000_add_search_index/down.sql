@@ -0,0 +1,8 @@
+DROP TRIGGER episodes_search_update;
+DROP TRIGGER episodes_search_delete;
+DROP TRIGGER episodes_search_insert;
+DROP TRIGGER shows_search_update;
+DROP TRIGGER shows_search_delete;
+DROP TRIGGER shows_search_insert;
+DROP TABLE episodes_search;
+DROP TABLE shows_search;
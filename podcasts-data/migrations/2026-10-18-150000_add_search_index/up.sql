@@ -0,0 +1,50 @@
+CREATE VIRTUAL TABLE shows_search USING fts5(
+        title,
+        description,
+        content='shows',
+        content_rowid='id'
+);
+
+CREATE VIRTUAL TABLE episodes_search USING fts5(
+        title,
+        description,
+        content='episodes',
+        content_rowid='rowid'
+);
+
+INSERT INTO shows_search(shows_search) VALUES ('rebuild');
+INSERT INTO episodes_search(episodes_search) VALUES ('rebuild');
+
+CREATE TRIGGER shows_search_insert AFTER INSERT ON shows BEGIN
+        INSERT INTO shows_search(rowid, title, description)
+        VALUES (new.id, new.title, new.description);
+END;
+
+CREATE TRIGGER shows_search_delete AFTER DELETE ON shows BEGIN
+        INSERT INTO shows_search(shows_search, rowid, title, description)
+        VALUES ('delete', old.id, old.title, old.description);
+END;
+
+CREATE TRIGGER shows_search_update AFTER UPDATE OF title, description ON shows BEGIN
+        INSERT INTO shows_search(shows_search, rowid, title, description)
+        VALUES ('delete', old.id, old.title, old.description);
+        INSERT INTO shows_search(rowid, title, description)
+        VALUES (new.id, new.title, new.description);
+END;
+
+CREATE TRIGGER episodes_search_insert AFTER INSERT ON episodes BEGIN
+        INSERT INTO episodes_search(rowid, title, description)
+        VALUES (new.rowid, new.title, new.description);
+END;
+
+CREATE TRIGGER episodes_search_delete AFTER DELETE ON episodes BEGIN
+        INSERT INTO episodes_search(episodes_search, rowid, title, description)
+        VALUES ('delete', old.rowid, old.title, old.description);
+END;
+
+CREATE TRIGGER episodes_search_update AFTER UPDATE OF title, description ON episodes BEGIN
+        INSERT INTO episodes_search(episodes_search, rowid, title, description)
+        VALUES ('delete', old.rowid, old.title, old.description);
+        INSERT INTO episodes_search(rowid, title, description)
+        VALUES (new.rowid, new.title, new.description);
+END;
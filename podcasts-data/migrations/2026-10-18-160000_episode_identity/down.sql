@@ -0,0 +1,49 @@
+ALTER TABLE episodes RENAME TO old_table;
+
+CREATE TABLE episodes (
+        title   TEXT NOT NULL,
+        uri     TEXT,
+        local_uri       TEXT,
+        description     TEXT,
+        epoch   INTEGER NOT NULL DEFAULT 0,
+        length  INTEGER,
+        duration        INTEGER,
+        play_position   INTEGER NOT NULL,
+        guid    TEXT,
+        played  INTEGER,
+        show_id INTEGER NOT NULL,
+        chapters_uri    TEXT,
+        transcript_uri  TEXT,
+        transcript_type TEXT,
+        PRIMARY KEY (title, show_id)
+);
+
+-- Episodes that share a title can no longer be told apart.
+INSERT OR IGNORE INTO episodes (rowid, title, uri, local_uri, description, epoch, length, duration, play_position, guid, played, show_id, chapters_uri, transcript_uri, transcript_type)
+SELECT rowid, title, uri, local_uri, description, epoch, length, duration, play_position, guid, played, show_id, chapters_uri, transcript_uri, transcript_type
+FROM old_table
+ORDER BY rowid;
+Drop table old_table;
+
+DELETE FROM download_queue WHERE episode_id NOT IN (SELECT rowid FROM episodes);
+DELETE FROM episode_chapters WHERE episode_id NOT IN (SELECT rowid FROM episodes);
+DELETE FROM play_queue WHERE episode_id NOT IN (SELECT rowid FROM episodes);
+
+CREATE TRIGGER episodes_search_insert AFTER INSERT ON episodes BEGIN
+        INSERT INTO episodes_search(rowid, title, description)
+        VALUES (new.rowid, new.title, new.description);
+END;
+
+CREATE TRIGGER episodes_search_delete AFTER DELETE ON episodes BEGIN
+        INSERT INTO episodes_search(episodes_search, rowid, title, description)
+        VALUES ('delete', old.rowid, old.title, old.description);
+END;
+
+CREATE TRIGGER episodes_search_update AFTER UPDATE OF title, description ON episodes BEGIN
+        INSERT INTO episodes_search(episodes_search, rowid, title, description)
+        VALUES ('delete', old.rowid, old.title, old.description);
+        INSERT INTO episodes_search(rowid, title, description)
+        VALUES (new.rowid, new.title, new.description);
+END;
+
+INSERT INTO episodes_search(episodes_search) VALUES ('rebuild');
@@ -0,0 +1,54 @@
+ALTER TABLE episodes RENAME TO old_table;
+
+CREATE TABLE episodes (
+        title   TEXT NOT NULL,
+        uri     TEXT,
+        local_uri       TEXT,
+        description     TEXT,
+        epoch   INTEGER NOT NULL DEFAULT 0,
+        length  INTEGER,
+        duration        INTEGER,
+        play_position   INTEGER NOT NULL DEFAULT 0,
+        guid    TEXT,
+        identity        TEXT NOT NULL,
+        played  INTEGER,
+        show_id INTEGER NOT NULL,
+        chapters_uri    TEXT,
+        transcript_uri  TEXT,
+        transcript_type TEXT,
+        PRIMARY KEY (identity, show_id)
+);
+
+-- Keep the rowids, as the queues, the chapters and the search index refer
+-- to them. Retitled episodes were indexed more than once, keep the copy
+-- with the most user state.
+INSERT OR IGNORE INTO episodes (rowid, title, uri, local_uri, description, epoch, length, duration, play_position, guid, identity, played, show_id, chapters_uri, transcript_uri, transcript_type)
+SELECT rowid, title, uri, local_uri, description, epoch, length, duration, play_position, guid,
+        COALESCE(NULLIF(guid, ''), NULLIF(uri, ''), title),
+        played, show_id, chapters_uri, transcript_uri, transcript_type
+FROM old_table
+ORDER BY local_uri IS NOT NULL DESC, played IS NOT NULL DESC, play_position DESC, rowid DESC;
+Drop table old_table;
+
+DELETE FROM download_queue WHERE episode_id NOT IN (SELECT rowid FROM episodes);
+DELETE FROM episode_chapters WHERE episode_id NOT IN (SELECT rowid FROM episodes);
+DELETE FROM play_queue WHERE episode_id NOT IN (SELECT rowid FROM episodes);
+
+CREATE TRIGGER episodes_search_insert AFTER INSERT ON episodes BEGIN
+        INSERT INTO episodes_search(rowid, title, description)
+        VALUES (new.rowid, new.title, new.description);
+END;
+
+CREATE TRIGGER episodes_search_delete AFTER DELETE ON episodes BEGIN
+        INSERT INTO episodes_search(episodes_search, rowid, title, description)
+        VALUES ('delete', old.rowid, old.title, old.description);
+END;
+
+CREATE TRIGGER episodes_search_update AFTER UPDATE OF title, description ON episodes BEGIN
+        INSERT INTO episodes_search(episodes_search, rowid, title, description)
+        VALUES ('delete', old.rowid, old.title, old.description);
+        INSERT INTO episodes_search(rowid, title, description)
+        VALUES (new.rowid, new.title, new.description);
+END;
+
+INSERT INTO episodes_search(episodes_search) VALUES ('rebuild');
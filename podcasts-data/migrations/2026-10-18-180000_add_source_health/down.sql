@@ -0,0 +1,5 @@
+ALTER TABLE source DROP COLUMN gone;
+ALTER TABLE source DROP COLUMN next_attempt;
+ALTER TABLE source DROP COLUMN last_success;
+ALTER TABLE source DROP COLUMN last_error;
+ALTER TABLE source DROP COLUMN failures;
@@ -0,0 +1,5 @@
+ALTER TABLE source ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE source ADD COLUMN last_error TEXT;
+ALTER TABLE source ADD COLUMN last_success INTEGER;
+ALTER TABLE source ADD COLUMN next_attempt INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE source ADD COLUMN gone INTEGER;
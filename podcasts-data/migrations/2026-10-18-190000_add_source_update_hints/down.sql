@@ -0,0 +1,3 @@
+ALTER TABLE source DROP COLUMN skip_days;
+ALTER TABLE source DROP COLUMN skip_hours;
+ALTER TABLE source DROP COLUMN update_interval;
@@ -0,0 +1,3 @@
+ALTER TABLE source ADD COLUMN update_interval INTEGER;
+ALTER TABLE source ADD COLUMN skip_hours INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE source ADD COLUMN skip_days INTEGER NOT NULL DEFAULT 0;
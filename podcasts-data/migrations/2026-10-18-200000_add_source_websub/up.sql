@@ -0,0 +1,2 @@
+ALTER TABLE source ADD COLUMN websub_hub TEXT;
+ALTER TABLE source ADD COLUMN self_url TEXT;
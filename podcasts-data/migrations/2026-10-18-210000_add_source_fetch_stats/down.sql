@@ -0,0 +1,4 @@
+ALTER TABLE source DROP COLUMN last_fetch_ms;
+ALTER TABLE source DROP COLUMN bytes_fetched;
+ALTER TABLE source DROP COLUMN not_modified;
+ALTER TABLE source DROP COLUMN fetches;
@@ -0,0 +1,4 @@
+ALTER TABLE source ADD COLUMN fetches INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE source ADD COLUMN not_modified INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE source ADD COLUMN bytes_fetched BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE source ADD COLUMN last_fetch_ms INTEGER;
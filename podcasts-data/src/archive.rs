@@ -0,0 +1,459 @@
+// archive.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Export and import the library, along with its listening state.
+//!
+//! The archive is a versioned JSON document with the subscriptions, the
+//! shows, the played flag and play position of their episodes and the
+//! settings of each show. Unlike the OPML export, it can restore a library
+//! on another machine the way it was left. `backup_to_file` makes a copy of
+//! the whole database instead.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::database::Database;
+use crate::errors::DataError;
+use crate::models::{
+    AutoDownload, Episode, NewEpisode, NewEpisodeBuilder, NewShow, NewSource, Retention, Save,
+    Show, ShowSettings, Source,
+};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The version of the archives `export_to_writer` writes.
+///
+/// Bump it when the format changes in a way older versions can't read.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    sources: Vec<ArchivedSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ArchivedSource {
+    uri: String,
+    /// Missing for the sources that were never indexed.
+    show: Option<ArchivedShow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ArchivedShow {
+    title: String,
+    link: String,
+    description: String,
+    image_uri: Option<String>,
+    /// Missing for the shows that were never configured.
+    settings: Option<ArchivedSettings>,
+    episodes: Vec<ArchivedEpisode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ArchivedSettings {
+    auto_download: AutoDownload,
+    playback_rate: Option<f64>,
+    skip_intro: u32,
+    skip_outro: u32,
+    retention: Retention,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ArchivedEpisode {
+    identity: String,
+    guid: Option<String>,
+    title: String,
+    uri: Option<String>,
+    epoch: i32,
+    duration: Option<i32>,
+    played: Option<i32>,
+    play_position: i32,
+}
+
+impl Archive {
+    /// Collect the library of `db`.
+    fn collect(db: &Database) -> Result<Archive, DataError> {
+        let mut shows: HashMap<i32, Show> = db
+            .get_podcasts()?
+            .into_iter()
+            .map(|show| (show.source_id(), show))
+            .collect();
+        let mut settings: HashMap<i32, ShowSettings> = db
+            .get_all_show_settings()?
+            .into_iter()
+            .map(|settings| (settings.show_id(), settings))
+            .collect();
+
+        let mut sources = Vec::new();
+        for source in db.get_sources()? {
+            let show = match shows.remove(&source.id()) {
+                Some(show) => {
+                    let episodes = db.get_pd_episodes(&show)?;
+                    let settings = settings.remove(&show.id());
+                    Some(ArchivedShow::new(&show, settings.as_ref(), &episodes))
+                }
+                None => None,
+            };
+            sources.push(ArchivedSource {
+                uri: source.uri().to_owned(),
+                show,
+            });
+        }
+
+        Ok(Archive {
+            version: ARCHIVE_VERSION,
+            sources,
+        })
+    }
+
+    /// Merge the archive into `db`, and return the `Source`s it contained.
+    fn restore(&self, db: &Database) -> Result<Vec<Source>, DataError> {
+        if self.version > ARCHIVE_VERSION {
+            return Err(DataError::UnsupportedArchive(self.version));
+        }
+        self.sources
+            .iter()
+            .map(|source| source.restore(db))
+            .collect()
+    }
+}
+
+impl ArchivedSource {
+    fn restore(&self, db: &Database) -> Result<Source, DataError> {
+        let url = Url::parse(&self.uri)?;
+        let source = NewSource::new(&url).to_source_in(db)?;
+        if let Some(show) = &self.show {
+            show.restore(db, source.id())?;
+        }
+        Ok(source)
+    }
+}
+
+impl ArchivedShow {
+    fn new(show: &Show, settings: Option<&ShowSettings>, episodes: &[Episode]) -> Self {
+        ArchivedShow {
+            title: show.title().to_owned(),
+            link: show.link().to_owned(),
+            description: show.description().to_owned(),
+            image_uri: show.image_uri().map(From::from),
+            settings: settings.map(ArchivedSettings::new),
+            episodes: episodes.iter().map(ArchivedEpisode::new).collect(),
+        }
+    }
+
+    /// Create the show if it's new, and bring its episodes and settings to
+    /// the archived state.
+    ///
+    /// The metadata of a show that already exists is left alone, the next
+    /// refresh takes care of it.
+    fn restore(&self, db: &Database, source_id: i32) -> Result<(), DataError> {
+        let show = if db.podcast_exists(source_id)? {
+            db.get_podcast_from_source_id(source_id)?
+        } else {
+            NewShow::from_metadata(
+                &self.title,
+                &self.link,
+                &self.description,
+                self.image_uri.clone(),
+                source_id,
+            )
+            .to_podcast_in(db)?
+        };
+
+        let mut new = Vec::new();
+        for ep in &self.episodes {
+            if !db.episode_exists(&ep.identity, show.id())? {
+                new.push(ep.to_new_episode(show.id())?);
+            }
+        }
+        if !new.is_empty() {
+            db.index_new_episodes(&new)?;
+        }
+
+        // Don't undo what was listened to locally with blank state
+        for ep in self.episodes.iter().filter(|ep| ep.has_state()) {
+            db.restore_episode_state(show.id(), &ep.identity, ep.played, ep.play_position)?;
+        }
+
+        if let Some(settings) = &self.settings {
+            settings.restore(db, show.id())?;
+        }
+        Ok(())
+    }
+}
+
+impl ArchivedSettings {
+    fn new(settings: &ShowSettings) -> Self {
+        ArchivedSettings {
+            auto_download: settings.auto_download(),
+            playback_rate: settings.playback_rate(),
+            skip_intro: settings.skip_intro(),
+            skip_outro: settings.skip_outro(),
+            retention: settings.retention(),
+        }
+    }
+
+    fn restore(&self, db: &Database, show_id: i32) -> Result<(), DataError> {
+        let mut settings = db.get_show_settings(show_id)?;
+        settings.set_auto_download(self.auto_download);
+        settings.set_playback_rate(self.playback_rate);
+        settings.set_skip_intro(self.skip_intro);
+        settings.set_skip_outro(self.skip_outro);
+        settings.set_retention(self.retention);
+        settings.save_in(db)?;
+        Ok(())
+    }
+}
+
+impl ArchivedEpisode {
+    fn new(ep: &Episode) -> Self {
+        ArchivedEpisode {
+            identity: ep.identity().to_owned(),
+            guid: ep.guid().map(From::from),
+            title: ep.title().to_owned(),
+            uri: ep.uri().map(From::from),
+            epoch: ep.epoch(),
+            duration: ep.duration(),
+            played: ep.played(),
+            play_position: ep.play_position(),
+        }
+    }
+
+    fn has_state(&self) -> bool {
+        self.played.is_some() || self.play_position > 0
+    }
+
+    fn to_new_episode(&self, show_id: i32) -> Result<NewEpisode, DataError> {
+        NewEpisodeBuilder::default()
+            .title(self.title.clone())
+            .uri(self.uri.clone())
+            .guid(self.guid.clone())
+            .identity(self.identity.clone())
+            .epoch(self.epoch)
+            .duration(self.duration)
+            .show_id(show_id)
+            .build()
+            .map_err(|err| DataError::BuilderError(format!("{err}")))
+    }
+}
+
+/// Write the archive of the library of `db` into `W`.
+pub fn export_to_writer<W: Write>(db: &Database, writer: W) -> Result<(), DataError> {
+    // Read everything from one snapshot, so that a refresh can't interleave
+    let archive = db.snapshot(Archive::collect)?;
+    serde_json::to_writer_pretty(writer, &archive)?;
+    Ok(())
+}
+
+/// Write the archive of the library of `db` to a file at `path`.
+pub fn export_to_file<P: AsRef<Path>>(db: &Database, path: P) -> Result<(), DataError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    export_to_writer(db, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read an archive from `R` and merge it into `db`.
+///
+/// Nothing is imported if any of it fails. The `Source`s of the archive are
+/// returned, so that they can be refreshed.
+pub fn import_from_reader<R: Read>(db: &Database, reader: R) -> Result<Vec<Source>, DataError> {
+    let archive: Archive = serde_json::from_reader(reader)?;
+    db.transaction(|db| archive.restore(db))
+}
+
+/// Read an archive from the file at `path` and merge it into `db`.
+pub fn import_from_file<P: AsRef<Path>>(db: &Database, path: P) -> Result<Vec<Source>, DataError> {
+    let file = File::open(path)?;
+    import_from_reader(db, BufReader::new(file))
+}
+
+/// Write a copy of the database of `db` to `path`, while it's in use.
+///
+/// An existing file at `path` is only replaced once the copy is complete.
+pub fn backup_to_file<P: AsRef<Path>>(db: &Database, path: P) -> Result<(), DataError> {
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let tempdir = tempfile::Builder::new()
+        .prefix(".podcasts-backup")
+        .tempdir_in(dir)?;
+    let copy = tempdir.path().join("podcasts.db");
+
+    db.vacuum_into(&copy.to_string_lossy())?;
+    fs::rename(&copy, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::get_feed;
+    use anyhow::Result;
+
+    fn open(dir: &tempfile::TempDir, name: &str) -> Result<Database> {
+        Ok(Database::open(dir.path().join(name))?)
+    }
+
+    fn source(db: &Database, uri: &str) -> Result<Source> {
+        Ok(NewSource::new(&Url::parse(uri)?).to_source_in(db)?)
+    }
+
+    #[test]
+    fn test_archive_round_trip() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let first = open(&dir, "first.db")?;
+        let retitled = source(&first, "https://example.org/retitled.xml")?;
+        source(&first, "https://example.org/never-indexed.xml")?;
+        get_feed("tests/feeds/2026-10-18-Retitled.xml", retitled.id()).index_in(&first)?;
+
+        let show = first.get_podcast_from_source_id(retitled.id())?;
+        first.restore_episode_state(show.id(), "retitled-episode-1", Some(1_500_000_000), 0)?;
+        first.restore_episode_state(show.id(), "retitled-episode-3", None, 300)?;
+        let mut settings = first.get_show_settings(show.id())?;
+        settings.set_auto_download(AutoDownload::Latest(2));
+        settings.set_playback_rate(Some(1.5));
+        settings.set_retention(Retention::Days(3));
+        settings.save_in(&first)?;
+
+        let mut json = Vec::new();
+        export_to_writer(&first, &mut json)?;
+        let value: serde_json::Value = serde_json::from_slice(&json)?;
+        assert_eq!(value["version"], ARCHIVE_VERSION);
+
+        let second = open(&dir, "second.db")?;
+        let sources = import_from_reader(&second, json.as_slice())?;
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].uri(), "https://example.org/retitled.xml");
+        assert_eq!(sources[1].uri(), "https://example.org/never-indexed.xml");
+        assert_eq!(second.get_podcasts()?.len(), 1);
+
+        let restored = second.get_podcast_from_source_id(sources[0].id())?;
+        assert_eq!(restored.title(), show.title());
+        let episodes = second.get_pd_episodes(&restored)?;
+        let old = first.get_pd_episodes(&show)?;
+        assert_eq!(episodes.len(), 3);
+        for (ep, old) in episodes.iter().zip(&old) {
+            assert_eq!(ep.identity(), old.identity());
+            assert_eq!(ep.played(), old.played());
+            assert_eq!(ep.play_position(), old.play_position());
+        }
+        let settings = second.get_show_settings(restored.id())?;
+        assert_eq!(settings.auto_download(), AutoDownload::Latest(2));
+        assert_eq!(settings.playback_rate(), Some(1.5));
+        assert_eq!(settings.retention(), Retention::Days(3));
+
+        // Refreshing the restored show keeps its state
+        let feed = get_feed(
+            "tests/feeds/2026-10-18-Retitled-updated.xml",
+            sources[0].id(),
+        );
+        feed.index_in(&second)?;
+        let ep = second.get_episode_from_pk("retitled-episode-1", restored.id())?;
+        assert_eq!(ep.title(), "Episode 1: The Pilot");
+        assert_eq!(ep.played(), Some(1_500_000_000));
+
+        // Importing again changes nothing
+        import_from_reader(&second, json.as_slice())?;
+        assert_eq!(second.get_sources()?.len(), 2);
+        assert_eq!(second.get_pd_episodes(&restored)?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_version() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let db = open(&dir, "podcasts.db")?;
+        let json = r#"{
+            "version": 99,
+            "sources": [{ "uri": "https://example.org/feed.xml", "show": null }]
+        }"#;
+
+        let err = import_from_reader(&db, json.as_bytes()).unwrap_err();
+        assert!(matches!(err, DataError::UnsupportedArchive(99)));
+        assert!(db.get_sources()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_during_refresh() -> Result<()> {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::{Duration, Instant};
+
+        let dir = tempfile::TempDir::new()?;
+        let db = open(&dir, "podcasts.db")?;
+        let retitled = source(&db, "https://example.org/retitled.xml")?;
+        get_feed("tests/feeds/2026-10-18-Retitled.xml", retitled.id()).index_in(&db)?;
+
+        // Hold the writer, like a long refresh would
+        let (started, wait_started) = mpsc::channel();
+        let (finish, wait_finish) = mpsc::channel::<()>();
+        let refresh = {
+            let db = db.clone();
+            thread::spawn(move || {
+                db.transaction(|tx| {
+                    let url = Url::parse("https://example.org/intercepted.xml")?;
+                    let id = NewSource::new(&url).to_source_in(tx)?.id();
+                    get_feed("tests/feeds/2018-01-20-Intercepted.xml", id).index_in(tx)?;
+                    started.send(()).unwrap();
+                    wait_finish.recv().unwrap();
+                    Ok(())
+                })
+            })
+        };
+        wait_started.recv()?;
+
+        // The export doesn't wait for it, and only sees what was committed
+        let start = Instant::now();
+        let mut json = Vec::new();
+        export_to_writer(&db, &mut json)?;
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let value: serde_json::Value = serde_json::from_slice(&json)?;
+        assert_eq!(value["sources"].as_array().unwrap().len(), 1);
+
+        finish.send(())?;
+        refresh.join().unwrap()?;
+        assert_eq!(db.get_sources()?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let db = open(&dir, "podcasts.db")?;
+        let path = dir.path().join("backup.db");
+        get_feed("tests/feeds/2018-01-20-Intercepted.xml", 42).index_in(&db)?;
+
+        backup_to_file(&db, &path)?;
+        assert_eq!(Database::open(&path)?.get_episodes()?.len(), 43);
+
+        // An older backup is replaced
+        get_feed("tests/feeds/2026-10-18-Retitled.xml", 43).index_in(&db)?;
+        backup_to_file(&db, &path)?;
+        assert_eq!(Database::open(&path)?.get_podcasts()?.len(), 2);
+        Ok(())
+    }
+}
@@ -17,18 +17,23 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-//! Database Setup. This is only public to help with some unit tests.
+//! Database Setup, and the `Database` handle the queries run on.
 // Diesel embed_migrations! triggers the lint
 
+use diesel::connection::{AnsiTransactionManager, SimpleConnection, TransactionManager};
 use diesel::prelude::*;
 use diesel::r2d2;
-use diesel::r2d2::ConnectionManager;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, PooledConnection};
 
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
 use once_cell::sync::Lazy;
 
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
 
 use crate::errors::DataError;
 
@@ -36,10 +41,17 @@ use crate::errors::DataError;
 use crate::xdg_dirs;
 
 type Pool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+type Pooled = PooledConnection<ConnectionManager<SqliteConnection>>;
+
+/// How long to wait for a lock held by another connection.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many connections can read from a `Database` at the same time.
+const READERS: u32 = 4;
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/");
 
-static POOL: Lazy<Pool> = Lazy::new(|| init_pool(DB_PATH.to_str().unwrap()));
+static DATABASE: Lazy<Database> =
+    Lazy::new(|| Database::open(&*DB_PATH).expect("Failed to open the database."));
 
 #[cfg(not(test))]
 static DB_PATH: Lazy<PathBuf> = Lazy::new(|| {
@@ -55,24 +67,273 @@ pub(crate) static TEMPDIR: Lazy<tempfile::TempDir> =
 #[cfg(test)]
 static DB_PATH: Lazy<PathBuf> = Lazy::new(|| TEMPDIR.path().join("podcasts.db"));
 
-/// Get an r2d2 `SqliteConnection`.
-pub(crate) fn connection() -> Pool {
-    POOL.clone()
+/// A handle to a podcasts database.
+///
+/// Clones are cheap and share the same connections. The queries of
+/// `dbqueries` are available as methods, and the models can be saved
+/// and indexed into a specific `Database` with the `*_in` methods of their
+/// traits.
+///
+/// The database is in WAL mode: writes go through a single connection,
+/// while a pool of readers keeps answering queries during a refresh.
+#[derive(Clone)]
+pub struct Database {
+    writer: Pool,
+    readers: Pool,
+    /// The connection of the `transaction` this handle runs in, if any.
+    transaction: Option<Arc<Mutex<Pooled>>>,
+    /// The thread running a `transaction` on the writer, if any.
+    writing_thread: Arc<Mutex<Option<ThreadId>>>,
 }
 
-fn init_pool(db_path: &str) -> Pool {
-    let manager = ConnectionManager::<SqliteConnection>::new(db_path);
-    let pool = r2d2::Pool::builder()
-        .max_size(1)
-        .build(manager)
-        .expect("Failed to create pool.");
+impl Database {
+    /// Open the database at `path`, creating it if needed, and bring its
+    /// schema up to date.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Database, DataError> {
+        let path = path.as_ref().to_string_lossy();
+        let writer = r2d2::Pool::builder()
+            .max_size(1)
+            .connection_customizer(Box::new(ConnectionOptions))
+            .build(ConnectionManager::new(path.clone()))?;
+        {
+            let mut con = writer.get()?;
+            con.batch_execute("PRAGMA journal_mode = WAL;")?;
+            run_migration_on(&mut con)?;
+        }
+        // Only opened once the migrations created the schema
+        let readers = r2d2::Pool::builder()
+            .max_size(READERS)
+            .connection_customizer(Box::new(ConnectionOptions))
+            .build(ConnectionManager::new(path))?;
+
+        info!("Database pool initialized.");
+        Ok(Database {
+            writer,
+            readers,
+            transaction: None,
+            writing_thread: Arc::default(),
+        })
+    }
+
+    /// Get the database of the XDG data directory, which the free functions
+    /// of `dbqueries` and the convenience methods of the models use.
+    pub fn global() -> Database {
+        DATABASE.clone()
+    }
+
+    /// Get the `SqliteConnection` to write with.
+    ///
+    /// Fails instead of waiting forever when the writer is taken by the
+    /// `transaction` of the current thread.
+    pub(crate) fn get(&self) -> Result<Conn<'_>, DataError> {
+        match &self.transaction {
+            Some(con) => match con.try_lock() {
+                Ok(con) => Ok(Conn::Transaction(con)),
+                Err(TryLockError::Poisoned(err)) => Ok(Conn::Transaction(err.into_inner())),
+                Err(TryLockError::WouldBlock) => Err(DataError::Bail(
+                    "The connection of the transaction is already in use".into(),
+                )),
+            },
+            None if self.is_writing_thread() => Err(DataError::Bail(
+                "Can't write outside of the transaction of this thread".into(),
+            )),
+            None => Ok(self.writer.get().map(Conn::Pooled)?),
+        }
+    }
+
+    /// Get a `SqliteConnection` to read with, that doesn't wait for writes.
+    ///
+    /// In a `transaction` this is the connection of the transaction, so
+    /// that its own changes are visible.
+    pub(crate) fn read(&self) -> Result<Conn<'_>, DataError> {
+        match self.transaction {
+            Some(_) => self.get(),
+            None => Ok(self.readers.get().map(Conn::Pooled)?),
+        }
+    }
+
+    fn is_writing_thread(&self) -> bool {
+        let writing = self
+            .writing_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *writing == Some(thread::current().id())
+    }
+
+    /// Run `f` in a single transaction.
+    ///
+    /// Everything `f` does with the `Database` it's given is committed if it
+    /// returns `Ok`, and rolled back otherwise, or if it panics. Nested
+    /// transactions run in the outer one.
+    ///
+    /// `f` must only use the `Database` it's given, and one connection of it
+    /// at a time. Writing through any other handle, like the free functions
+    /// of `dbqueries`, fails as it would wait for the transaction to end.
+    pub fn transaction<T, F>(&self, f: F) -> Result<T, DataError>
+    where
+        F: FnOnce(&Database) -> Result<T, DataError>,
+    {
+        if self.transaction.is_some() {
+            return f(self);
+        }
+
+        let tx = OpenTransaction::begin(self)?;
+        let value = f(&tx.db)?;
+        tx.commit()?;
+        Ok(value)
+    }
 
+    /// Run `f` against a consistent snapshot of the database.
+    ///
+    /// Unlike `transaction` this runs on a reader, so it doesn't wait for a
+    /// refresh to finish, nor holds one back. `f` must only read, and only
+    /// use the `Database` it's given. In a `transaction` it runs in it.
+    pub fn snapshot<T, F>(&self, f: F) -> Result<T, DataError>
+    where
+        F: FnOnce(&Database) -> Result<T, DataError>,
     {
-        let mut db = pool.get().expect("Failed to initialize pool.");
-        run_migration_on(&mut db).expect("Failed to run migrations during init.");
+        if self.transaction.is_some() {
+            return f(self);
+        }
+
+        let tx = OpenTransaction::begin_read(self)?;
+        f(&tx.db)
     }
-    info!("Database pool initialized.");
-    pool
+}
+
+/// A transaction on a connection of a `Database`, that is rolled back when
+/// dropped before being committed.
+struct OpenTransaction {
+    db: Database,
+    /// Whether this is on the writer, rather than a snapshot on a reader.
+    writing: bool,
+    committed: bool,
+}
+
+impl OpenTransaction {
+    fn begin(db: &Database) -> Result<Self, DataError> {
+        if db.is_writing_thread() {
+            return Err(DataError::Bail(
+                "Can't start a transaction inside another one of this thread".into(),
+            ));
+        }
+        let mut con = db.writer.get()?;
+        AnsiTransactionManager::begin_transaction_sql(&mut *con, "BEGIN IMMEDIATE")?;
+
+        let mut writing = db
+            .writing_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *writing = Some(thread::current().id());
+        drop(writing);
+
+        Ok(OpenTransaction {
+            db: Database {
+                transaction: Some(Arc::new(Mutex::new(con))),
+                ..db.clone()
+            },
+            writing: true,
+            committed: false,
+        })
+    }
+
+    fn begin_read(db: &Database) -> Result<Self, DataError> {
+        let mut con = db.readers.get()?;
+        AnsiTransactionManager::begin_transaction_sql(&mut *con, "BEGIN DEFERRED")?;
+
+        Ok(OpenTransaction {
+            db: Database {
+                transaction: Some(Arc::new(Mutex::new(con))),
+                ..db.clone()
+            },
+            writing: false,
+            committed: false,
+        })
+    }
+
+    fn commit(mut self) -> Result<(), DataError> {
+        AnsiTransactionManager::commit_transaction(&mut *self.db.get()?)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for OpenTransaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            if let Ok(mut con) = self.db.get() {
+                match AnsiTransactionManager::rollback_transaction(&mut *con) {
+                    // A failed commit may have rolled back already
+                    Ok(()) | Err(diesel::result::Error::NotInTransaction) => (),
+                    Err(err) => error!("Failed to roll back a transaction: {}", err),
+                }
+            }
+        }
+
+        if self.writing {
+            let mut writing = self
+                .db
+                .writing_thread
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            *writing = None;
+        }
+    }
+}
+
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("writer", &self.writer)
+            .field("readers", &self.readers)
+            .field("transaction", &self.transaction.is_some())
+            .finish()
+    }
+}
+
+/// A connection of a `Database`.
+pub(crate) enum Conn<'a> {
+    Pooled(Pooled),
+    Transaction(MutexGuard<'a, Pooled>),
+}
+
+impl Deref for Conn<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        match self {
+            Conn::Pooled(con) => con,
+            Conn::Transaction(con) => con,
+        }
+    }
+}
+
+impl DerefMut for Conn<'_> {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        match self {
+            Conn::Pooled(con) => con,
+            Conn::Transaction(con) => con,
+        }
+    }
+}
+
+/// The settings of every connection of a `Database`.
+#[derive(Debug, Clone, Copy)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, con: &mut SqliteConnection) -> Result<(), r2d2::Error> {
+        con.batch_execute(&format!(
+            "PRAGMA busy_timeout = {}; PRAGMA synchronous = NORMAL;",
+            BUSY_TIMEOUT.as_millis()
+        ))
+        .map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// Get the global `Database`.
+pub(crate) fn connection() -> Database {
+    Database::global()
 }
 
 fn run_migration_on(
@@ -86,9 +347,163 @@ fn run_migration_on(
 /// Reset the database into a clean state.
 // Test share a Temp file db.
 pub fn truncate_db() -> Result<(), DataError> {
-    use diesel::connection::SimpleConnection;
     let db = connection();
     let mut con = db.get()?;
-    con.batch_execute("DELETE FROM episodes; DELETE FROM shows; DELETE FROM source")?;
+    con.batch_execute(
+        "DELETE FROM download_queue; DELETE FROM episode_chapters; DELETE FROM episodes; \
+         DELETE FROM play_queue; DELETE FROM show_settings; DELETE FROM shows; DELETE FROM source",
+    )?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_episode_identity_migration() {
+        use crate::schema::episodes::dsl::*;
+        use crate::schema::play_queue;
+
+        let mut con = SqliteConnection::establish(":memory:").unwrap();
+        for migration in con.pending_migrations(MIGRATIONS).unwrap() {
+            if migration.name().to_string().ends_with("_episode_identity") {
+                break;
+            }
+            con.run_migration(&migration).unwrap();
+        }
+
+        // The same episode indexed twice after being retitled, and then queued
+        con.batch_execute(
+            "INSERT INTO episodes (rowid, title, uri, guid, play_position, show_id) VALUES \
+             (1, 'Pilot', 'https://example.org/1.mp3', 'ep-1', 0, 1), \
+             (2, 'Pilot (Remastered)', 'https://example.org/1.mp3', 'ep-1', 60, 1), \
+             (3, 'No Guid', 'https://example.org/2.mp3', NULL, 0, 1), \
+             (4, 'Nothing', NULL, '', 0, 1), \
+             (7, 'Pilot', 'https://example.org/other/1.mp3', 'ep-1', 0, 2); \
+             INSERT INTO play_queue (episode_id, position) VALUES (1, 0), (7, 1);",
+        )
+        .unwrap();
+        con.run_pending_migrations(MIGRATIONS).unwrap();
+
+        let rows = episodes
+            .select((rowid, title, identity))
+            .order(rowid)
+            .load::<(i32, String, String)>(&mut con)
+            .unwrap();
+        let expected = [
+            (2, "Pilot (Remastered)", "ep-1"),
+            (3, "No Guid", "https://example.org/2.mp3"),
+            (4, "Nothing", "Nothing"),
+            (7, "Pilot", "ep-1"),
+        ];
+        assert_eq!(rows.len(), expected.len());
+        for (row, (id, t, key)) in rows.iter().zip(expected) {
+            assert_eq!((row.0, row.1.as_str(), row.2.as_str()), (id, t, key));
+        }
+
+        let queue = play_queue::table
+            .select(play_queue::episode_id)
+            .load::<i32>(&mut con)
+            .unwrap();
+        assert_eq!(queue, vec![7]);
+    }
+
+    #[test]
+    fn test_open_databases() -> anyhow::Result<()> {
+        use crate::models::Save;
+        use crate::utils::get_feed;
+        use crate::EpisodeWidgetModel;
+
+        let dir = tempfile::TempDir::new()?;
+        let first = Database::open(dir.path().join("first.db"))?;
+        let second = Database::open(dir.path().join("second.db"))?;
+
+        let feed = get_feed("tests/feeds/2018-01-20-Intercepted.xml", 42);
+        let summary = feed.clone().index_in(&first)?;
+        assert_eq!(summary.inserted().len(), 43);
+        assert_eq!(first.get_podcasts()?.len(), 1);
+        assert!(second.get_podcasts()?.is_empty());
+
+        let id = summary.inserted()[0];
+        let mut ep: EpisodeWidgetModel = first.get_episode_from_rowid(id)?.into();
+        ep.set_local_uri(Some("/tmp/episode.mp3"));
+        ep.save_in(&first)?;
+        let local_uri = first.get_episode_local_uri_from_id(id)?;
+        assert_eq!(local_uri.as_deref(), Some("/tmp/episode.mp3"));
+
+        // Indexing into one database leaves the other alone
+        feed.index_in(&second)?;
+        assert_eq!(second.get_episode_local_uri_from_id(id)?, None);
+
+        // The data survives reopening the file
+        drop(first);
+        let first = Database::open(dir.path().join("first.db"))?;
+        assert_eq!(first.get_episodes()?.len(), 43);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction() -> anyhow::Result<()> {
+        use crate::utils::get_feed;
+
+        let dir = tempfile::TempDir::new()?;
+        let db = Database::open(dir.path().join("podcasts.db"))?;
+        let feed = get_feed("tests/feeds/2018-01-20-Intercepted.xml", 42);
+
+        // Nothing is written when the transaction fails
+        let res = db.transaction(|tx| {
+            feed.clone().index_in(tx)?;
+            assert_eq!(tx.get_podcasts()?.len(), 1);
+            Err::<(), _>(DataError::Bail("rollback".into()))
+        });
+        assert!(res.is_err());
+        assert!(db.get_podcasts()?.is_empty());
+        assert!(db.get_episodes()?.is_empty());
+
+        db.transaction(|tx| {
+            feed.index_in(tx)?;
+            // Readers don't wait for the writer, and only see committed data
+            assert!(db.get_podcasts()?.is_empty());
+            assert_eq!(tx.get_episodes()?.len(), 43);
+            Ok(())
+        })?;
+        assert_eq!(db.get_podcasts()?.len(), 1);
+        assert_eq!(db.get_episodes()?.len(), 43);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_misuse() -> anyhow::Result<()> {
+        use crate::utils::get_feed;
+
+        let dir = tempfile::TempDir::new()?;
+        let db = Database::open(dir.path().join("podcasts.db"))?;
+        let feed = get_feed("tests/feeds/2018-01-20-Intercepted.xml", 42);
+
+        // A panic rolls back, and leaves the writer usable
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            db.transaction(|tx| -> Result<(), DataError> {
+                feed.clone().index_in(tx)?;
+                panic!("indexing blew up");
+            })
+        }));
+        assert!(res.is_err());
+        assert!(db.get_podcasts()?.is_empty());
+
+        // Writing outside of the transaction, or with two of its connections
+        // at once, fails instead of waiting for it
+        let res = db.transaction(|tx| {
+            assert!(db.get().is_err());
+            let _con = tx.get()?;
+            assert!(tx.get().is_err());
+            assert!(db.transaction(|_| Ok(())).is_err());
+            Ok(())
+        });
+        assert!(res.is_ok());
+
+        feed.index_in(&db)?;
+        assert_eq!(db.get_podcasts()?.len(), 1);
+        Ok(())
+    }
+}
@@ -18,6 +18,9 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 //! Random CRUD helper functions.
+//!
+//! They are methods of `Database`, and the free functions of this module
+//! run them on the global `Database`.
 
 use chrono::prelude::*;
 use diesel::prelude::*;
@@ -25,108 +28,191 @@ use diesel::prelude::*;
 use diesel::dsl::exists;
 use diesel::select;
 
-use crate::database::connection;
+use crate::database::{connection, Database};
 use crate::errors::DataError;
 use crate::models::*;
 
-pub fn get_sources() -> Result<Vec<Source>, DataError> {
-    use crate::schema::source::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+impl Database {
+    pub fn get_sources(&self) -> Result<Vec<Source>, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
 
-    source
-        .order((http_etag.asc(), last_modified.asc()))
-        .load::<Source>(&mut con)
-        .map_err(From::from)
-}
+        source
+            .order((http_etag.asc(), last_modified.asc()))
+            .load::<Source>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_podcasts() -> Result<Vec<Show>, DataError> {
-    use crate::schema::shows::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Get the sources that are not backing off from failed refreshes at `now`.
+    pub fn get_due_sources(&self, now: i32) -> Result<Vec<Source>, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
 
-    shows
-        .order(title.asc())
-        .load::<Show>(&mut con)
-        .map_err(From::from)
-}
+        source
+            .filter(next_attempt.le(now))
+            .order((http_etag.asc(), last_modified.asc()))
+            .load::<Source>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_podcasts_filter(filter_ids: &[i32]) -> Result<Vec<Show>, DataError> {
-    use crate::schema::shows::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Get the sources whose last refresh failed, the longest failing first.
+    pub fn get_failing_sources(&self) -> Result<Vec<Source>, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
 
-    shows
-        .order(title.asc())
-        .filter(id.ne_all(filter_ids))
-        .load::<Show>(&mut con)
-        .map_err(From::from)
-}
+        source
+            .filter(failures.gt(0))
+            .order((failures.desc(), id.asc()))
+            .load::<Source>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_episodes() -> Result<Vec<Episode>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Get the `FetchStats` of every source.
+    pub fn get_fetch_stats(&self) -> Result<Vec<FetchStats>, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
 
-    episodes
-        .order(epoch.desc())
-        .load::<Episode>(&mut con)
-        .map_err(From::from)
-}
+        source
+            .select((id, uri, fetches, not_modified, bytes_fetched, last_fetch_ms))
+            .order(id.asc())
+            .load::<FetchStats>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub(crate) fn get_downloaded_episodes() -> Result<Vec<EpisodeCleanerModel>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Get the `FetchStats` of the source with the given id.
+    pub fn get_source_fetch_stats(&self, id_: i32) -> Result<FetchStats, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
 
-    episodes
-        .select((rowid, local_uri, played))
-        .filter(local_uri.is_not_null())
-        .load::<EpisodeCleanerModel>(&mut con)
-        .map_err(From::from)
-}
+        source
+            .select((id, uri, fetches, not_modified, bytes_fetched, last_fetch_ms))
+            .filter(id.eq(id_))
+            .get_result::<FetchStats>(&mut *con)
+            .map_err(From::from)
+    }
 
-// pub(crate) fn get_played_episodes() -> Result<Vec<Episode>, DataError> {
-//     use schema::episodes::dsl::*;
+    pub fn get_podcasts(&self) -> Result<Vec<Show>, DataError> {
+        use crate::schema::shows::dsl::*;
+        let mut con = self.read()?;
 
-//     let db = connection();
-//     let mut con = db.get()?;
-//     episodes
-//         .filter(played.is_not_null())
-//         .load::<Episode>(&mut con)
-//         .map_err(From::from)
-// }
+        shows
+            .order(title.asc())
+            .load::<Show>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub(crate) fn get_played_cleaner_episodes() -> Result<Vec<EpisodeCleanerModel>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    pub fn get_podcasts_filter(&self, filter_ids: &[i32]) -> Result<Vec<Show>, DataError> {
+        use crate::schema::shows::dsl::*;
+        let mut con = self.read()?;
 
-    episodes
-        .select((rowid, local_uri, played))
-        .filter(played.is_not_null())
-        .load::<EpisodeCleanerModel>(&mut con)
-        .map_err(From::from)
-}
+        shows
+            .order(title.asc())
+            .filter(id.ne_all(filter_ids))
+            .load::<Show>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_episode_from_rowid(ep_id: i32) -> Result<Episode, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    pub fn get_episodes(&self) -> Result<Vec<Episode>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
 
-    episodes
-        .filter(rowid.eq(ep_id))
-        .get_result::<Episode>(&mut con)
-        .map_err(From::from)
-}
+        episodes
+            .order(epoch.desc())
+            .load::<Episode>(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_episode_widget_from_rowid(ep_id: i32) -> Result<EpisodeWidgetModel, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    pub(crate) fn get_downloaded_episodes(&self) -> Result<Vec<EpisodeCleanerModel>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select((rowid, local_uri, played))
+            .filter(local_uri.is_not_null())
+            .load::<EpisodeCleanerModel>(&mut *con)
+            .map_err(From::from)
+    }
+
+    // pub(crate) fn get_played_episodes() -> Result<Vec<Episode>, DataError> {
+    //     use schema::episodes::dsl::*;
+
+    //     let db = connection();
+    //     let mut con = db.get()?;
+    //     episodes
+    //         .filter(played.is_not_null())
+    //         .load::<Episode>(&mut *con)
+    //         .map_err(From::from)
+    // }
+
+    /// Get the played episodes, along with the id of their show.
+    pub(crate) fn get_played_cleaner_episodes(
+        &self,
+    ) -> Result<Vec<(i32, EpisodeCleanerModel)>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select((show_id, (rowid, local_uri, played)))
+            .filter(played.is_not_null())
+            .load::<(i32, EpisodeCleanerModel)>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_episode_from_rowid(&self, ep_id: i32) -> Result<Episode, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .filter(rowid.eq(ep_id))
+            .get_result::<Episode>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_episode_widget_from_rowid(
+        &self,
+        ep_id: i32,
+    ) -> Result<EpisodeWidgetModel, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select((
+                rowid,
+                title,
+                uri,
+                local_uri,
+                epoch,
+                length,
+                duration,
+                played,
+                play_position,
+                show_id,
+                removed_upstream,
+            ))
+            .filter(rowid.eq(ep_id))
+            .get_result::<EpisodeWidgetModel>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_episode_local_uri_from_id(&self, ep_id: i32) -> Result<Option<String>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .filter(rowid.eq(ep_id))
+            .select(local_uri)
+            .get_result::<Option<String>>(&mut *con)
+            .map_err(From::from)
+    }
 
-    episodes
-        .select((
+    pub fn get_episodes_widgets_filter_limit(
+        &self,
+        filter_ids: &[i32],
+        limit: u32,
+    ) -> Result<Vec<EpisodeWidgetModel>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+        let columns = (
             rowid,
             title,
             uri,
@@ -137,246 +223,807 @@ pub fn get_episode_widget_from_rowid(ep_id: i32) -> Result<EpisodeWidgetModel, D
             played,
             play_position,
             show_id,
+            removed_upstream,
+        );
+
+        episodes
+            .select(columns)
+            .order(epoch.desc())
+            .filter(show_id.ne_all(filter_ids))
+            .limit(i64::from(limit))
+            .load::<EpisodeWidgetModel>(&mut *con)
+            .map_err(From::from)
+    }
+
+    /// Get the chapters of an episode, ordered by their starting time.
+    pub fn get_episode_chapters(&self, ep_id: i32) -> Result<Vec<Chapter>, DataError> {
+        use crate::schema::episode_chapters::dsl::*;
+        let mut con = self.read()?;
+
+        episode_chapters
+            .filter(episode_id.eq(ep_id))
+            .order(start_time.asc())
+            .load::<Chapter>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_podcast_from_id(&self, pid: i32) -> Result<Show, DataError> {
+        use crate::schema::shows::dsl::*;
+        let mut con = self.read()?;
+
+        shows
+            .filter(id.eq(pid))
+            .get_result::<Show>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_podcast_cover_from_id(&self, pid: i32) -> Result<ShowCoverModel, DataError> {
+        use crate::schema::shows::dsl::*;
+        let mut con = self.read()?;
+
+        shows
+            .select((id, title, image_uri, image_uri_hash, image_cached))
+            .filter(id.eq(pid))
+            .get_result::<ShowCoverModel>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_pd_episodes(&self, parent: &Show) -> Result<Vec<Episode>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        Episode::belonging_to(parent)
+            .order(epoch.desc())
+            .load::<Episode>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_pd_episodes_count(&self, parent: &Show) -> Result<i64, DataError> {
+        let mut con = self.read()?;
+
+        Episode::belonging_to(parent)
+            .count()
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_pd_episodeswidgets(
+        &self,
+        parent: &Show,
+    ) -> Result<Vec<EpisodeWidgetModel>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+        let columns = (
+            rowid,
+            title,
+            uri,
+            local_uri,
+            epoch,
+            length,
+            duration,
+            played,
+            play_position,
+            show_id,
+            removed_upstream,
+        );
+
+        episodes
+            .select(columns)
+            .filter(show_id.eq(parent.id()))
+            .order(epoch.desc())
+            .load::<EpisodeWidgetModel>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_pd_unplayed_episodes(&self, parent: &Show) -> Result<Vec<Episode>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        Episode::belonging_to(parent)
+            .filter(played.is_null())
+            .order(epoch.desc())
+            .load::<Episode>(&mut *con)
+            .map_err(From::from)
+    }
+
+    // pub(crate) fn get_pd_episodes_limit(parent: &Show, limit: u32) ->
+    // Result<Vec<Episode>, DataError> {     use schema::episodes::dsl::*;
+
+    //     let db = connection();
+    //     let mut con = db.get()?;
+
+    //     Episode::belonging_to(parent)
+    //         .order(epoch.desc())
+    //         .limit(i64::from(limit))
+    //         .load::<Episode>(&mut *con)
+    //         .map_err(From::from)
+    // }
+
+    pub fn get_source_from_uri(&self, uri_: &str) -> Result<Source, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
+
+        source
+            .filter(uri.eq(uri_))
+            .get_result::<Source>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_source_from_id(&self, id_: i32) -> Result<Source, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
+
+        source
+            .filter(id.eq(id_))
+            .get_result::<Source>(&mut *con)
+            .map_err(From::from)
+    }
+
+    /// Get the `Source` a WebSub notification about `topic` is about, matching
+    /// either the self url the feed declared or the url it is fetched from.
+    pub fn get_source_from_topic(&self, topic: &str) -> Result<Source, DataError> {
+        use crate::schema::source::dsl::*;
+        let mut con = self.read()?;
+
+        source
+            .filter(self_url.eq(topic).or(uri.eq(topic)))
+            .first::<Source>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_podcast_from_source_id(&self, sid: i32) -> Result<Show, DataError> {
+        use crate::schema::shows::dsl::*;
+        let mut con = self.read()?;
+
+        shows
+            .filter(source_id.eq(sid))
+            .get_result::<Show>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_episode_from_pk(&self, identity_: &str, pid: i32) -> Result<Episode, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .filter(identity.eq(identity_))
+            .filter(show_id.eq(pid))
+            .get_result::<Episode>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub(crate) fn get_episode_minimal_from_pk(
+        &self,
+        identity_: &str,
+        pid: i32,
+    ) -> Result<EpisodeMinimal, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select((
+                rowid,
+                title,
+                uri,
+                epoch,
+                length,
+                duration,
+                play_position,
+                guid,
+                show_id,
+                chapters_uri,
+                transcript_uri,
+                transcript_type,
+            ))
+            .filter(identity.eq(identity_))
+            .filter(show_id.eq(pid))
+            .get_result::<EpisodeMinimal>(&mut *con)
+            .map_err(From::from)
+    }
+
+    /// Get the `rowid`s of the episodes of a show with the given identities.
+    pub(crate) fn get_episode_rowids(
+        &self,
+        pid: i32,
+        identities: &[&str],
+    ) -> Result<Vec<i32>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select(rowid)
+            .filter(show_id.eq(pid))
+            .filter(identity.eq_any(identities))
+            .load::<i32>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub fn get_episodes_minimal_from_rowids(
+        &self,
+        ids: &[i32],
+    ) -> Result<Vec<EpisodeMinimal>, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select((
+                rowid,
+                title,
+                uri,
+                epoch,
+                length,
+                duration,
+                play_position,
+                guid,
+                show_id,
+                chapters_uri,
+                transcript_uri,
+                transcript_type,
+            ))
+            .filter(rowid.eq_any(ids))
+            .load::<EpisodeMinimal>(&mut *con)
+            .map_err(From::from)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_episode_cleaner_from_pk(
+        &self,
+        identity_: &str,
+        pid: i32,
+    ) -> Result<EpisodeCleanerModel, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.read()?;
+
+        episodes
+            .select((rowid, local_uri, played))
+            .filter(identity.eq(identity_))
+            .filter(show_id.eq(pid))
+            .get_result::<EpisodeCleanerModel>(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub(crate) fn remove_feed(&self, pd: &Show) -> Result<(), DataError> {
+        let mut con = self.get()?;
+
+        con.transaction(|conn| {
+            delete_source(conn, pd.source_id())?;
+            delete_podcast(conn, pd.id())?;
+            delete_podcast_episode_chapters(conn, pd.id())?;
+            delete_podcast_downloads(conn, pd.id())?;
+            delete_podcast_queue(conn, pd.id())?;
+            delete_show_settings(conn, pd.id())?;
+            delete_podcast_episodes(conn, pd.id())?;
+            info!("Feed removed from the Database.");
+            Ok(())
+        })
+    }
+
+    /// Remove a `Source` that never got indexed into a show.
+    ///
+    /// Use `utils::delete_show` for the sources that have one.
+    pub fn remove_source(&self, source_id: i32) -> Result<(), DataError> {
+        let mut con = self.get()?;
+
+        delete_source(&mut con, source_id)?;
+        Ok(())
+    }
+
+    pub fn source_exists(&self, url: &str) -> Result<bool, DataError> {
+        use crate::schema::source::dsl::*;
+
+        let mut con = self.read()?;
+
+        select(exists(source.filter(uri.eq(url))))
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub(crate) fn podcast_exists(&self, source_id_: i32) -> Result<bool, DataError> {
+        use crate::schema::shows::dsl::*;
+
+        let mut con = self.read()?;
+
+        select(exists(shows.filter(source_id.eq(source_id_))))
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
+
+    pub(crate) fn episode_exists(&self, identity_: &str, show_id_: i32) -> Result<bool, DataError> {
+        use crate::schema::episodes::dsl::*;
+
+        let mut con = self.read()?;
+
+        select(exists(
+            episodes
+                .filter(show_id.eq(show_id_))
+                .filter(identity.eq(identity_)),
         ))
-        .filter(rowid.eq(ep_id))
-        .get_result::<EpisodeWidgetModel>(&mut con)
+        .get_result(&mut *con)
         .map_err(From::from)
-}
+    }
 
-pub fn get_episode_local_uri_from_id(ep_id: i32) -> Result<Option<String>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Check if the `episodes table contains any rows
+    ///
+    /// Return true if `episodes` table is populated.
+    pub fn is_episodes_populated(&self, filter_show_ids: &[i32]) -> Result<bool, DataError> {
+        use crate::schema::episodes::dsl::*;
 
-    episodes
-        .filter(rowid.eq(ep_id))
-        .select(local_uri)
-        .get_result::<Option<String>>(&mut con)
-        .map_err(From::from)
-}
+        let mut con = self.read()?;
 
-pub fn get_episodes_widgets_filter_limit(
-    filter_ids: &[i32],
-    limit: u32,
-) -> Result<Vec<EpisodeWidgetModel>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
-    let columns = (
-        rowid,
-        title,
-        uri,
-        local_uri,
-        epoch,
-        length,
-        duration,
-        played,
-        play_position,
-        show_id,
-    );
-
-    episodes
-        .select(columns)
-        .order(epoch.desc())
-        .filter(show_id.ne_all(filter_ids))
-        .limit(i64::from(limit))
-        .load::<EpisodeWidgetModel>(&mut con)
-        .map_err(From::from)
-}
+        select(exists(episodes.filter(show_id.ne_all(filter_show_ids))))
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_podcast_from_id(pid: i32) -> Result<Show, DataError> {
-    use crate::schema::shows::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Check if the `shows` table contains any rows
+    ///
+    /// Return true if `shows` table is populated.
+    pub fn is_podcasts_populated(&self, filter_ids: &[i32]) -> Result<bool, DataError> {
+        use crate::schema::shows::dsl::*;
 
-    shows
-        .filter(id.eq(pid))
-        .get_result::<Show>(&mut con)
-        .map_err(From::from)
-}
+        let mut con = self.read()?;
 
-pub fn get_podcast_cover_from_id(pid: i32) -> Result<ShowCoverModel, DataError> {
-    use crate::schema::shows::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+        select(exists(shows.filter(id.ne_all(filter_ids))))
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
 
-    shows
-        .select((id, title, image_uri, image_uri_hash, image_cached))
-        .filter(id.eq(pid))
-        .get_result::<ShowCoverModel>(&mut con)
-        .map_err(From::from)
-}
+    /// Check if the `source` table contains any rows
+    ///
+    /// Return true if `source` table is populated.
+    pub fn is_source_populated(&self, filter_ids: &[i32]) -> Result<bool, DataError> {
+        use crate::schema::source::dsl::*;
 
-pub fn get_pd_episodes(parent: &Show) -> Result<Vec<Episode>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+        let mut con = self.read()?;
 
-    Episode::belonging_to(parent)
-        .order(epoch.desc())
-        .load::<Episode>(&mut con)
-        .map_err(From::from)
-}
+        select(exists(source.filter(id.ne_all(filter_ids))))
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
 
-pub fn get_pd_episodes_count(parent: &Show) -> Result<i64, DataError> {
-    let db = connection();
-    let mut con = db.get()?;
+    pub(crate) fn index_new_episodes(&self, eps: &[NewEpisode]) -> Result<(), DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.get()?;
 
-    Episode::belonging_to(parent)
-        .count()
-        .get_result(&mut con)
+        diesel::insert_into(episodes)
+            .values(eps)
+            .execute(&mut *con)
+            .map_err(From::from)
+            .map(|_| ())
+    }
+
+    /// Replace the stored chapters of an episode.
+    pub(crate) fn replace_episode_chapters(
+        &self,
+        ep_id: i32,
+        chapters: &[NewChapter],
+    ) -> Result<(), DataError> {
+        use crate::schema::episode_chapters::dsl::*;
+        let mut con = self.get()?;
+
+        con.transaction(|conn| {
+            diesel::delete(episode_chapters.filter(episode_id.eq(ep_id))).execute(conn)?;
+            diesel::insert_into(episode_chapters)
+                .values(chapters)
+                .execute(conn)
+                .map(|_| ())
+        })
         .map_err(From::from)
-}
+    }
 
-pub fn get_pd_episodeswidgets(parent: &Show) -> Result<Vec<EpisodeWidgetModel>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
-    let columns = (
-        rowid,
-        title,
-        uri,
-        local_uri,
-        epoch,
-        length,
-        duration,
-        played,
-        play_position,
-        show_id,
-    );
-
-    episodes
-        .select(columns)
-        .filter(show_id.eq(parent.id()))
-        .order(epoch.desc())
-        .load::<EpisodeWidgetModel>(&mut con)
+    /// Add an episode to the download queue.
+    ///
+    /// Downloads that are queued or running already are left as they are.
+    /// Paused, failed and finished ones are reset, and queued again.
+    pub fn enqueue_download(&self, ep_id: i32, dir: &str) -> Result<QueuedDownload, DataError> {
+        use crate::schema::download_queue::dsl::*;
+        let mut con = self.get()?;
+
+        con.transaction(|conn| {
+            let queued = download_queue
+                .filter(episode_id.eq(ep_id))
+                .get_result::<QueuedDownload>(conn)
+                .optional()?;
+            if let Some(queued) = queued {
+                if matches!(
+                    queued.state(),
+                    DownloadState::Queued | DownloadState::Running
+                ) {
+                    return Ok(queued);
+                }
+            }
+
+            diesel::replace_into(download_queue)
+                .values(NewQueuedDownload::new(ep_id, dir))
+                .execute(conn)?;
+            download_queue
+                .filter(episode_id.eq(ep_id))
+                .get_result::<QueuedDownload>(conn)
+        })
         .map_err(From::from)
-}
+    }
 
-pub fn get_pd_unplayed_episodes(parent: &Show) -> Result<Vec<Episode>, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    pub fn get_queued_download(&self, ep_id: i32) -> Result<QueuedDownload, DataError> {
+        use crate::schema::download_queue::dsl::*;
+        let mut con = self.read()?;
 
-    Episode::belonging_to(parent)
-        .filter(played.is_null())
-        .order(epoch.desc())
-        .load::<Episode>(&mut con)
-        .map_err(From::from)
-}
+        download_queue
+            .filter(episode_id.eq(ep_id))
+            .get_result::<QueuedDownload>(&mut *con)
+            .map_err(From::from)
+    }
 
-// pub(crate) fn get_pd_episodes_limit(parent: &Show, limit: u32) ->
-// Result<Vec<Episode>, DataError> {     use schema::episodes::dsl::*;
+    /// Get the whole download queue, in the order it was queued.
+    pub fn get_download_queue(&self) -> Result<Vec<QueuedDownload>, DataError> {
+        use crate::schema::download_queue::dsl::*;
+        let mut con = self.read()?;
+
+        download_queue
+            .order(queued_at.asc())
+            .load::<QueuedDownload>(&mut *con)
+            .map_err(From::from)
+    }
 
-//     let db = connection();
-//     let mut con = db.get()?;
+    /// Get up to `limit` queued downloads that are ready to start at `now`,
+    /// oldest first.
+    pub fn get_due_downloads(
+        &self,
+        now: i32,
+        limit: i64,
+    ) -> Result<Vec<QueuedDownload>, DataError> {
+        use crate::schema::download_queue::dsl::*;
+        let mut con = self.read()?;
+
+        download_queue
+            .filter(state.eq(DownloadState::Queued.to_string()))
+            .filter(next_attempt.le(now))
+            .order(queued_at.asc())
+            .limit(limit)
+            .load::<QueuedDownload>(&mut *con)
+            .map_err(From::from)
+    }
 
-//     Episode::belonging_to(parent)
-//         .order(epoch.desc())
-//         .limit(i64::from(limit))
-//         .load::<Episode>(&mut con)
-//         .map_err(From::from)
-// }
+    pub fn count_running_downloads(&self) -> Result<i64, DataError> {
+        use crate::schema::download_queue::dsl::*;
+        let mut con = self.read()?;
 
-pub fn get_source_from_uri(uri_: &str) -> Result<Source, DataError> {
-    use crate::schema::source::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+        download_queue
+            .filter(state.eq(DownloadState::Running.to_string()))
+            .count()
+            .get_result(&mut *con)
+            .map_err(From::from)
+    }
 
-    source
-        .filter(uri.eq(uri_))
-        .get_result::<Source>(&mut con)
+    /// Bring the download queue back after a restart.
+    ///
+    /// Downloads that were interrupted are queued again, and finished ones
+    /// are dropped. Returns the downloads that are still queued.
+    pub fn restore_download_queue(&self) -> Result<Vec<QueuedDownload>, DataError> {
+        use crate::schema::download_queue::dsl::*;
+        let mut con = self.get()?;
+
+        con.transaction(|conn| {
+            diesel::delete(download_queue.filter(state.eq(DownloadState::Done.to_string())))
+                .execute(conn)?;
+            diesel::update(download_queue.filter(state.eq(DownloadState::Running.to_string())))
+                .set(state.eq(DownloadState::Queued.to_string()))
+                .execute(conn)?;
+            download_queue
+                .filter(state.eq(DownloadState::Queued.to_string()))
+                .order(queued_at.asc())
+                .load::<QueuedDownload>(conn)
+        })
         .map_err(From::from)
-}
+    }
 
-pub fn get_source_from_id(id_: i32) -> Result<Source, DataError> {
-    use crate::schema::source::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Get the `ShowSettings` of a show.
+    ///
+    /// Shows that were never configured get the default settings.
+    pub fn get_show_settings(&self, pid: i32) -> Result<ShowSettings, DataError> {
+        use crate::schema::show_settings::dsl::*;
+        let mut con = self.read()?;
+
+        let settings = show_settings
+            .filter(show_id.eq(pid))
+            .get_result::<ShowSettings>(&mut *con)
+            .optional()?;
+        Ok(settings.unwrap_or_else(|| ShowSettings::new(pid)))
+    }
 
-    source
-        .filter(id.eq(id_))
-        .get_result::<Source>(&mut con)
-        .map_err(From::from)
-}
+    /// Get the settings of all the shows that were configured.
+    pub(crate) fn get_all_show_settings(&self) -> Result<Vec<ShowSettings>, DataError> {
+        use crate::schema::show_settings::dsl::*;
+        let mut con = self.read()?;
 
-pub fn get_podcast_from_source_id(sid: i32) -> Result<Show, DataError> {
-    use crate::schema::shows::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+        show_settings
+            .load::<ShowSettings>(&mut *con)
+            .map_err(From::from)
+    }
 
-    shows
-        .filter(source_id.eq(sid))
-        .get_result::<Show>(&mut con)
-        .map_err(From::from)
-}
+    /// Get the rowids of the episodes in the Up Next queue, in playing order.
+    pub fn get_play_queue(&self) -> Result<Vec<i32>, DataError> {
+        use crate::schema::play_queue::dsl::*;
+        let mut con = self.read()?;
 
-pub fn get_episode_from_pk(title_: &str, pid: i32) -> Result<Episode, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+        play_queue
+            .select(episode_id)
+            .order(position.asc())
+            .load::<i32>(&mut *con)
+            .map_err(From::from)
+    }
 
-    episodes
-        .filter(title.eq(title_))
-        .filter(show_id.eq(pid))
-        .get_result::<Episode>(&mut con)
-        .map_err(From::from)
-}
+    /// Add an episode to the end of the Up Next queue.
+    ///
+    /// Episodes that are already queued keep their place.
+    pub fn queue_episode(&self, ep_id: i32) -> Result<Vec<i32>, DataError> {
+        self.update_play_queue(|queue| {
+            if !queue.contains(&ep_id) {
+                queue.push(ep_id);
+            }
+        })
+    }
 
-pub(crate) fn get_episode_minimal_from_pk(
-    title_: &str,
-    pid: i32,
-) -> Result<EpisodeMinimal, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
+    /// Put an episode at the front of the Up Next queue.
+    pub fn queue_episode_next(&self, ep_id: i32) -> Result<Vec<i32>, DataError> {
+        self.update_play_queue(|queue| {
+            queue.retain(|id| *id != ep_id);
+            queue.insert(0, ep_id);
+        })
+    }
 
-    episodes
-        .select((
-            rowid,
-            title,
-            uri,
-            epoch,
-            length,
-            duration,
-            play_position,
-            guid,
-            show_id,
-        ))
-        .filter(title.eq(title_))
-        .filter(show_id.eq(pid))
-        .get_result::<EpisodeMinimal>(&mut con)
-        .map_err(From::from)
-}
+    /// Remove an episode from the Up Next queue.
+    pub fn dequeue_episode(&self, ep_id: i32) -> Result<Vec<i32>, DataError> {
+        self.update_play_queue(|queue| queue.retain(|id| *id != ep_id))
+    }
 
-#[cfg(test)]
-pub(crate) fn get_episode_cleaner_from_pk(
-    title_: &str,
-    pid: i32,
-) -> Result<EpisodeCleanerModel, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
-
-    episodes
-        .select((rowid, local_uri, played))
-        .filter(title.eq(title_))
-        .filter(show_id.eq(pid))
-        .get_result::<EpisodeCleanerModel>(&mut con)
+    /// Move a queued episode to `index`, clamped to the end of the queue.
+    ///
+    /// Episodes that are not queued are ignored.
+    pub fn move_queued_episode(&self, ep_id: i32, index: usize) -> Result<Vec<i32>, DataError> {
+        self.update_play_queue(|queue| {
+            if let Some(current) = queue.iter().position(|id| *id == ep_id) {
+                queue.remove(current);
+                queue.insert(index.min(queue.len()), ep_id);
+            }
+        })
+    }
+
+    /// Rewrite the Up Next queue after `f` reorders it, and return the new order.
+    fn update_play_queue<F>(&self, f: F) -> Result<Vec<i32>, DataError>
+    where
+        F: FnOnce(&mut Vec<i32>),
+    {
+        use crate::schema::play_queue::dsl::*;
+        let mut con = self.get()?;
+
+        con.transaction(|conn| {
+            let mut queue = play_queue
+                .select(episode_id)
+                .order(position.asc())
+                .load::<i32>(conn)?;
+            f(&mut queue);
+
+            let rows: Vec<_> = queue
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (episode_id.eq(id), position.eq(i as i32)))
+                .collect();
+            diesel::delete(play_queue).execute(conn)?;
+            diesel::insert_into(play_queue)
+                .values(&rows)
+                .execute(conn)?;
+            Ok(queue)
+        })
+    }
+
+    /// Search the titles and descriptions of shows and episodes.
+    ///
+    /// Every word of `input` has to match, at least as the start of a word.
+    /// Up to `limit` shows and `limit` episodes are returned, best matches first.
+    pub fn search(&self, input: &str, limit: u32) -> Result<SearchResults, DataError> {
+        use crate::schema::{episodes, shows};
+
+        let query = match fts_query(input) {
+            Some(query) => query,
+            None => return Ok(SearchResults::default()),
+        };
+        let mut con = self.read()?;
+        let limit = i64::from(limit);
+
+        let show_matches = search_table(&mut con, "shows_search", &query, limit)?;
+        let ids: Vec<i32> = show_matches.iter().map(|m| m.id).collect();
+        let found = shows::table
+            .filter(shows::id.eq_any(&ids))
+            .load::<Show>(&mut *con)?;
+        let shows = show_matches
+            .iter()
+            .filter_map(|m| {
+                let show = found.iter().find(|s| Show::id(s) == m.id)?;
+                Some(SearchHit::new(show.clone(), &m.snippet))
+            })
+            .collect();
+
+        let episode_matches = search_table(&mut con, "episodes_search", &query, limit)?;
+        let ids: Vec<i32> = episode_matches.iter().map(|m| m.id).collect();
+        let found = episodes::table
+            .select((
+                episodes::rowid,
+                episodes::title,
+                episodes::uri,
+                episodes::local_uri,
+                episodes::epoch,
+                episodes::length,
+                episodes::duration,
+                episodes::played,
+                episodes::play_position,
+                episodes::show_id,
+                episodes::removed_upstream,
+            ))
+            .filter(episodes::rowid.eq_any(&ids))
+            .load::<EpisodeWidgetModel>(&mut *con)?;
+        let episodes = episode_matches
+            .iter()
+            .filter_map(|m| {
+                let episode = found.iter().find(|e| e.rowid() == m.id)?;
+                Some(SearchHit::new(episode.clone(), &m.snippet))
+            })
+            .collect();
+
+        Ok(SearchResults { shows, episodes })
+    }
+
+    pub fn update_none_to_played_now(&self, parent: &Show) -> Result<usize, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.get()?;
+
+        let epoch_now = Utc::now().timestamp() as i32;
+        con.transaction(|conn| {
+            diesel::update(Episode::belonging_to(parent).filter(played.is_null()))
+                .set(played.eq(Some(epoch_now)))
+                .execute(conn)
+                .map_err(From::from)
+        })
+    }
+
+    /// Flag the episodes of a show that are missing from its feed as removed
+    /// upstream, and unflag the ones that were published again.
+    ///
+    /// `present` are the identities of the episodes the feed still lists.
+    pub(crate) fn update_removed_episodes(
+        &self,
+        pid: i32,
+        present: &[&str],
+    ) -> Result<(), DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.get()?;
+
+        let epoch_now = Utc::now().timestamp() as i32;
+        con.transaction(|conn| {
+            diesel::update(
+                episodes
+                    .filter(show_id.eq(pid))
+                    .filter(identity.ne_all(present))
+                    .filter(removed_upstream.is_null()),
+            )
+            .set(removed_upstream.eq(Some(epoch_now)))
+            .execute(conn)?;
+
+            diesel::update(
+                episodes
+                    .filter(show_id.eq(pid))
+                    .filter(identity.eq_any(present))
+                    .filter(removed_upstream.is_not_null()),
+            )
+            .set(removed_upstream.eq(None::<i32>))
+            .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Set the played flag and play position of the episode of a show with
+    /// the given identity.
+    pub(crate) fn restore_episode_state(
+        &self,
+        pid: i32,
+        identity_: &str,
+        played_: Option<i32>,
+        position: i32,
+    ) -> Result<usize, DataError> {
+        use crate::schema::episodes::dsl::*;
+        let mut con = self.get()?;
+
+        diesel::update(
+            episodes
+                .filter(show_id.eq(pid))
+                .filter(identity.eq(identity_)),
+        )
+        .set((played.eq(played_), play_position.eq(position)))
+        .execute(&mut *con)
         .map_err(From::from)
-}
+    }
 
-pub(crate) fn remove_feed(pd: &Show) -> Result<(), DataError> {
-    let db = connection();
-    let mut con = db.get()?;
+    /// Write a copy of the database to a new file at `path`.
+    ///
+    /// The copy is a consistent snapshot, taken without blocking writes.
+    pub(crate) fn vacuum_into(&self, path: &str) -> Result<(), DataError> {
+        let mut con = self.read()?;
 
-    con.transaction(|conn| {
-        delete_source(conn, pd.source_id())?;
-        delete_podcast(conn, pd.id())?;
-        delete_podcast_episodes(conn, pd.id())?;
-        info!("Feed removed from the Database.");
+        diesel::sql_query("VACUUM INTO ?")
+            .bind::<diesel::sql_types::Text, _>(path)
+            .execute(&mut *con)?;
         Ok(())
-    })
+    }
+}
+
+/// Define free functions that run the `Database` method of the same name on
+/// the global `Database`.
+macro_rules! global_queries {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            $vis fn $name($($arg: $ty),*) -> $ret {
+                connection().$name($($arg),*)
+            }
+        )*
+    };
+}
+
+global_queries! {
+    pub fn get_sources() -> Result<Vec<Source>, DataError>;
+    pub fn get_due_sources(now: i32) -> Result<Vec<Source>, DataError>;
+    pub fn get_failing_sources() -> Result<Vec<Source>, DataError>;
+    pub fn get_fetch_stats() -> Result<Vec<FetchStats>, DataError>;
+    pub fn get_source_fetch_stats(id_: i32) -> Result<FetchStats, DataError>;
+    pub fn get_podcasts() -> Result<Vec<Show>, DataError>;
+    pub fn get_podcasts_filter(filter_ids: &[i32]) -> Result<Vec<Show>, DataError>;
+    pub fn get_episodes() -> Result<Vec<Episode>, DataError>;
+    pub(crate) fn get_downloaded_episodes() -> Result<Vec<EpisodeCleanerModel>, DataError>;
+    pub(crate) fn get_played_cleaner_episodes()
+        -> Result<Vec<(i32, EpisodeCleanerModel)>, DataError>;
+    pub fn get_episode_from_rowid(ep_id: i32) -> Result<Episode, DataError>;
+    pub fn get_episode_widget_from_rowid(ep_id: i32) -> Result<EpisodeWidgetModel, DataError>;
+    pub fn get_episode_local_uri_from_id(ep_id: i32) -> Result<Option<String>, DataError>;
+    pub fn get_episodes_widgets_filter_limit(filter_ids: &[i32], limit: u32)
+        -> Result<Vec<EpisodeWidgetModel>, DataError>;
+    pub fn get_episode_chapters(ep_id: i32) -> Result<Vec<Chapter>, DataError>;
+    pub fn get_podcast_from_id(pid: i32) -> Result<Show, DataError>;
+    pub fn get_podcast_cover_from_id(pid: i32) -> Result<ShowCoverModel, DataError>;
+    pub fn get_pd_episodes(parent: &Show) -> Result<Vec<Episode>, DataError>;
+    pub fn get_pd_episodes_count(parent: &Show) -> Result<i64, DataError>;
+    pub fn get_pd_episodeswidgets(parent: &Show) -> Result<Vec<EpisodeWidgetModel>, DataError>;
+    pub fn get_pd_unplayed_episodes(parent: &Show) -> Result<Vec<Episode>, DataError>;
+    pub fn get_source_from_uri(uri_: &str) -> Result<Source, DataError>;
+    pub fn get_source_from_id(id_: i32) -> Result<Source, DataError>;
+    pub fn get_source_from_topic(topic: &str) -> Result<Source, DataError>;
+    pub fn get_podcast_from_source_id(sid: i32) -> Result<Show, DataError>;
+    pub fn get_episode_from_pk(identity_: &str, pid: i32) -> Result<Episode, DataError>;
+    pub fn get_episodes_minimal_from_rowids(ids: &[i32]) -> Result<Vec<EpisodeMinimal>, DataError>;
+    #[cfg(test)]
+    pub(crate) fn get_episode_cleaner_from_pk(identity_: &str, pid: i32)
+        -> Result<EpisodeCleanerModel, DataError>;
+    pub(crate) fn remove_feed(pd: &Show) -> Result<(), DataError>;
+    pub fn remove_source(source_id: i32) -> Result<(), DataError>;
+    pub fn source_exists(url: &str) -> Result<bool, DataError>;
+    pub fn is_episodes_populated(filter_show_ids: &[i32]) -> Result<bool, DataError>;
+    pub fn is_podcasts_populated(filter_ids: &[i32]) -> Result<bool, DataError>;
+    pub fn is_source_populated(filter_ids: &[i32]) -> Result<bool, DataError>;
+    pub(crate) fn replace_episode_chapters(ep_id: i32, chapters: &[NewChapter])
+        -> Result<(), DataError>;
+    pub fn enqueue_download(ep_id: i32, dir: &str) -> Result<QueuedDownload, DataError>;
+    pub fn get_queued_download(ep_id: i32) -> Result<QueuedDownload, DataError>;
+    pub fn get_download_queue() -> Result<Vec<QueuedDownload>, DataError>;
+    pub fn get_due_downloads(now: i32, limit: i64) -> Result<Vec<QueuedDownload>, DataError>;
+    pub fn count_running_downloads() -> Result<i64, DataError>;
+    pub fn restore_download_queue() -> Result<Vec<QueuedDownload>, DataError>;
+    pub fn get_show_settings(pid: i32) -> Result<ShowSettings, DataError>;
+    pub(crate) fn get_all_show_settings() -> Result<Vec<ShowSettings>, DataError>;
+    pub fn get_play_queue() -> Result<Vec<i32>, DataError>;
+    pub fn queue_episode(ep_id: i32) -> Result<Vec<i32>, DataError>;
+    pub fn queue_episode_next(ep_id: i32) -> Result<Vec<i32>, DataError>;
+    pub fn dequeue_episode(ep_id: i32) -> Result<Vec<i32>, DataError>;
+    pub fn move_queued_episode(ep_id: i32, index: usize) -> Result<Vec<i32>, DataError>;
+    pub fn search(input: &str, limit: u32) -> Result<SearchResults, DataError>;
+    pub fn update_none_to_played_now(parent: &Show) -> Result<usize, DataError>;
 }
 
 fn delete_source(con: &mut SqliteConnection, source_id: i32) -> QueryResult<usize> {
@@ -397,109 +1044,75 @@ fn delete_podcast_episodes(con: &mut SqliteConnection, parent_id: i32) -> QueryR
     diesel::delete(episodes.filter(show_id.eq(parent_id))).execute(con)
 }
 
-pub fn source_exists(url: &str) -> Result<bool, DataError> {
-    use crate::schema::source::dsl::*;
-
-    let db = connection();
-    let mut con = db.get()?;
-
-    select(exists(source.filter(uri.eq(url))))
-        .get_result(&mut con)
-        .map_err(From::from)
+fn delete_podcast_episode_chapters(
+    con: &mut SqliteConnection,
+    parent_id: i32,
+) -> QueryResult<usize> {
+    use crate::schema::episode_chapters::dsl::*;
+    use crate::schema::episodes;
+
+    let show_episodes = episodes::table
+        .select(episodes::rowid)
+        .filter(episodes::show_id.eq(parent_id));
+    diesel::delete(episode_chapters.filter(episode_id.eq_any(show_episodes))).execute(con)
 }
 
-pub(crate) fn podcast_exists(source_id_: i32) -> Result<bool, DataError> {
-    use crate::schema::shows::dsl::*;
+fn delete_podcast_downloads(con: &mut SqliteConnection, parent_id: i32) -> QueryResult<usize> {
+    use crate::schema::download_queue::dsl::*;
+    use crate::schema::episodes;
 
-    let db = connection();
-    let mut con = db.get()?;
-
-    select(exists(shows.filter(source_id.eq(source_id_))))
-        .get_result(&mut con)
-        .map_err(From::from)
+    let show_episodes = episodes::table
+        .select(episodes::rowid)
+        .filter(episodes::show_id.eq(parent_id));
+    diesel::delete(download_queue.filter(episode_id.eq_any(show_episodes))).execute(con)
 }
 
-pub(crate) fn episode_exists(title_: &str, show_id_: i32) -> Result<bool, DataError> {
-    use crate::schema::episodes::dsl::*;
-
-    let db = connection();
-    let mut con = db.get()?;
+fn delete_podcast_queue(con: &mut SqliteConnection, parent_id: i32) -> QueryResult<usize> {
+    use crate::schema::episodes;
+    use crate::schema::play_queue::dsl::*;
 
-    select(exists(
-        episodes
-            .filter(show_id.eq(show_id_))
-            .filter(title.eq(title_)),
-    ))
-    .get_result(&mut con)
-    .map_err(From::from)
+    let show_episodes = episodes::table
+        .select(episodes::rowid)
+        .filter(episodes::show_id.eq(parent_id));
+    diesel::delete(play_queue.filter(episode_id.eq_any(show_episodes))).execute(con)
 }
 
-/// Check if the `episodes table contains any rows
-///
-/// Return true if `episodes` table is populated.
-pub fn is_episodes_populated(filter_show_ids: &[i32]) -> Result<bool, DataError> {
-    use crate::schema::episodes::dsl::*;
+fn delete_show_settings(con: &mut SqliteConnection, parent_id: i32) -> QueryResult<usize> {
+    use crate::schema::show_settings::dsl::*;
 
-    let db = connection();
-    let mut con = db.get()?;
-
-    select(exists(episodes.filter(show_id.ne_all(filter_show_ids))))
-        .get_result(&mut con)
-        .map_err(From::from)
+    diesel::delete(show_settings.filter(show_id.eq(parent_id))).execute(con)
 }
 
-/// Check if the `shows` table contains any rows
-///
-/// Return true if `shows` table is populated.
-pub fn is_podcasts_populated(filter_ids: &[i32]) -> Result<bool, DataError> {
-    use crate::schema::shows::dsl::*;
-
-    let db = connection();
-    let mut con = db.get()?;
-
-    select(exists(shows.filter(id.ne_all(filter_ids))))
-        .get_result(&mut con)
-        .map_err(From::from)
+/// A row of a full-text search table that matched.
+#[derive(QueryableByName)]
+struct SearchMatch {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    snippet: String,
 }
 
-/// Check if the `source` table contains any rows
+/// Run `query` against one of the full-text search tables.
 ///
-/// Return true if `source` table is populated.
-pub fn is_source_populated(filter_ids: &[i32]) -> Result<bool, DataError> {
-    use crate::schema::source::dsl::*;
-
-    let db = connection();
-    let mut con = db.get()?;
-
-    select(exists(source.filter(id.ne_all(filter_ids))))
-        .get_result(&mut con)
-        .map_err(From::from)
-}
-
-pub(crate) fn index_new_episodes(eps: &[NewEpisode]) -> Result<(), DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
-
-    diesel::insert_into(episodes)
-        .values(eps)
-        .execute(&mut con)
-        .map_err(From::from)
-        .map(|_| ())
-}
-
-pub fn update_none_to_played_now(parent: &Show) -> Result<usize, DataError> {
-    use crate::schema::episodes::dsl::*;
-    let db = connection();
-    let mut con = db.get()?;
-
-    let epoch_now = Utc::now().timestamp() as i32;
-    con.transaction(|conn| {
-        diesel::update(Episode::belonging_to(parent).filter(played.is_null()))
-            .set(played.eq(Some(epoch_now)))
-            .execute(conn)
-            .map_err(From::from)
-    })
+/// The title is weighted above the description when ranking the matches.
+fn search_table(
+    con: &mut SqliteConnection,
+    table: &str,
+    query: &str,
+    limit: i64,
+) -> QueryResult<Vec<SearchMatch>> {
+    use diesel::sql_types::{BigInt, Text};
+
+    diesel::sql_query(format!(
+        "SELECT rowid AS id, snippet({table}, -1, ?, ?, '…', 12) AS snippet \
+         FROM {table} WHERE {table} MATCH ? \
+         ORDER BY bm25({table}, 10.0, 1.0) LIMIT ?"
+    ))
+    .bind::<Text, _>(MATCH_START.to_string())
+    .bind::<Text, _>(MATCH_END.to_string())
+    .bind::<Text, _>(query)
+    .bind::<BigInt, _>(limit)
+    .load(con)
 }
 
 #[cfg(test)]
@@ -529,4 +1142,187 @@ mod tests {
         assert_eq!(eps_num2, 0);
         Ok(())
     }
+
+    #[test]
+    fn test_episode_chapters() -> Result<()> {
+        truncate_db()?;
+
+        let url = "https://example.org/podcast/feed.xml";
+        let source = Source::from_url(url)?;
+        let feed =
+            crate::utils::get_feed("tests/feeds/2026-10-18-PodcastNamespace.xml", source.id());
+        feed.index()?;
+        let pd = get_podcast_from_source_id(source.id())?;
+        let ep = get_episode_from_pk("sampler-episode-3", pd.id())?;
+        assert_eq!(
+            ep.chapters_uri(),
+            Some("https://example.org/podcast/episode-3/chapters.json")
+        );
+        assert!(get_episode_chapters(ep.rowid())?.is_empty());
+
+        let data = std::fs::read("tests/feeds/2026-10-18-PodcastNamespace-chapters.json")?;
+        let chapters = crate::parser::parse_chapters_json(&data, ep.rowid())?;
+        replace_episode_chapters(ep.rowid(), &chapters)?;
+        // Replacing the chapters should not duplicate them
+        replace_episode_chapters(ep.rowid(), &chapters)?;
+
+        let stored = get_episode_chapters(ep.rowid())?;
+        assert_eq!(stored.len(), 4);
+        assert_eq!(stored[0].title(), Some("Introduction"));
+        assert_eq!(stored[0].end_time(), Some(95));
+        assert_eq!(stored[3].start_time(), 2713);
+
+        remove_feed(&pd)?;
+        assert!(get_episode_chapters(ep.rowid())?.is_empty());
+        Ok(())
+    }
+    #[test]
+    fn test_download_queue() -> Result<()> {
+        truncate_db()?;
+
+        let url = "https://example.org/podcast/feed.xml";
+        let source = Source::from_url(url)?;
+        let feed =
+            crate::utils::get_feed("tests/feeds/2026-10-18-PodcastNamespace.xml", source.id());
+        feed.index()?;
+        let pd = get_podcast_from_source_id(source.id())?;
+        let eps = get_pd_episodes(&pd)?;
+        let dir = "/tmp/podcasts";
+
+        for ep in &eps {
+            assert_eq!(
+                enqueue_download(ep.rowid(), dir)?.state(),
+                DownloadState::Queued
+            );
+        }
+        assert_eq!(get_download_queue()?.len(), 3);
+
+        let now = Utc::now().timestamp() as i32;
+        let mut due = get_due_downloads(now, 2)?;
+        assert_eq!(due.len(), 2);
+        for dl in &mut due {
+            dl.set_state(DownloadState::Running);
+            dl.save()?;
+        }
+        assert_eq!(count_running_downloads()?, 2);
+
+        // A failed attempt is retried later
+        let delay = due[0].retry_later("Connection reset");
+        assert_eq!(delay, Some(std::time::Duration::from_secs(30)));
+        due[0].save()?;
+        assert_eq!(get_due_downloads(now, 10)?.len(), 1);
+        assert_eq!(count_running_downloads()?, 1);
+
+        due[1].set_state(DownloadState::Done);
+        due[1].save()?;
+
+        // Queueing a waiting download again leaves it alone
+        let queued = enqueue_download(due[0].episode_id(), dir)?;
+        assert_eq!(queued.attempts(), 1);
+        assert_eq!(queued.error(), Some("Connection reset"));
+
+        // Queueing a finished one again resets the entry
+        let requeued = enqueue_download(due[1].episode_id(), dir)?;
+        assert_eq!(requeued.state(), DownloadState::Queued);
+        assert_eq!(requeued.attempts(), 0);
+
+        let restored = restore_download_queue()?;
+        assert_eq!(restored.len(), 3);
+        assert_eq!(get_download_queue()?.len(), 3);
+
+        remove_feed(&pd)?;
+        assert!(get_download_queue()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_enqueue_running_download() -> Result<()> {
+        truncate_db()?;
+
+        let url = "https://example.org/podcast/feed.xml";
+        let source = Source::from_url(url)?;
+        let feed =
+            crate::utils::get_feed("tests/feeds/2026-10-18-PodcastNamespace.xml", source.id());
+        feed.index()?;
+        let pd = get_podcast_from_source_id(source.id())?;
+        let ep = get_pd_episodes(&pd)?.remove(0);
+
+        let mut running = enqueue_download(ep.rowid(), "/tmp/podcasts")?;
+        running.set_state(DownloadState::Running);
+        running.save()?;
+
+        // Queueing it again, like an auto-download would, doesn't start it twice
+        let queued = enqueue_download(ep.rowid(), "/tmp/elsewhere")?;
+        assert_eq!(queued.state(), DownloadState::Running);
+        assert_eq!(queued.download_dir(), "/tmp/podcasts");
+        assert_eq!(count_running_downloads()?, 1);
+        assert!(get_due_downloads(Utc::now().timestamp() as i32, 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_play_queue() -> Result<()> {
+        truncate_db()?;
+
+        let url = "https://example.org/podcast/feed.xml";
+        let source = Source::from_url(url)?;
+        let feed =
+            crate::utils::get_feed("tests/feeds/2026-10-18-PodcastNamespace.xml", source.id());
+        feed.index()?;
+        let pd = get_podcast_from_source_id(source.id())?;
+        let ids: Vec<i32> = get_pd_episodes(&pd)?.iter().map(|e| e.rowid()).collect();
+        let (a, b, c) = (ids[0], ids[1], ids[2]);
+
+        assert!(get_play_queue()?.is_empty());
+        queue_episode(a)?;
+        queue_episode(b)?;
+        // Queueing twice does not duplicate the episode
+        assert_eq!(queue_episode(a)?, vec![a, b]);
+        assert_eq!(queue_episode_next(c)?, vec![c, a, b]);
+        assert_eq!(queue_episode_next(b)?, vec![b, c, a]);
+
+        assert_eq!(move_queued_episode(b, 1)?, vec![c, b, a]);
+        assert_eq!(move_queued_episode(c, 10)?, vec![b, a, c]);
+        assert_eq!(dequeue_episode(a)?, vec![b, c]);
+        assert_eq!(get_play_queue()?, vec![b, c]);
+
+        remove_feed(&pd)?;
+        assert!(get_play_queue()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search() -> Result<()> {
+        truncate_db()?;
+
+        let url = "https://example.org/podcast/feed.xml";
+        let source = Source::from_url(url)?;
+        let feed =
+            crate::utils::get_feed("tests/feeds/2026-10-18-PodcastNamespace.xml", source.id());
+        feed.index()?;
+        let pd = get_podcast_from_source_id(source.id())?;
+
+        assert!(search("  ", 10)?.is_empty());
+        assert!(search("nothing matches this", 10)?.is_empty());
+
+        let results = search("namespace samp", 10)?;
+        assert_eq!(results.shows().len(), 1);
+        assert_eq!(results.shows()[0].item().id(), pd.id());
+        assert!(results.shows()[0].snippet().contains("<b>Namespace</b>"));
+
+        let results = search("CHAPTERS", 10)?;
+        assert!(results.shows().is_empty());
+        assert_eq!(results.episodes().len(), 2);
+        assert_eq!(search("chapters", 1)?.episodes().len(), 1);
+
+        let results = search("unsupported", 10)?;
+        assert_eq!(results.episodes().len(), 1);
+        let hit = &results.episodes()[0];
+        assert_eq!(hit.item().title(), "Episode 2: Unsupported Chapters");
+        assert!(hit.snippet().contains("<b>Unsupported</b>"));
+
+        remove_feed(&pd)?;
+        assert!(search("chapters", 10)?.is_empty());
+        Ok(())
+    }
 }
@@ -20,18 +20,22 @@
 use glob::glob;
 use reqwest::header::*;
 use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use tempfile::TempDir;
 
 use std::fs;
-use std::fs::{copy, remove_file, File};
+use std::fs::{copy, remove_file, OpenOptions};
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::errors::DownloadError;
-use crate::utils;
+use crate::errors::{DataError, DownloadError};
+use crate::transcript::{Transcript, TranscriptFormat};
 use crate::xdg_dirs::PODCASTS_CACHE;
-use crate::{EpisodeWidgetModel, Save, ShowCoverModel};
+use crate::{dbqueries, parser, utils};
+use crate::{Chapter, EpisodeWidgetModel, Save, ShowCoverModel};
 
 // TODO: Replace path that are of type &str with std::path.
 // TODO: Have a convention/document absolute/relative paths, if they should end
@@ -46,6 +50,13 @@ pub trait DownloadProgress {
     fn cancel(&mut self);
 }
 
+/// How long to wait for a connection to a server.
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The shared settings of every `reqwest::Client`.
+///
+/// Only connecting is bounded in time, as downloading an episode can take
+/// arbitrarily long. Use `timeout` on the builder to bound whole requests.
 pub fn client_builder() -> reqwest::ClientBuilder {
     // Haven't included the loop check as
     // Steal the Stars would trigger it as
@@ -65,82 +76,250 @@ pub fn client_builder() -> reqwest::ClientBuilder {
     reqwest::Client::builder()
         .redirect(policy)
         .referer(false)
+        .connect_timeout(CONNECT_TIMEOUT)
         .user_agent(crate::USER_AGENT)
 }
 
+/// Book-keeping of a partially downloaded file.
+///
+/// It is stored next to the `.part` file, so an interrupted download can be
+/// resumed with a `Range` request, as long as the remote file did not change.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_type: Option<String>,
+    total_len: Option<u64>,
+}
+
+impl PartialDownload {
+    /// The validator to send with `If-Range`.
+    ///
+    /// Weak ETags can not be used for ranges, so fall back to `Last-Modified`.
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// The `.part` file a download is written into, and its `PartialDownload`
+/// metadata.
+#[derive(Debug, Clone)]
+struct PartialFiles {
+    part: PathBuf,
+    meta: PathBuf,
+}
+
+impl PartialFiles {
+    /// The partial files of an episode, kept until it is fully downloaded.
+    ///
+    /// They have to be in the cache instead of the tmp dir cause rename can't
+    /// move cross filesystems.
+    fn for_episode(rowid: i32) -> Result<Self, DownloadError> {
+        let dir = partial_dir();
+        fs::create_dir_all(&dir)?;
+        Ok(Self::in_dir(&dir, &rowid.to_string()))
+    }
+
+    fn in_dir(dir: &Path, name: &str) -> Self {
+        PartialFiles {
+            part: dir.join(format!("{name}.part")),
+            meta: dir.join(format!("{name}.json")),
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn partial_dir() -> PathBuf {
+    PODCASTS_CACHE.join("partial_downloads")
+}
+
+#[cfg(test)]
+fn partial_dir() -> PathBuf {
+    crate::database::TEMPDIR.path().join("partial_downloads")
+}
+
+/// Load the partial download of `url`, and the offset to resume it from.
+fn load_partial(part: &Path, meta: &Path, url: &str) -> Option<(PartialDownload, u64)> {
+    let offset = fs::metadata(part).ok()?.len();
+    let partial: PartialDownload = serde_json::from_slice(&fs::read(meta).ok()?).ok()?;
+
+    if offset == 0 || partial.url != url || partial.validator().is_none() {
+        return None;
+    }
+    Some((partial, offset))
+}
+
+fn remove_partial(part: &Path, meta: &Path) {
+    for path in [part, meta] {
+        if path.exists() {
+            remove_file(path)
+                .map_err(|err| error!("Failed to remove {}: {}", path.display(), err))
+                .ok();
+        }
+    }
+}
+
+/// Parses a `Content-Range: bytes start-end/total` header.
+///
+/// Returns the first byte of the range and the size of the whole file,
+/// if it's known.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    let total = total.trim().parse().ok();
+    Some((start, total))
+}
+
+fn header_str(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .map(From::from)
+}
+
+fn cancel_progress(progress: &Option<Arc<Mutex<dyn DownloadProgress + Send>>>) {
+    if let Some(ref prog) = progress {
+        if let Ok(mut m) = prog.lock() {
+            m.cancel();
+        }
+    }
+}
+
 // Adapted from https://github.com/mattgathu/rget .
 // I never wanted to write a custom downloader.
 // Sorry to those who will have to work with that code.
 // Would much rather use a crate,
 // or bindings for a lib like youtube-dl(python),
 // But can't seem to find one.
-// TODO: Write unit-tests.
+//
+// The content is written into `partial`, and resumed from there if a previous
+// attempt was interrupted.
 async fn download_into(
     dir: &str,
     file_title: &str,
     url: &str,
+    partial: &PartialFiles,
     progress: Option<Arc<Mutex<dyn DownloadProgress + Send>>>,
 ) -> Result<String, DownloadError> {
     info!("GET request to: {}", url);
 
-    let client = client_builder().build()?;
-    let resp = client.get(url).send().await?;
-    info!("Status Resp: {}", resp.status());
+    let (part_file, meta_file) = (&partial.part, &partial.meta);
+    let mut resume = load_partial(part_file, meta_file, url);
 
-    if !resp.status().is_success() {
-        if let Some(ref prog) = progress {
-            if let Ok(mut m) = prog.lock() {
-                m.cancel();
+    let client = client_builder().build()?;
+    let resp = loop {
+        let mut req = client.get(url);
+        if let Some((ref partial, offset)) = resume {
+            info!("Resuming download from byte {}", offset);
+            req = req.header(RANGE, format!("bytes={offset}-"));
+            if let Some(validator) = partial.validator() {
+                req = req.header(IF_RANGE, validator);
             }
         }
 
+        let resp = req.send().await?;
+        info!("Status Resp: {}", resp.status());
+
+        // The file shrunk, or we already have all of it, start over to be sure.
+        if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE && resume.is_some() {
+            remove_partial(part_file, meta_file);
+            resume = None;
+            continue;
+        }
+        break resp;
+    };
+
+    if !resp.status().is_success() {
+        cancel_progress(&progress);
         return Err(DownloadError::UnexpectedResponse(resp.status()));
     }
 
     let headers = resp.headers().clone();
-    let ct_len = headers
-        .get(CONTENT_LENGTH)
-        .and_then(|h| h.to_str().ok())
-        .and_then(|len| len.parse().ok());
-    let ct_type = headers
-        .get(CONTENT_TYPE)
-        .and_then(|h| h.to_str().ok())
-        .map(From::from);
+    let ct_len: Option<u64> = header_str(&headers, CONTENT_LENGTH).and_then(|len| len.parse().ok());
+
+    // A `206` continues the partial file, anything else is the whole file,
+    // either cause the server ignored the `Range` or the file changed.
+    let (offset, total_len, previous) = if resp.status() == StatusCode::PARTIAL_CONTENT {
+        let offset = resume.as_ref().map(|(_, offset)| *offset).unwrap_or(0);
+        let range = header_str(&headers, CONTENT_RANGE).and_then(|r| parse_content_range(&r));
+        match range {
+            Some((start, total)) if start == offset => {
+                let total = total.or_else(|| ct_len.map(|len| offset + len));
+                (offset, total, resume.map(|(partial, _)| partial))
+            }
+            _ => {
+                cancel_progress(&progress);
+                return Err(DownloadError::UnexpectedResponse(resp.status()));
+            }
+        }
+    } else {
+        (0, ct_len, None)
+    };
+
+    let previous = previous.unwrap_or_default();
+    let partial = PartialDownload {
+        url: url.to_owned(),
+        etag: header_str(&headers, ETAG).or(previous.etag),
+        last_modified: header_str(&headers, LAST_MODIFIED).or(previous.last_modified),
+        content_type: header_str(&headers, CONTENT_TYPE).or(previous.content_type),
+        total_len,
+    };
 
-    if let Some(ct_len) = ct_len {
-        info!("File Length: {}", ct_len);
+    if let Some(total_len) = total_len {
+        info!("File Length: {}", total_len);
     }
-    if let Some(ct_type) = ct_type {
+    if let Some(ref ct_type) = partial.content_type {
         info!("Content Type: {}", ct_type);
     }
 
-    let ext = get_ext(ct_type).unwrap_or_else(|| String::from("unknown"));
+    let ext = get_ext(partial.content_type.as_deref()).unwrap_or_else(|| String::from("unknown"));
     info!("Extension: {}", ext);
 
-    // Construct a temp file to save desired content.
-    // It has to be a `new_in` instead of new cause rename can't move cross
-    // filesystems.
-    let tempdir = TempDir::with_prefix_in("temp_download", PODCASTS_CACHE.to_str().unwrap())?;
-    let out_file = format!("{}/temp.part", tempdir.path().to_str().unwrap(),);
-
-    if let Some(ct_len) = ct_len {
-        if let Some(ref p) = progress {
-            if let Ok(mut m) = p.lock() {
-                m.set_size(ct_len);
+    if let Some(ref p) = progress {
+        if let Ok(mut m) = p.lock() {
+            if let Some(total_len) = total_len {
+                m.set_size(total_len);
             }
+            m.set_downloaded(offset);
         }
     };
 
+    // Store the validators before any content, so an interrupted download
+    // can always be resumed.
+    fs::write(
+        meta_file,
+        serde_json::to_vec(&partial).map_err(DataError::from)?,
+    )?;
+
     // Save requested content into the file.
-    save_io(&out_file, resp, progress).await?;
+    save_io(part_file, resp, offset > 0, progress).await?;
+
+    // Make sure we got the whole file.
+    let len = fs::metadata(part_file)?.len();
+    if let Some(expected) = total_len {
+        if len != expected {
+            // Something went very wrong if there is more than expected.
+            if len > expected {
+                remove_partial(part_file, meta_file);
+            }
+            return Err(DownloadError::IncompleteDownload {
+                expected,
+                found: len,
+            });
+        }
+    }
 
     // Construct the desired path.
     let target = format!("{}/{}.{}", dir, file_title, ext);
-    // Rename/move the tempfile into a permanent place upon success.
+    // Rename/move the partial file into a permanent place upon success.
     // Unlike rename(), copy() + remove_file() works even when the
-    // temp dir is on a different mount point than the target dir.
-    copy(&out_file, &target)?;
-    remove_file(out_file)?;
+    // cache dir is on a different mount point than the target dir.
+    copy(part_file, &target)?;
+    remove_partial(part_file, meta_file);
     info!("Downloading of {} completed successfully.", &target);
     Ok(target)
 }
@@ -163,40 +342,56 @@ fn get_ext(content: Option<&str>) -> Option<String> {
 // TODO: Refactor... Somehow.
 /// Handles the I/O of fetching a remote file and saving into a Buffer and A
 /// File.
+///
+/// When `append` is set the content is added at the end of `file`,
+/// otherwise the file is truncated first.
 #[allow(clippy::needless_pass_by_value)]
 async fn save_io(
-    file: &str,
+    file: &Path,
     resp: reqwest::Response,
+    append: bool,
     progress: Option<Arc<Mutex<dyn DownloadProgress + Send>>>,
 ) -> Result<(), DownloadError> {
     use futures::StreamExt;
     use std::ops::Deref;
 
-    info!("Downloading into: {}", file);
-    let mut writer = BufWriter::new(File::create(file)?);
+    info!("Downloading into: {}", file.display());
+    let out = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(file)?;
+    let mut writer = BufWriter::new(out);
     let mut body_stream = resp.bytes_stream();
 
     while let Some(chunk) = body_stream.next().await {
-        if let Ok(chunk) = chunk {
-            writer.write_all(chunk.deref())?;
-            // This sucks.
-            // Actually the whole download module is hack, so w/e.
-            if let Some(prog) = progress.clone() {
-                let len = writer.get_ref().metadata().map(|x| x.len());
-                if let Ok(l) = len {
-                    if let Ok(mut m) = prog.lock() {
-                        if m.should_cancel() {
-                            return Err(DownloadError::DownloadCancelled);
-                        }
-                        m.set_downloaded(l);
+        // Keep what we have so far, the partial file can be resumed.
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                writer.flush()?;
+                return Err(err.into());
+            }
+        };
+        writer.write_all(chunk.deref())?;
+        // This sucks.
+        // Actually the whole download module is hack, so w/e.
+        if let Some(prog) = progress.clone() {
+            let len = writer.get_ref().metadata().map(|x| x.len());
+            if let Ok(l) = len {
+                if let Ok(mut m) = prog.lock() {
+                    if m.should_cancel() {
+                        writer.flush()?;
+                        return Err(DownloadError::DownloadCancelled);
                     }
+                    m.set_downloaded(l);
                 }
             }
-        } else {
-            break;
         }
     }
 
+    writer.flush()?;
     Ok(())
 }
 
@@ -221,6 +416,7 @@ pub async fn get_episode(
         download_dir,
         &ep.rowid().to_string(),
         ep.uri().unwrap(),
+        &PartialFiles::for_episode(ep.rowid())?,
         progress,
     )
     .await?;
@@ -274,19 +470,269 @@ pub async fn cache_image(pd: &ShowCoverModel) -> Result<String, DownloadError> {
 
     let cache_path = utils::get_cover_dir(pd.title())?;
 
-    let path = download_into(&cache_path, "cover", &url, None).await?;
+    // Covers are small, so they are not worth resuming. Each download gets
+    // its own temp dir, that is removed once it's done.
+    let tempdir = TempDir::with_prefix_in("temp_download", &*PODCASTS_CACHE)?;
+    let partial = PartialFiles::in_dir(tempdir.path(), "cover");
+    let path = download_into(&cache_path, "cover", &url, &partial, None).await?;
     info!("Cached img into: {}", &path);
     Ok(path)
 }
 
+/// Fetch the `<podcast:chapters>` file of an episode and store its chapters.
+///
+/// The chapters are only fetched once, after that the stored ones are returned.
+/// Episodes that do not advertise any chapters return an empty `Vec`.
+pub async fn cache_chapters(ep_id: i32) -> Result<Vec<Chapter>, DownloadError> {
+    let chapters = dbqueries::get_episode_chapters(ep_id)?;
+    if !chapters.is_empty() {
+        return Ok(chapters);
+    }
+
+    let ep = dbqueries::get_episode_from_rowid(ep_id)?;
+    let url = match ep.chapters_uri() {
+        Some(url) => url,
+        None => return Ok(chapters),
+    };
+
+    info!("GET request to: {}", url);
+    let client = client_builder().build()?;
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        return Err(DownloadError::UnexpectedResponse(resp.status()));
+    }
+
+    let body = resp.bytes().await?;
+    let new_chapters = parser::parse_chapters_json(&body, ep_id)?;
+    dbqueries::replace_episode_chapters(ep_id, &new_chapters)?;
+    info!(
+        "Cached {} chapters for episode {}",
+        new_chapters.len(),
+        ep_id
+    );
+
+    dbqueries::get_episode_chapters(ep_id).map_err(From::from)
+}
+
+/// Fetch the `<podcast:transcript>` of an episode and parse it.
+///
+/// The file is cached in the download folder of the show, next to
+/// the episodes, so it is only fetched once.
+/// Episodes without a transcript in a supported format return `None`.
+pub async fn cache_transcript(ep_id: i32) -> Result<Option<Transcript>, DownloadError> {
+    let ep = dbqueries::get_episode_from_rowid(ep_id)?;
+    let (url, format) = match (ep.transcript_uri(), ep.transcript_type()) {
+        (Some(url), Some(mime)) => match TranscriptFormat::from_mime(mime) {
+            Some(format) => (url, format),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    let pd = dbqueries::get_podcast_from_id(ep.show_id())?;
+    let dir = utils::get_download_dir(pd.title())?;
+    let path = format!("{}/{}-transcript.{}", dir, ep_id, format.extension());
+
+    if Path::new(&path).exists() {
+        let data = fs::read(&path)?;
+        let transcript = Transcript::parse(format, &String::from_utf8_lossy(&data))?;
+        return Ok(Some(transcript));
+    }
+
+    info!("GET request to: {}", url);
+    let client = client_builder().build()?;
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        return Err(DownloadError::UnexpectedResponse(resp.status()));
+    }
+
+    // Only keep what parses, so an error page gets fetched again next time.
+    let body = resp.bytes().await?;
+    let transcript = Transcript::parse(format, &String::from_utf8_lossy(&body))?;
+    if !transcript.cues().is_empty() {
+        fs::write(&path, &body)?;
+        info!("Cached transcript into: {}", &path);
+    }
+    Ok(Some(transcript))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_server::MockServer;
     use crate::pipeline::pipeline;
     use crate::{dbqueries, Source};
     use anyhow::Result;
     use std::fs;
 
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-999/1000"),
+            Some((100, Some(1000)))
+        );
+        assert_eq!(parse_content_range("bytes 0-0/*"), Some((0, None)));
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(parse_content_range("items 1-2/3"), None);
+    }
+
+    #[test]
+    fn test_load_partial() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let part = dir.path().join("42.part");
+        let meta = dir.path().join("42.json");
+        let url = "https://example.org/podcast/episode-3.mp3";
+
+        let mut partial = PartialDownload {
+            url: url.to_owned(),
+            etag: Some("W/\"weak\"".to_owned()),
+            ..Default::default()
+        };
+        fs::write(&part, b"0123456789")?;
+        fs::write(&meta, serde_json::to_vec(&partial)?)?;
+        // Weak ETags can't be used to resume
+        assert_eq!(load_partial(&part, &meta, url), None);
+
+        partial.last_modified = Some("Sat, 17 Oct 2026 10:00:00 GMT".to_owned());
+        fs::write(&meta, serde_json::to_vec(&partial)?)?;
+        assert_eq!(partial.validator(), partial.last_modified.as_deref());
+        assert_eq!(load_partial(&part, &meta, url), Some((partial.clone(), 10)));
+        // The episode moved to another url
+        assert_eq!(
+            load_partial(&part, &meta, "https://example.org/new.mp3"),
+            None
+        );
+
+        partial.etag = Some("\"strong\"".to_owned());
+        assert_eq!(partial.validator(), Some("\"strong\""));
+        Ok(())
+    }
+
+    const ETAG_V1: &str = "\"v1\"";
+
+    /// An episode of 100 bytes, served with an `ETag`.
+    fn episode_server() -> (MockServer, Vec<u8>) {
+        let server = MockServer::start();
+        let body: Vec<u8> = (0..100).collect();
+        let headers = [("ETag", ETAG_V1), ("Content-Type", "audio/ogg")];
+        server.route("/episode.ogg", 200, &headers, &body);
+        (server, body)
+    }
+
+    /// Leave a partial download of `len` bytes of `x`, fetched when the
+    /// episode had the `etag`.
+    fn leave_partial(dir: &Path, url: &str, etag: &str, len: usize) -> Result<PartialFiles> {
+        let partial = PartialFiles::in_dir(dir, "1");
+        let meta = PartialDownload {
+            url: url.to_owned(),
+            etag: Some(etag.to_owned()),
+            ..Default::default()
+        };
+        fs::write(&partial.part, vec![b'x'; len])?;
+        fs::write(&partial.meta, serde_json::to_vec(&meta)?)?;
+        Ok(partial)
+    }
+
+    fn download(dir: &Path, url: &str, partial: &PartialFiles) -> Result<String, DownloadError> {
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(download_into(
+            dir.to_str().unwrap(),
+            "1",
+            url,
+            partial,
+            None,
+        ))
+    }
+
+    #[test]
+    fn test_download_resume() -> Result<()> {
+        let (server, body) = episode_server();
+        let dir = tempfile::TempDir::new()?;
+        let url = server.url("/episode.ogg");
+        let partial = leave_partial(dir.path(), &url, ETAG_V1, 40)?;
+
+        // Only the rest of the file is fetched, and added after the partial one
+        let path = download(dir.path(), &url, &partial)?;
+        assert_eq!(path, format!("{}/1.ogg", dir.path().display()));
+        let mut expected = vec![b'x'; 40];
+        expected.extend_from_slice(&body[40..]);
+        assert_eq!(fs::read(&path)?, expected);
+        assert!(!partial.part.exists());
+        assert!(!partial.meta.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_download_resume_changed() -> Result<()> {
+        let (server, body) = episode_server();
+        let dir = tempfile::TempDir::new()?;
+        let url = server.url("/episode.ogg");
+
+        // The episode changed since, so `If-Range` gets the whole file back
+        let partial = leave_partial(dir.path(), &url, "\"v0\"", 40)?;
+        let path = download(dir.path(), &url, &partial)?;
+        assert_eq!(fs::read(&path)?, body);
+        assert_eq!(server.hits("/episode.ogg"), 1);
+
+        // There is nothing left to fetch, so the download starts over
+        let partial = leave_partial(dir.path(), &url, ETAG_V1, 100)?;
+        let path = download(dir.path(), &url, &partial)?;
+        assert_eq!(fs::read(&path)?, body);
+        assert_eq!(server.hits("/episode.ogg"), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_download_interrupted() -> Result<()> {
+        let (server, body) = episode_server();
+        let dir = tempfile::TempDir::new()?;
+        let url = server.url("/episode.ogg");
+        let partial = PartialFiles::in_dir(dir.path(), "1");
+        let target = dir.path().join("1.ogg");
+
+        // The connection drops before `Content-Length` bytes were received
+        let headers = [("ETag", ETAG_V1), ("Content-Type", "audio/ogg")];
+        server.truncated_route("/episode.ogg", &headers, &body, 30);
+        assert!(download(dir.path(), &url, &partial).is_err());
+        assert!(!target.exists());
+        assert_eq!(fs::read(&partial.part)?, &body[..30]);
+
+        // The next attempt picks up where it stopped
+        server.route("/episode.ogg", 200, &headers, &body);
+        download(dir.path(), &url, &partial)?;
+        assert_eq!(fs::read(&target)?, body);
+        Ok(())
+    }
+
+    #[test]
+    fn test_download_incomplete() -> Result<()> {
+        let server = MockServer::start();
+        let dir = tempfile::TempDir::new()?;
+        let url = server.url("/episode.ogg");
+        let partial = leave_partial(dir.path(), &url, ETAG_V1, 40)?;
+
+        // The server only sends part of the requested range
+        let body: Vec<u8> = (40..100).collect();
+        let headers = [
+            ("ETag", ETAG_V1),
+            ("Content-Type", "audio/ogg"),
+            ("Content-Range", "bytes 40-99/200"),
+        ];
+        server.route("/episode.ogg", 206, &headers, &body);
+        let err = download(dir.path(), &url, &partial).unwrap_err();
+        assert!(matches!(
+            err,
+            DownloadError::IncompleteDownload {
+                expected: 200,
+                found: 100
+            }
+        ));
+        assert!(!dir.path().join("1.ogg").exists());
+        // What was received is kept, to be resumed later
+        assert_eq!(fs::metadata(&partial.part)?.len(), 100);
+        Ok(())
+    }
+
     #[test]
     // This test inserts an rss feed to your `XDG_DATA/podcasts/podcasts.db` so we make it explicit
     // to run it.
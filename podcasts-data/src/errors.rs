@@ -44,6 +44,10 @@ pub enum DataError {
     IOError(#[from] io::Error),
     #[error("RSS Error: {0}")]
     RssError(#[from] rss::Error),
+    #[error("Atom Error: {0}")]
+    AtomError(#[from] atom_syndication::Error),
+    #[error("JSON Error: {0}")]
+    JsonError(#[from] serde_json::Error),
     #[error("XML Reader Error: {0}")]
     XmlReaderError(#[from] xml::reader::Error),
     #[error("Error: {0}")]
@@ -55,9 +59,9 @@ pub enum DataError {
         context: String,
     },
     #[error("Source redirects to a new url")]
-    FeedRedirect(Source),
+    FeedRedirect(Box<Source>),
     #[error("Feed is up to date")]
-    FeedNotModified(Source),
+    FeedNotModified(Box<Source>),
     #[error("Error occurred while Parsing an Episode. Reason: {}", reason)]
     ParseEpisodeError { reason: String, parent_id: i32 },
     #[error("Episode was not changed and thus skipped.")]
@@ -66,6 +70,8 @@ pub enum DataError {
     InvalidUri(#[from] http::uri::InvalidUri),
     #[error("Builder error: {0}")]
     BuilderError(String),
+    #[error("Unsupported archive version: {0}")]
+    UnsupportedArchive(u32),
 }
 
 #[derive(Error, Debug)]
@@ -80,6 +86,8 @@ pub enum DownloadError {
     UnexpectedResponse(reqwest::StatusCode),
     #[error("The Download was cancelled.")]
     DownloadCancelled,
+    #[error("Downloaded {found} bytes out of {expected}.")]
+    IncompleteDownload { expected: u64, found: u64 },
     #[error("Remote Image location not found.")]
     NoImageLocation,
     #[error("Failed to parse CacheLocation.")]
@@ -87,3 +95,15 @@ pub enum DownloadError {
     #[error("Failed to parse Cached Image Location.")]
     InvalidCachedImageLocation,
 }
+
+impl DownloadError {
+    /// Whether the download might succeed if it's tried again later.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DownloadError::RequestError(_)
+                | DownloadError::UnexpectedResponse(_)
+                | DownloadError::IncompleteDownload { .. }
+        )
+    }
+}
@@ -19,65 +19,218 @@
 
 //! Index Feeds.
 
-use crate::dbqueries;
+use crate::database::{connection, Database};
 use crate::errors::DataError;
-use crate::models::{Index, IndexState, Update};
+use crate::models::{IndexState, Save, Update};
 use crate::models::{NewEpisode, NewEpisodeMinimal, NewShow, Show};
+use crate::syndication::{Channel, Item};
 
-/// Wrapper struct that hold a `Source` id and the `rss::Channel`
+use url::Url;
+
+/// Wrapper struct that hold a `Source` id and the `Channel`
 /// that corresponds to the `Source.uri` field.
 #[derive(Debug, Clone, Builder, PartialEq)]
 #[builder(derive(Debug))]
 #[builder(setter(into))]
 pub struct Feed {
-    /// The `rss::Channel` parsed from the `Source` uri.
-    channel: rss::Channel,
-    /// The `Source` id where the xml `rss::Channel` came from.
+    /// The `Channel` parsed from the `Source` uri.
+    channel: Channel,
+    /// The `Source` id where the `Channel` came from.
     source_id: i32,
 }
 
+/// What indexing a `Feed` changed in the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexSummary {
+    inserted: Vec<i32>,
+    updated: usize,
+    unchanged: usize,
+    moved_to: Option<String>,
+}
+
+impl IndexSummary {
+    /// Get the `rowid`s of the episodes that were newly inserted.
+    pub fn inserted(&self) -> &[i32] {
+        &self.inserted
+    }
+
+    /// Get the number of known episodes that were updated.
+    pub fn updated(&self) -> usize {
+        self.updated
+    }
+
+    /// Get the number of known episodes that were already up to date.
+    pub fn unchanged(&self) -> usize {
+        self.unchanged
+    }
+
+    /// Get the url the `Source` was moved to, if the feed announced a new
+    /// location.
+    pub fn moved_to(&self) -> Option<&str> {
+        self.moved_to.as_deref()
+    }
+}
+
 impl Feed {
-    /// Index the contents of the RSS `Feed` into the database.
-    pub fn index(self) -> Result<(), DataError> {
-        let show = self.parse_podcast().to_podcast()?;
-        self.index_channel_items(show)
+    /// Index the contents of the `Feed` into the database.
+    pub fn index(self) -> Result<IndexSummary, DataError> {
+        self.index_in(&connection())
+    }
+
+    /// Index the contents of the `Feed` into `db`.
+    ///
+    /// The whole feed is written in a single transaction, so if any episode
+    /// fails to be stored nothing is, and the error is returned.
+    pub fn index_in(self, db: &Database) -> Result<IndexSummary, DataError> {
+        db.transaction(|db| {
+            let show = self.parse_podcast().to_podcast_in(db)?;
+            self.store_source_metadata(db);
+            let moved_to = self.relocate(db);
+            let mut summary = self.index_channel_items(db, show)?;
+            summary.moved_to = moved_to;
+            Ok(summary)
+        })
+    }
+
+    /// Remember how often the publisher asks for the feed to be refreshed,
+    /// and where its updates are announced.
+    fn store_source_metadata(&self, db: &Database) {
+        let chan = &self.channel;
+        let hints = chan.update_hints();
+        let stored = db
+            .get_source_from_id(self.source_id)
+            .and_then(|mut source| {
+                let websub = (chan.websub_hub(), chan.self_url());
+                if source.update_hints() != hints
+                    || (source.websub_hub(), source.self_url()) != websub
+                {
+                    source.set_update_hints(hints);
+                    source.set_websub(websub.0, websub.1);
+                    source.save_in(db)?;
+                }
+                Ok(())
+            });
+        if let Err(err) = stored {
+            error!(
+                "Failed to store the metadata of source {}: {}",
+                self.source_id, err
+            );
+        }
+    }
+
+    /// Follow the new location the publisher announced in the feed, if any.
+    fn relocate(&self, db: &Database) -> Option<String> {
+        let url = self.channel.new_feed_url()?;
+        // Publishers unlock their feed before moving it to another host
+        if self.channel.locked() {
+            warn!(
+                "Ignoring the new location of locked source {}: {}",
+                self.source_id, url
+            );
+            return None;
+        }
+
+        relocate_source(db, self.source_id, url)
+            .map_err(|err| error!("Failed to move source {}: {}", self.source_id, err))
+            .ok()
+            .flatten()
     }
 
     fn parse_podcast(&self) -> NewShow {
         NewShow::new(&self.channel, self.source_id)
     }
 
-    fn index_channel_items(self, pd: Show) -> Result<(), DataError> {
-        let stream = self.channel.into_items().into_iter();
-        // Parse the episodes
-        let episodes = stream.filter_map(move |item| {
-            let ret = NewEpisodeMinimal::new(&item, pd.id())
-                .and_then(move |ep| determine_ep_state(ep, &item));
-            if ret.is_ok() {
-                Some(ret)
-            } else {
-                error!("{:?}", ret);
-                None
+    fn index_channel_items(self, db: &Database, pd: Show) -> Result<IndexSummary, DataError> {
+        let show_id = pd.id();
+        let mut summary = IndexSummary::default();
+        let mut present = Vec::new();
+        let mut insertable: Vec<NewEpisode> = Vec::new();
+
+        for item in self.channel.into_items() {
+            // Items that aren't episodes are skipped, not fatal
+            let ep = match NewEpisodeMinimal::new(&item, show_id) {
+                Ok(ep) => ep,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+            present.push(ep.identity().to_owned());
+
+            match determine_ep_state(db, ep, &item)? {
+                IndexState::NotChanged => summary.unchanged += 1,
+                IndexState::Update((ep, rowid)) => {
+                    ep.update_in(db, rowid)?;
+                    summary.updated += 1;
+                }
+                // The last of the items that share an identity wins
+                IndexState::Index(ep) => {
+                    insertable.retain(|other| other.identity() != ep.identity());
+                    insertable.push(ep);
+                }
             }
-        });
-        // Filter errors, Index updatable episodes, return insertables.
-        let insertable_episodes = filter_episodes(episodes);
-        batch_insert_episodes(&insertable_episodes);
-        Ok(())
+        }
+
+        if !insertable.is_empty() {
+            info!("Indexing {} episodes.", insertable.len());
+            db.index_new_episodes(&insertable)?;
+        }
+
+        // A feed without a single valid item is more likely broken than empty
+        if !present.is_empty() {
+            let present: Vec<_> = present.iter().map(String::as_str).collect();
+            db.update_removed_episodes(show_id, &present)?;
+        }
+
+        let identities: Vec<_> = insertable.iter().map(|ep| ep.identity()).collect();
+        summary.inserted = db.get_episode_rowids(show_id, &identities)?;
+        Ok(summary)
+    }
+}
+
+/// Point the `Source` to `new_url`, and return it if it was valid and new.
+fn relocate_source(
+    db: &Database,
+    source_id: i32,
+    new_url: &str,
+) -> Result<Option<String>, DataError> {
+    let url = Url::parse(new_url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        warn!("Ignoring the new location of source {}: {}", source_id, url);
+        return Ok(None);
+    }
+
+    let mut source = db.get_source_from_id(source_id)?;
+    if Url::parse(source.uri()).ok().as_ref() == Some(&url) {
+        return Ok(None);
     }
+    if db.source_exists(url.as_str())? {
+        warn!(
+            "Source {} moved to {}, which is already subscribed to",
+            source_id, url
+        );
+        return Ok(None);
+    }
+
+    info!("Source {} moved to {}", source_id, url);
+    source.set_uri(url.to_string());
+    source.clear_etags();
+    source.save_in(db)?;
+    Ok(Some(url.into()))
 }
 
 fn determine_ep_state(
+    db: &Database,
     ep: NewEpisodeMinimal,
-    item: &rss::Item,
+    item: &Item,
 ) -> Result<IndexState<NewEpisode>, DataError> {
     // Check if feed exists
-    let exists = dbqueries::episode_exists(ep.title(), ep.show_id())?;
+    let exists = db.episode_exists(ep.identity(), ep.show_id())?;
 
     if !exists {
         Ok(IndexState::Index(ep.into_new_episode(item)))
     } else {
-        let old = dbqueries::get_episode_minimal_from_pk(ep.title(), ep.show_id())?;
+        let old = db.get_episode_minimal_from_pk(ep.identity(), ep.show_id())?;
         let rowid = old.rowid();
 
         if ep != old {
@@ -88,61 +241,17 @@ fn determine_ep_state(
     }
 }
 
-fn filter_episodes<S>(stream: S) -> Vec<NewEpisode>
-where
-    S: Iterator<Item = Result<IndexState<NewEpisode>, DataError>>,
-{
-    stream
-        .filter_map(Result::ok)
-        .filter_map(|state| {
-            match state {
-                IndexState::NotChanged => None,
-                // Update individual rows, and filter them
-                IndexState::Update((ref ep, rowid)) => {
-                    ep.update(rowid)
-                        .map_err(|err| error!("{}", err))
-                        .map_err(|_| error!("Failed to index episode: {:?}.", ep.title()))
-                        .ok();
-                    None
-                }
-                IndexState::Index(s) => Some(s),
-            }
-        })
-        // only Index is left, collect them for batch index
-        .collect()
-}
-
-fn batch_insert_episodes(episodes: &[NewEpisode]) {
-    if episodes.is_empty() {
-        return;
-    };
-
-    info!("Indexing {} episodes.", episodes.len());
-    dbqueries::index_new_episodes(episodes)
-        .map_err(|err| {
-            error!("Failed batch indexng: {}", err);
-            info!("Fallign back to individual indexing.");
-        })
-        .unwrap_or_else(|_| {
-            episodes.iter().for_each(|ep| {
-                ep.index()
-                    .map_err(|err| error!("Error: {}.", err))
-                    .map_err(|_| error!("Failed to index episode: {:?}.", ep.title()))
-                    .ok();
-            });
-        })
-}
-
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
-    use rss::Channel;
 
     use crate::database::truncate_db;
     use crate::dbqueries;
+    use crate::models::{EpisodeWidgetModel, Save};
     use crate::utils::get_feed;
     use crate::Source;
 
+    use diesel::connection::SimpleConnection;
     use std::fs;
     use std::io::BufReader;
 
@@ -198,7 +307,7 @@ mod tests {
 
         // Index the channels
         for feed in feeds {
-            feed.index()?
+            feed.index()?;
         }
 
         // Assert the index rows equal the controlled results
@@ -231,7 +340,7 @@ mod tests {
         let feed = get_feed(path, 42);
         let pd = feed.parse_podcast().to_podcast()?;
 
-        feed.index_channel_items(pd)?;
+        feed.index_channel_items(&Database::global(), pd)?;
         assert_eq!(dbqueries::get_podcasts()?.len(), 1);
         assert_eq!(dbqueries::get_episodes()?.len(), 43);
         Ok(())
@@ -253,4 +362,252 @@ mod tests {
         assert_eq!(feed.parse_podcast(), pd);
         Ok(())
     }
+    #[test]
+    fn test_feed_index_formats() -> Result<()> {
+        truncate_db()?;
+
+        let paths = [
+            "tests/feeds/2026-10-18-PodcastNamespace.xml",
+            "tests/feeds/2026-10-18-PodcastNamespace-atom.xml",
+            "tests/feeds/2026-10-18-PodcastNamespace.json",
+        ];
+        for (id, path) in paths.iter().enumerate() {
+            get_feed(path, id as i32).index()?;
+        }
+
+        let shows = dbqueries::get_podcasts()?;
+        assert_eq!(shows.len(), 3);
+        assert_eq!(dbqueries::get_episodes()?.len(), 9);
+        for show in &shows {
+            assert_eq!(show.title(), "Podcast Namespace Sampler");
+            assert_eq!(
+                show.image_uri(),
+                Some("https://example.org/podcast/cover.jpg")
+            );
+
+            let episodes = dbqueries::get_pd_episodes(show)?;
+            assert_eq!(episodes.len(), 3);
+            assert_eq!(episodes[0].title(), "Episode 3: Chapters");
+            assert_eq!(episodes[0].guid(), Some("sampler-episode-3"));
+            assert_eq!(episodes[0].epoch(), 1792231200);
+            assert_eq!(episodes[0].duration(), Some(3031));
+            assert_eq!(
+                episodes[0].uri(),
+                Some("https://example.org/podcast/episode-3.mp3")
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_snapshots() -> Result<()> {
+        truncate_db()?;
+
+        get_feed("tests/feeds/2018-01-20-Intercepted.xml", 42).index()?;
+        assert_eq!(dbqueries::get_episodes()?.len(), 43);
+
+        // Reindexing a later snapshot only adds the new episodes
+        let summary = get_feed("tests/feeds/2018-02-03-Intercepted.xml", 42).index()?;
+        assert_eq!(summary.inserted().len(), 4);
+        // The publisher moved every enclosure to https in the meantime
+        assert_eq!(summary.updated(), 43);
+        assert_eq!(dbqueries::get_episodes()?.len(), 47);
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_retitled() -> Result<()> {
+        truncate_db()?;
+
+        get_feed("tests/feeds/2026-10-18-Retitled.xml", 42).index()?;
+        let pd = dbqueries::get_podcast_from_source_id(42)?;
+        let old = dbqueries::get_pd_episodes(&pd)?;
+        assert_eq!(old.len(), 3);
+
+        // Listen to the first episode and download the second one
+        let mut ep: EpisodeWidgetModel =
+            dbqueries::get_episode_from_pk("retitled-episode-1", pd.id())?.into();
+        ep.set_play_position(120)?;
+        let no_guid = "https://example.org/retitled/episode-2.mp3";
+        let mut ep: EpisodeWidgetModel = dbqueries::get_episode_from_pk(no_guid, pd.id())?.into();
+        ep.set_local_uri(Some("/tmp/episode-2.mp3"));
+        ep.save()?;
+        dbqueries::queue_episode(ep.rowid())?;
+
+        let summary = get_feed("tests/feeds/2026-10-18-Retitled-updated.xml", 42).index()?;
+        assert_eq!(summary.inserted().len(), 1);
+        assert_eq!(summary.updated(), 3);
+        let episodes = dbqueries::get_pd_episodes(&pd)?;
+        assert_eq!(episodes.len(), 4);
+        assert_eq!(episodes[0].title(), "Episode 4: Newcomer");
+        assert_eq!(summary.inserted(), [episodes[0].rowid()]);
+
+        // The retitled episodes kept their rows, and their state
+        for (ep, old) in episodes[1..].iter().zip(&old) {
+            assert_eq!(ep.rowid(), old.rowid());
+            assert_eq!(ep.identity(), old.identity());
+            assert_ne!(ep.title(), old.title());
+        }
+        assert_eq!(episodes[1].title(), "Episode 3: Re-Uploaded");
+        assert_eq!(
+            episodes[1].uri(),
+            Some("https://example.org/retitled/episode-3-fixed.mp3")
+        );
+        assert_eq!(episodes[2].identity(), no_guid);
+        assert_eq!(episodes[2].local_uri(), Some("/tmp/episode-2.mp3"));
+        assert_eq!(dbqueries::get_play_queue()?, vec![episodes[2].rowid()]);
+        assert_eq!(episodes[3].title(), "Episode 1: The Pilot");
+        assert_eq!(episodes[3].play_position(), 120);
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_removed_upstream() -> Result<()> {
+        truncate_db()?;
+
+        get_feed("tests/feeds/2026-10-18-Retitled-updated.xml", 42).index()?;
+        let pd = dbqueries::get_podcast_from_source_id(42)?;
+        let episodes = dbqueries::get_pd_episodes(&pd)?;
+        assert_eq!(episodes.len(), 4);
+        assert!(episodes.iter().all(|ep| ep.removed_upstream().is_none()));
+
+        // The older snapshot lacks the latest episode, as if it got pulled
+        get_feed("tests/feeds/2026-10-18-Retitled.xml", 42).index()?;
+        let episodes = dbqueries::get_pd_episodes(&pd)?;
+        assert_eq!(episodes.len(), 4);
+        assert_eq!(episodes[0].title(), "Episode 4: Newcomer");
+        assert!(episodes[0].removed_upstream().is_some());
+        assert!(episodes[1..]
+            .iter()
+            .all(|ep| ep.removed_upstream().is_none()));
+
+        // and it comes back when it is published again
+        get_feed("tests/feeds/2026-10-18-Retitled-updated.xml", 42).index()?;
+        let episodes = dbqueries::get_pd_episodes(&pd)?;
+        assert!(episodes.iter().all(|ep| ep.removed_upstream().is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_moved() -> Result<()> {
+        truncate_db()?;
+        let mut source = Source::from_url("https://example.org/moved/feed.xml")?;
+        source.set_http_etag(Some("\"v1\""));
+        let source = source.save()?;
+
+        // The lock keeps the feed where it is
+        let path = "tests/feeds/2026-10-18-Moved-locked.xml";
+        let summary = get_feed(path, source.id()).index()?;
+        assert_eq!(summary.moved_to(), None);
+        let source = dbqueries::get_source_from_id(source.id())?;
+        assert_eq!(source.uri(), "https://example.org/moved/feed.xml");
+
+        let path = "tests/feeds/2026-10-18-Moved.xml";
+        let summary = get_feed(path, source.id()).index()?;
+        let new_url = "https://new.example.org/moved/feed.xml";
+        assert_eq!(summary.moved_to(), Some(new_url));
+        let source = dbqueries::get_source_from_id(source.id())?;
+        assert_eq!(source.uri(), new_url);
+        assert_eq!(source.http_etag(), None);
+        // Reindexing the old feed from its new location does not move it again
+        let summary = get_feed(path, source.id()).index()?;
+        assert_eq!(summary.moved_to(), None);
+
+        // Moving onto another subscription is refused
+        let other = Source::from_url("https://other.example.org/moved/feed.xml")?;
+        let path = "tests/feeds/2026-10-18-Moved-podcast.xml";
+        let summary = get_feed(path, source.id()).index()?;
+        assert_eq!(summary.moved_to(), None);
+        dbqueries::remove_source(other.id())?;
+        let summary = get_feed(path, source.id()).index()?;
+        assert_eq!(summary.moved_to(), Some(other.uri()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_update_hints() -> Result<()> {
+        truncate_db()?;
+        let source = Source::from_url("https://example.org/hints/feed.xml")?;
+        let feed = get_feed("tests/feeds/2026-10-18-UpdateHints.xml", source.id());
+        let hints = feed.channel.update_hints();
+        feed.index()?;
+
+        let mut source = dbqueries::get_source_from_id(source.id())?;
+        assert_eq!(source.update_hints(), hints);
+        source.record_success();
+        assert!(source.next_attempt() >= chrono::Utc::now().timestamp() as i32 + 12 * 3600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_websub() -> Result<()> {
+        truncate_db()?;
+        let source = Source::from_url("https://example.org/websub/feed.xml")?;
+        get_feed("tests/feeds/2026-10-18-WebSub.xml", source.id()).index()?;
+
+        let source = dbqueries::get_source_from_id(source.id())?;
+        assert_eq!(source.websub_hub(), Some("https://hub.example.org/"));
+        assert_eq!(source.self_url(), Some("https://feeds.example.org/websub"));
+        // Hubs know the feed by its self url
+        let topic = dbqueries::get_source_from_topic("https://feeds.example.org/websub")?;
+        assert_eq!(topic.id(), source.id());
+        Ok(())
+    }
+
+    /// Make storing the episode titled `title` fail, on `event`.
+    fn fail_episode(db: &Database, event: &str, title: &str) -> Result<()> {
+        db.get()?.batch_execute(&format!(
+            "CREATE TRIGGER fail_{event} BEFORE {event} ON episodes WHEN NEW.title = '{title}' \
+             BEGIN SELECT RAISE(ABORT, 'injected failure'); END;"
+        ))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_failing_insert() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let db = Database::open(dir.path().join("podcasts.db"))?;
+        let feed = get_feed("tests/feeds/2026-10-18-Retitled.xml", 42);
+
+        fail_episode(&db, "INSERT", "Episode 2")?;
+        let err = feed.clone().index_in(&db).unwrap_err();
+        assert!(err.to_string().contains("injected failure"));
+        // Not even the show was stored
+        assert!(db.get_podcasts()?.is_empty());
+        assert!(db.get_episodes()?.is_empty());
+
+        db.get()?.batch_execute("DROP TRIGGER fail_INSERT")?;
+        let summary = feed.index_in(&db)?;
+        assert_eq!(summary.inserted().len(), 3);
+        assert_eq!((summary.updated(), summary.unchanged()), (0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_feed_index_failing_update() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let db = Database::open(dir.path().join("podcasts.db"))?;
+        get_feed("tests/feeds/2026-10-18-Retitled.xml", 42).index_in(&db)?;
+        let old = db.get_episodes()?;
+        let updated = get_feed("tests/feeds/2026-10-18-Retitled-updated.xml", 42);
+
+        // The last of the updated episodes fails, after the others
+        fail_episode(&db, "UPDATE", "Episode 1: The Pilot")?;
+        assert!(updated.clone().index_in(&db).is_err());
+        let episodes = db.get_episodes()?;
+        assert_eq!(episodes.len(), 3);
+        for (ep, old) in episodes.iter().zip(&old) {
+            assert_eq!(ep.title(), old.title());
+        }
+
+        db.get()?.batch_execute("DROP TRIGGER fail_UPDATE")?;
+        let summary = updated.clone().index_in(&db)?;
+        assert_eq!(summary.inserted().len(), 1);
+        assert_eq!((summary.updated(), summary.unchanged()), (3, 0));
+
+        let summary = updated.index_in(&db)?;
+        assert!(summary.inserted().is_empty());
+        assert_eq!((summary.updated(), summary.unchanged()), (0, 4));
+        Ok(())
+    }
 }
@@ -70,6 +70,7 @@ extern crate diesel;
 #[macro_use]
 extern crate log;
 
+pub mod archive;
 pub mod database;
 #[allow(missing_docs)]
 pub mod dbqueries;
@@ -78,16 +79,24 @@ pub mod downloader;
 #[allow(missing_docs)]
 pub mod errors;
 mod feed;
+#[cfg(any(test, feature = "mock-server"))]
+pub mod mock_server;
 pub(crate) mod models;
 pub mod opml;
 mod parser;
 pub mod pipeline;
 mod schema;
+pub mod syndication;
+pub mod transcript;
 pub mod utils;
 
-pub use crate::feed::{Feed, FeedBuilder};
+pub use crate::database::Database;
+pub use crate::feed::{Feed, FeedBuilder, IndexSummary};
 pub use crate::models::Save;
-pub use crate::models::{Episode, EpisodeWidgetModel, Show, ShowCoverModel, Source};
+pub use crate::models::{
+    AutoDownload, Chapter, DownloadState, Episode, EpisodeWidgetModel, FetchStats, QueuedDownload,
+    Retention, SearchHit, SearchResults, Show, ShowCoverModel, ShowSettings, Source,
+};
 
 // Set the user agent, See #53 for more
 // Keep this in sync with Tor-browser releases
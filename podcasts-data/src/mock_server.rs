@@ -0,0 +1,250 @@
+// mock_server.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A tiny HTTP server, to test the network code without the internet.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A canned response.
+#[derive(Debug, Clone)]
+pub(crate) struct Route {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Duration,
+    /// How much of the body to send before dropping the connection.
+    sent: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: HashMap<String, Route>,
+    hits: HashMap<String, usize>,
+    in_flight: usize,
+    max_in_flight: usize,
+}
+
+/// Serves `Route`s on a random local port until the test process exits.
+///
+/// A route with an `ETag` header answers `304 Not Modified` to requests
+/// carrying a matching `If-None-Match` header, and unknown paths get a `404`.
+/// `200` routes also answer `Range: bytes=start-` requests, as long as their
+/// `If-Range` matches the `ETag` or `Last-Modified` of the route.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    /// Start serving on a random local port.
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = shared.clone();
+                thread::spawn(move || handle(stream, &state));
+            }
+        });
+
+        MockServer { addr, state }
+    }
+
+    /// Get the absolute url of `path` on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Answer requests for `path` with the given response.
+    pub fn route(&self, path: &str, status: u16, headers: &[(&str, &str)], body: &[u8]) {
+        self.delayed_route(path, Duration::ZERO, status, headers, body)
+    }
+
+    /// Answer requests for `path` with the given response, after `delay`.
+    pub fn delayed_route(
+        &self,
+        path: &str,
+        delay: Duration,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) {
+        let route = Route {
+            status,
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_vec(),
+            delay,
+            sent: None,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.routes.insert(path.to_owned(), route);
+    }
+
+    /// Answer requests for `path` like `route`, but drop the connection
+    /// after sending `sent` bytes of the response body.
+    pub fn truncated_route(&self, path: &str, headers: &[(&str, &str)], body: &[u8], sent: usize) {
+        self.route(path, 200, headers, body);
+        let mut state = self.state.lock().unwrap();
+        if let Some(route) = state.routes.get_mut(path) {
+            route.sent = Some(sent);
+        }
+    }
+
+    /// Get the number of requests `path` received.
+    pub fn hits(&self, path: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.hits.get(path).copied().unwrap_or_default()
+    }
+
+    /// Get the highest number of requests that were served at the same time.
+    pub fn max_in_flight(&self) -> usize {
+        self.state.lock().unwrap().max_in_flight
+    }
+}
+
+fn handle(stream: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_owned();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.trim().is_empty() => break,
+            Ok(_) => {
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.insert(key.trim().to_ascii_lowercase(), value.trim().to_owned());
+                }
+            }
+        }
+    }
+
+    let route = {
+        let mut state = state.lock().unwrap();
+        *state.hits.entry(path.clone()).or_default() += 1;
+        state.in_flight += 1;
+        state.max_in_flight = state.max_in_flight.max(state.in_flight);
+        state.routes.get(&path).cloned()
+    };
+    let mut route = route.unwrap_or(Route {
+        status: 404,
+        headers: Vec::new(),
+        body: Vec::new(),
+        delay: Duration::ZERO,
+        sent: None,
+    });
+    thread::sleep(route.delay);
+
+    let etag = route
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("etag"))
+        .map(|(_, v)| v.as_str());
+    if etag.is_some() && etag == headers.get("if-none-match").map(String::as_str) {
+        route.status = 304;
+        route.body.clear();
+    }
+    if route.status == 200 {
+        if let Some(start) = range_start(&route, &headers) {
+            serve_range(&mut route, start);
+        }
+    }
+
+    let reason = http::StatusCode::from_u16(route.status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Unknown");
+    let mut response = format!("HTTP/1.1 {} {}\r\n", route.status, reason);
+    for (key, value) in &route.headers {
+        response.push_str(&format!("{key}: {value}\r\n"));
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        route.body.len()
+    ));
+    let sent = route.sent.unwrap_or(route.body.len()).min(route.body.len());
+
+    // Done before answering, so the client can't start another request first
+    state.lock().unwrap().in_flight -= 1;
+    let mut stream = reader.into_inner();
+    stream
+        .write_all(response.as_bytes())
+        .and_then(|_| stream.write_all(&route.body[..sent]))
+        .ok();
+}
+
+/// Get the offset of a `Range` request that `route` can answer.
+fn range_start(route: &Route, headers: &HashMap<String, String>) -> Option<usize> {
+    let start = headers
+        .get("range")?
+        .strip_prefix("bytes=")?
+        .strip_suffix('-')?
+        .parse()
+        .ok()?;
+    match headers.get("if-range") {
+        Some(validator) => route
+            .headers
+            .iter()
+            .filter(|(k, _)| {
+                k.eq_ignore_ascii_case("etag") || k.eq_ignore_ascii_case("last-modified")
+            })
+            .any(|(_, v)| v == validator)
+            .then_some(start),
+        None => Some(start),
+    }
+}
+
+fn serve_range(route: &mut Route, start: usize) {
+    let len = route.body.len();
+    if start >= len {
+        route.status = 416;
+        route
+            .headers
+            .push(("Content-Range".into(), format!("bytes */{len}")));
+        route.body.clear();
+    } else {
+        route.status = 206;
+        route.headers.push((
+            "Content-Range".into(),
+            format!("bytes {}-{}/{}", start, len - 1, len),
+        ));
+        route.body.drain(..start);
+    }
+}
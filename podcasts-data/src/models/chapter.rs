@@ -0,0 +1,98 @@
+// chapter.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::schema::episode_chapters;
+
+#[derive(Queryable, Identifiable, PartialEq)]
+#[diesel(table_name = episode_chapters)]
+#[diesel(primary_key(episode_id, start_time))]
+#[derive(Debug, Clone)]
+/// Diesel Model of the episode_chapters table.
+pub struct Chapter {
+    episode_id: i32,
+    start_time: i32,
+    end_time: Option<i32>,
+    title: Option<String>,
+    image_uri: Option<String>,
+    link: Option<String>,
+}
+
+impl Chapter {
+    /// Get the `rowid` of the `Episode` the chapter belongs to.
+    pub fn episode_id(&self) -> i32 {
+        self.episode_id
+    }
+
+    /// Get the `start_time` value.
+    ///
+    /// The number represents the offset from the start of the episode in seconds.
+    pub fn start_time(&self) -> i32 {
+        self.start_time
+    }
+
+    /// Get the `end_time` value.
+    ///
+    /// The number represents the offset from the start of the episode in seconds.
+    /// None means the chapter lasts until the next one starts.
+    pub fn end_time(&self) -> Option<i32> {
+        self.end_time
+    }
+
+    /// Get the `title` of the chapter.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Get the `image_uri` of the chapter artwork.
+    pub fn image_uri(&self) -> Option<&str> {
+        self.image_uri.as_deref()
+    }
+
+    /// Get the `link` the chapter refers to.
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = episode_chapters)]
+#[derive(Debug, Clone, Default, Builder, PartialEq)]
+#[builder(default)]
+#[builder(derive(Debug))]
+#[builder(setter(into))]
+pub(crate) struct NewChapter {
+    episode_id: i32,
+    start_time: i32,
+    end_time: Option<i32>,
+    title: Option<String>,
+    image_uri: Option<String>,
+    link: Option<String>,
+}
+
+// Ignore the following getters. They are used in unit tests mainly.
+impl NewChapter {
+    pub(crate) fn start_time(&self) -> i32 {
+        self.start_time
+    }
+
+    #[cfg(test)]
+    pub(crate) fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+}
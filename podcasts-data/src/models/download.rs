@@ -0,0 +1,332 @@
+// download.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::SaveChangesDsl;
+
+use crate::database::Database;
+use crate::errors::{DataError, DownloadError};
+use crate::models::Save;
+use crate::schema::download_queue;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How many times a failing download is retried before giving up.
+pub(crate) const MAX_DOWNLOAD_ATTEMPTS: i32 = 5;
+
+/// The state of an entry of the download queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadState {
+    /// Waiting for a free slot, or for the next retry.
+    Queued,
+    /// Currently being downloaded.
+    Running,
+    /// Stopped by the user, it won't start again on its own.
+    Paused,
+    /// Gave up after too many attempts, or on an unrecoverable error.
+    Failed,
+    /// The episode was downloaded successfully.
+    Done,
+}
+
+impl DownloadState {
+    fn as_str(self) -> &'static str {
+        match self {
+            DownloadState::Queued => "queued",
+            DownloadState::Running => "running",
+            DownloadState::Paused => "paused",
+            DownloadState::Failed => "failed",
+            DownloadState::Done => "done",
+        }
+    }
+}
+
+impl fmt::Display for DownloadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DownloadState {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queued" => Ok(DownloadState::Queued),
+            "running" => Ok(DownloadState::Running),
+            "paused" => Ok(DownloadState::Paused),
+            "failed" => Ok(DownloadState::Failed),
+            "done" => Ok(DownloadState::Done),
+            _ => Err(DataError::Bail(format!("Unknown download state: {s}"))),
+        }
+    }
+}
+
+#[derive(Queryable, Identifiable, AsChangeset, PartialEq)]
+#[diesel(table_name = download_queue)]
+#[diesel(primary_key(episode_id))]
+#[diesel(treat_none_as_null = true)]
+#[derive(Debug, Clone)]
+/// Diesel Model of the download_queue table.
+pub struct QueuedDownload {
+    episode_id: i32,
+    download_dir: String,
+    state: String,
+    attempts: i32,
+    next_attempt: i32,
+    error: Option<String>,
+    queued_at: i32,
+}
+
+impl Save<QueuedDownload> for QueuedDownload {
+    type Error = DataError;
+
+    /// Helper method to easily save/"sync" current state of self to the
+    /// Database.
+    fn save_in(&self, db: &Database) -> Result<QueuedDownload, Self::Error> {
+        let mut con = db.get()?;
+
+        self.save_changes::<QueuedDownload>(&mut *con)
+            .map_err(From::from)
+    }
+}
+
+impl QueuedDownload {
+    /// Get the `rowid` of the `Episode` to download.
+    pub fn episode_id(&self) -> i32 {
+        self.episode_id
+    }
+
+    /// Get the directory the episode is saved into.
+    pub fn download_dir(&self) -> &str {
+        &self.download_dir
+    }
+
+    /// Get the `DownloadState`.
+    ///
+    /// Unknown values are treated as `Failed`.
+    pub fn state(&self) -> DownloadState {
+        self.state.parse().unwrap_or(DownloadState::Failed)
+    }
+
+    /// Set the `DownloadState`.
+    pub fn set_state(&mut self, state: DownloadState) {
+        self.state = state.as_str().to_owned();
+    }
+
+    /// Get how many times the download has failed so far.
+    pub fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    /// Get the epoch before which the download should not be retried.
+    pub fn next_attempt(&self) -> i32 {
+        self.next_attempt
+    }
+
+    /// Get the error of the last failed attempt.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Get the epoch the download was queued at.
+    pub fn queued_at(&self) -> i32 {
+        self.queued_at
+    }
+
+    /// Give up on the download.
+    pub fn fail(&mut self, error: &str) {
+        self.error = Some(error.to_owned());
+        self.set_state(DownloadState::Failed);
+    }
+
+    /// Record a failed attempt.
+    ///
+    /// The download is queued again after an exponential backoff,
+    /// and is marked as `Failed` once it runs out of attempts.
+    /// Returns how long to wait until the retry, if any.
+    pub fn retry_later(&mut self, error: &str) -> Option<Duration> {
+        self.attempts += 1;
+        self.error = Some(error.to_owned());
+
+        if self.attempts >= MAX_DOWNLOAD_ATTEMPTS {
+            self.fail(error);
+            return None;
+        }
+
+        let delay = retry_delay(self.attempts);
+        self.next_attempt = Utc::now().timestamp() as i32 + delay.as_secs() as i32;
+        self.set_state(DownloadState::Queued);
+        Some(delay)
+    }
+
+    /// Update the state with the `result` of an attempt.
+    ///
+    /// Transient errors are retried with `retry_later`, any other error
+    /// fails the download.
+    /// Returns how long to wait until the retry, if any.
+    pub fn finish(&mut self, result: &Result<(), DownloadError>) -> Option<Duration> {
+        match result {
+            Ok(()) => {
+                self.set_state(DownloadState::Done);
+                None
+            }
+            Err(DownloadError::DownloadCancelled) => {
+                self.set_state(DownloadState::Paused);
+                None
+            }
+            Err(err) if err.is_transient() => {
+                warn!("Download of episode {} failed: {}", self.episode_id, err);
+                self.retry_later(&err.to_string())
+            }
+            Err(err) => {
+                error!("Download Failed: {}", err);
+                self.fail(&err.to_string());
+                None
+            }
+        }
+    }
+}
+
+/// The backoff before retrying a download that failed `attempts` times.
+///
+/// Starts at 30 seconds and doubles on every attempt, up to an hour.
+pub(crate) fn retry_delay(attempts: i32) -> Duration {
+    let exp = attempts.clamp(1, 8) as u32 - 1;
+    Duration::from_secs((30 * 2u64.pow(exp)).min(3600))
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = download_queue)]
+#[derive(Debug, Clone)]
+pub(crate) struct NewQueuedDownload<'a> {
+    episode_id: i32,
+    download_dir: &'a str,
+    state: &'static str,
+    queued_at: i32,
+}
+
+impl<'a> NewQueuedDownload<'a> {
+    pub(crate) fn new(episode_id: i32, download_dir: &'a str) -> Self {
+        NewQueuedDownload {
+            episode_id,
+            download_dir,
+            state: DownloadState::Queued.as_str(),
+            queued_at: Utc::now().timestamp() as i32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::truncate_db;
+    use crate::downloader::get_episode;
+    use crate::mock_server::MockServer;
+    use crate::pipeline::pipeline;
+    use crate::{dbqueries, Source};
+    use anyhow::Result;
+
+    #[test]
+    fn test_download_state() {
+        for state in [
+            DownloadState::Queued,
+            DownloadState::Running,
+            DownloadState::Paused,
+            DownloadState::Failed,
+            DownloadState::Done,
+        ] {
+            assert_eq!(state.to_string().parse::<DownloadState>().unwrap(), state);
+        }
+        assert!("bogus".parse::<DownloadState>().is_err());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1).as_secs(), 30);
+        assert_eq!(retry_delay(2).as_secs(), 60);
+        assert_eq!(retry_delay(4).as_secs(), 240);
+        assert_eq!(retry_delay(100).as_secs(), 3600);
+    }
+
+    #[test]
+    fn test_truncated_download_is_retried() -> Result<()> {
+        truncate_db()?;
+        let server = MockServer::start();
+        let feed = format!(
+            "<rss version=\"2.0\"><channel><title>Truncated</title>\
+             <link>https://example.org</link><description>Flaky</description>\
+             <item><title>Pilot</title><guid>pilot</guid>\
+             <enclosure url=\"{}\" length=\"100\" type=\"audio/ogg\"/></item>\
+             </channel></rss>",
+            server.url("/pilot.ogg")
+        );
+        server.route("/feed.xml", 200, &[], feed.as_bytes());
+        let source = Source::from_url(&server.url("/feed.xml"))?;
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(pipeline(vec![source.clone()]))?;
+
+        let pd = dbqueries::get_podcast_from_source_id(source.id())?;
+        let id = dbqueries::get_pd_episodes(&pd)?[0].rowid();
+        let dir = tempfile::TempDir::new()?;
+        let dir = dir.path().to_str().unwrap();
+        let mut download = dbqueries::enqueue_download(id, dir)?;
+        let attempt = |download: &mut QueuedDownload| -> Result<Option<Duration>> {
+            let mut ep = dbqueries::get_episode_widget_from_rowid(id)?;
+            let result = rt.block_on(get_episode(&mut ep, dir, None));
+            let retry = download.finish(&result);
+            download.save()?;
+            Ok(retry)
+        };
+
+        // The connection drops half way through
+        let body: Vec<u8> = (0..100).collect();
+        let headers = [("ETag", "\"v1\""), ("Content-Type", "audio/ogg")];
+        server.truncated_route("/pilot.ogg", &headers, &body, 30);
+        assert_eq!(attempt(&mut download)?, Some(Duration::from_secs(30)));
+
+        // The server only sends part of what is left
+        let headers = [
+            ("ETag", "\"v1\""),
+            ("Content-Type", "audio/ogg"),
+            ("Content-Range", "bytes 30-99/100"),
+        ];
+        server.route("/pilot.ogg", 206, &headers, &body[30..60]);
+        assert_eq!(attempt(&mut download)?, Some(Duration::from_secs(60)));
+        let queued = dbqueries::get_queued_download(id)?;
+        assert_eq!(queued.state(), DownloadState::Queued);
+        assert_eq!(queued.attempts(), 2);
+        assert_eq!(queued.error(), Some("Downloaded 60 bytes out of 100."));
+        let now = Utc::now().timestamp() as i32;
+        assert!(queued.next_attempt() > now);
+        assert!(dbqueries::get_due_downloads(now, 10)?.is_empty());
+
+        // And the retry resumes from there
+        let headers = [("ETag", "\"v1\""), ("Content-Type", "audio/ogg")];
+        server.route("/pilot.ogg", 200, &headers, &body);
+        assert_eq!(attempt(&mut download)?, None);
+        assert_eq!(download.state(), DownloadState::Done);
+        let path = dbqueries::get_episode_local_uri_from_id(id)?.unwrap();
+        assert_eq!(std::fs::read(path)?, body);
+        Ok(())
+    }
+}
@@ -22,7 +22,7 @@ use chrono::prelude::*;
 use diesel::prelude::*;
 use diesel::SaveChangesDsl;
 
-use crate::database::connection;
+use crate::database::Database;
 use crate::errors::DataError;
 use crate::models::{Save, Show};
 use crate::schema::episodes;
@@ -30,7 +30,7 @@ use crate::schema::episodes;
 #[derive(Queryable, Identifiable, AsChangeset, Associations, PartialEq)]
 #[diesel(table_name = episodes)]
 #[diesel(treat_none_as_null = true)]
-#[diesel(primary_key(title, show_id))]
+#[diesel(primary_key(identity, show_id))]
 #[diesel(belongs_to(Show, foreign_key = show_id))]
 #[derive(Debug, Clone)]
 /// Diesel Model of the episode table.
@@ -45,8 +45,13 @@ pub struct Episode {
     duration: Option<i32>,
     play_position: i32,
     guid: Option<String>,
+    identity: String,
     played: Option<i32>,
     show_id: i32,
+    chapters_uri: Option<String>,
+    transcript_uri: Option<String>,
+    transcript_type: Option<String>,
+    removed_upstream: Option<i32>,
 }
 
 impl Save<Episode> for Episode {
@@ -54,11 +59,10 @@ impl Save<Episode> for Episode {
 
     /// Helper method to easily save/"sync" current state of self to the
     /// Database.
-    fn save(&self) -> Result<Episode, Self::Error> {
-        let db = connection();
+    fn save_in(&self, db: &Database) -> Result<Episode, Self::Error> {
         let mut tempdb = db.get()?;
 
-        self.save_changes::<Episode>(&mut tempdb)
+        self.save_changes::<Episode>(&mut *tempdb)
             .map_err(From::from)
     }
 }
@@ -99,6 +103,13 @@ impl Episode {
         self.guid.as_deref()
     }
 
+    /// Get the key that identifies the Episode within its show.
+    ///
+    /// This is the `guid`, falling back to the `uri` and then the `title`.
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
     /// Get the `epoch` value.
     ///
     /// Retrieved from the rss Item publish date.
@@ -140,6 +151,37 @@ impl Episode {
     pub fn play_position(&self) -> i32 {
         self.play_position
     }
+
+    /// Get the `chapters_uri`.
+    ///
+    /// Represents the url of the Podcasting 2.0 chapters file of the episode,
+    /// as advertised by the `<podcast:chapters>` tag.
+    pub fn chapters_uri(&self) -> Option<&str> {
+        self.chapters_uri.as_deref()
+    }
+
+    /// Get the `transcript_uri`.
+    ///
+    /// Represents the url of the transcript of the episode,
+    /// as advertised by the `<podcast:transcript>` tag.
+    pub fn transcript_uri(&self) -> Option<&str> {
+        self.transcript_uri.as_deref()
+    }
+
+    /// Get the `transcript_type`.
+    ///
+    /// The mime type of the file `transcript_uri` points to.
+    pub fn transcript_type(&self) -> Option<&str> {
+        self.transcript_type.as_deref()
+    }
+
+    /// Epoch representation of when the episode was found missing from
+    /// its feed.
+    ///
+    /// None/Null for episodes that are still published.
+    pub fn removed_upstream(&self) -> Option<i32> {
+        self.removed_upstream
+    }
 }
 
 #[derive(Queryable, AsChangeset, PartialEq)]
@@ -159,6 +201,7 @@ pub struct EpisodeWidgetModel {
     played: Option<i32>,
     play_position: i32,
     show_id: i32,
+    removed_upstream: Option<i32>,
 }
 
 impl From<Episode> for EpisodeWidgetModel {
@@ -174,6 +217,7 @@ impl From<Episode> for EpisodeWidgetModel {
             played: e.played,
             play_position: e.play_position,
             show_id: e.show_id,
+            removed_upstream: e.removed_upstream,
         }
     }
 }
@@ -183,15 +227,14 @@ impl Save<usize> for EpisodeWidgetModel {
 
     /// Helper method to easily save/"sync" current state of self to the
     /// Database.
-    fn save(&self) -> Result<usize, Self::Error> {
+    fn save_in(&self, db: &Database) -> Result<usize, Self::Error> {
         use crate::schema::episodes::dsl::*;
 
-        let db = connection();
         let mut tempdb = db.get()?;
 
         diesel::update(episodes.filter(rowid.eq(self.rowid)))
             .set(self)
-            .execute(&mut tempdb)
+            .execute(&mut *tempdb)
             .map_err(From::from)
     }
 }
@@ -271,6 +314,14 @@ impl EpisodeWidgetModel {
         self.show_id
     }
 
+    /// Epoch representation of when the episode was found missing from
+    /// its feed.
+    ///
+    /// None/Null for episodes that are still published.
+    pub fn removed_upstream(&self) -> Option<i32> {
+        self.removed_upstream
+    }
+
     /// Sets the `played` value with the current `epoch` timestap and save it.
     pub fn set_played_now(&mut self) -> Result<(), DataError> {
         let epoch = Utc::now().timestamp() as i32;
@@ -323,15 +374,14 @@ impl Save<usize> for EpisodeCleanerModel {
 
     /// Helper method to easily save/"sync" current state of self to the
     /// Database.
-    fn save(&self) -> Result<usize, Self::Error> {
+    fn save_in(&self, db: &Database) -> Result<usize, Self::Error> {
         use crate::schema::episodes::dsl::*;
 
-        let db = connection();
         let mut tempdb = db.get()?;
 
         diesel::update(episodes.filter(rowid.eq(self.rowid)))
             .set(self)
-            .execute(&mut tempdb)
+            .execute(&mut *tempdb)
             .map_err(From::from)
     }
 }
@@ -394,6 +444,9 @@ pub struct EpisodeMinimal {
     play_position: i32,
     guid: Option<String>,
     show_id: i32,
+    chapters_uri: Option<String>,
+    transcript_uri: Option<String>,
+    transcript_type: Option<String>,
 }
 
 impl From<Episode> for EpisodeMinimal {
@@ -408,6 +461,9 @@ impl From<Episode> for EpisodeMinimal {
             duration: e.duration,
             play_position: e.play_position,
             show_id: e.show_id,
+            chapters_uri: e.chapters_uri,
+            transcript_uri: e.transcript_uri,
+            transcript_type: e.transcript_type,
         }
     }
 }
@@ -461,4 +517,17 @@ impl EpisodeMinimal {
     pub fn show_id(&self) -> i32 {
         self.show_id
     }
+    /// Get the `chapters_uri`.
+    pub fn chapters_uri(&self) -> Option<&str> {
+        self.chapters_uri.as_deref()
+    }
+    /// Get the `transcript_uri`.
+    pub fn transcript_uri(&self) -> Option<&str> {
+        self.transcript_uri.as_deref()
+    }
+
+    /// Get the `transcript_type`.
+    pub fn transcript_type(&self) -> Option<&str> {
+        self.transcript_type.as_deref()
+    }
 }
@@ -21,26 +21,38 @@ mod new_episode;
 mod new_show;
 mod new_source;
 
+mod chapter;
+mod download;
 mod episode;
+mod search;
 mod show;
+mod show_settings;
 mod source;
 
+use crate::database::{connection, Database};
+
 // use futures::prelude::*;
 // use futures::future::*;
 
+pub(crate) use self::chapter::{NewChapter, NewChapterBuilder};
+pub(crate) use self::download::NewQueuedDownload;
 pub(crate) use self::episode::EpisodeCleanerModel;
-pub(crate) use self::new_episode::{NewEpisode, NewEpisodeMinimal};
+pub(crate) use self::new_episode::{NewEpisode, NewEpisodeBuilder, NewEpisodeMinimal};
 pub(crate) use self::new_show::NewShow;
 pub(crate) use self::new_source::NewSource;
+pub(crate) use self::search::{fts_query, MATCH_END, MATCH_START};
+pub(crate) use self::source::Fetched;
 
-#[cfg(test)]
-pub(crate) use self::new_episode::NewEpisodeBuilder;
 #[cfg(test)]
 pub(crate) use self::new_show::NewShowBuilder;
 
+pub use self::chapter::Chapter;
+pub use self::download::{DownloadState, QueuedDownload};
 pub use self::episode::{Episode, EpisodeMinimal, EpisodeWidgetModel};
+pub use self::search::{SearchHit, SearchResults};
 pub use self::show::{Show, ShowCoverModel};
-pub use self::source::Source;
+pub use self::show_settings::{AutoDownload, Retention, ShowSettings};
+pub use self::source::{FetchStats, Source};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum IndexState<T> {
@@ -52,27 +64,36 @@ pub enum IndexState<T> {
 pub trait Insert<T> {
     type Error;
 
-    fn insert(&self) -> Result<T, Self::Error>;
+    fn insert_in(&self, db: &Database) -> Result<T, Self::Error>;
 }
 
 pub trait Update<T> {
     type Error;
 
-    fn update(&self, _: i32) -> Result<T, Self::Error>;
+    fn update_in(&self, db: &Database, _: i32) -> Result<T, Self::Error>;
 }
 
 // This might need to change in the future
 pub trait Index<T>: Insert<T> + Update<T> {
     type Error;
 
-    fn index(&self) -> Result<T, <Self as Index<T>>::Error>;
+    fn index_in(&self, db: &Database) -> Result<T, <Self as Index<T>>::Error>;
+
+    fn index(&self) -> Result<T, <Self as Index<T>>::Error> {
+        self.index_in(&connection())
+    }
 }
 
 /// FIXME: DOCS
 pub trait Save<T> {
     /// The Error type to be returned.
     type Error;
+    /// Save/"sync" the current state of a diesel model to `db`.
+    fn save_in(&self, db: &Database) -> Result<T, Self::Error>;
+
     /// Helper method to easily save/"sync" current state of a diesel model to
     /// the Database.
-    fn save(&self) -> Result<T, Self::Error>;
+    fn save(&self) -> Result<T, Self::Error> {
+        self.save_in(&connection())
+    }
 }
@@ -18,14 +18,13 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use diesel::prelude::*;
-use rfc822_sanitizer::parse_from_rfc2822_with_fallback as parse_rfc822;
 
-use crate::database::connection;
+use crate::database::Database;
 use crate::dbqueries;
 use crate::errors::DataError;
 use crate::models::{Episode, EpisodeMinimal, Index, Insert, Update};
-use crate::parser;
 use crate::schema::episodes;
+use crate::syndication::Item;
 use crate::utils::url_cleaner;
 
 #[derive(Insertable, AsChangeset)]
@@ -40,10 +39,14 @@ pub(crate) struct NewEpisode {
     description: Option<String>,
     length: Option<i32>,
     duration: Option<i32>,
-    play_position: i32,
     guid: Option<String>,
+    #[builder(default = "self.default_identity()")]
+    identity: String,
     epoch: i32,
     show_id: i32,
+    chapters_uri: Option<String>,
+    transcript_uri: Option<String>,
+    transcript_type: Option<String>,
 }
 
 impl From<NewEpisodeMinimal> for NewEpisode {
@@ -55,6 +58,10 @@ impl From<NewEpisodeMinimal> for NewEpisode {
             .epoch(e.epoch)
             .show_id(e.show_id)
             .guid(e.guid)
+            .identity(e.identity)
+            .chapters_uri(e.chapters_uri)
+            .transcript_uri(e.transcript_uri)
+            .transcript_type(e.transcript_type)
             .build()
             .unwrap()
     }
@@ -63,15 +70,14 @@ impl From<NewEpisodeMinimal> for NewEpisode {
 impl Insert<()> for NewEpisode {
     type Error = DataError;
 
-    fn insert(&self) -> Result<(), DataError> {
+    fn insert_in(&self, db: &Database) -> Result<(), DataError> {
         use crate::schema::episodes::dsl::*;
-        let db = connection();
         let mut con = db.get()?;
 
         info!("Inserting {:?}", self.title);
         diesel::insert_into(episodes)
             .values(self)
-            .execute(&mut con)
+            .execute(&mut *con)
             .map_err(From::from)
             .map(|_| ())
     }
@@ -80,15 +86,14 @@ impl Insert<()> for NewEpisode {
 impl Update<()> for NewEpisode {
     type Error = DataError;
 
-    fn update(&self, episode_id: i32) -> Result<(), DataError> {
+    fn update_in(&self, db: &Database, episode_id: i32) -> Result<(), DataError> {
         use crate::schema::episodes::dsl::*;
-        let db = connection();
         let mut con = db.get()?;
 
         info!("Updating {:?}", self.title);
         diesel::update(episodes.filter(rowid.eq(episode_id)))
             .set(self)
-            .execute(&mut con)
+            .execute(&mut *con)
             .map_err(From::from)
             .map(|_| ())
     }
@@ -99,19 +104,19 @@ impl Index<()> for NewEpisode {
 
     // Does not update the episode description if it's the only thing that has
     // changed.
-    fn index(&self) -> Result<(), DataError> {
-        let exists = dbqueries::episode_exists(self.title(), self.show_id())?;
+    fn index_in(&self, db: &Database) -> Result<(), DataError> {
+        let exists = db.episode_exists(self.identity(), self.show_id())?;
 
         if exists {
-            let other = dbqueries::get_episode_minimal_from_pk(self.title(), self.show_id())?;
+            let other = db.get_episode_minimal_from_pk(self.identity(), self.show_id())?;
 
             if self != &other {
-                self.update(other.rowid())
+                self.update_in(db, other.rowid())
             } else {
                 Ok(())
             }
         } else {
-            self.insert()
+            self.insert_in(db)
         }
     }
 }
@@ -124,6 +129,9 @@ impl PartialEq<EpisodeMinimal> for NewEpisode {
             && (self.epoch() == other.epoch())
             && (self.guid() == other.guid())
             && (self.show_id() == other.show_id())
+            && (self.chapters_uri() == other.chapters_uri())
+            && (self.transcript_uri() == other.transcript_uri())
+            && (self.transcript_type() == other.transcript_type())
     }
 }
 
@@ -132,26 +140,28 @@ impl PartialEq<Episode> for NewEpisode {
         (self.title() == other.title())
             && (self.uri() == other.uri())
             && (self.duration() == other.duration())
-            && (self.play_position() == other.play_position())
             && (self.epoch() == other.epoch())
             && (self.guid() == other.guid())
             && (self.show_id() == other.show_id())
             && (self.description() == other.description())
             && (self.length() == other.length())
+            && (self.chapters_uri() == other.chapters_uri())
+            && (self.transcript_uri() == other.transcript_uri())
+            && (self.transcript_type() == other.transcript_type())
     }
 }
 
 impl NewEpisode {
-    /// Parses an `rss::Item` into a `NewEpisode` Struct.
+    /// Parses an `Item` into a `NewEpisode` Struct.
     #[allow(dead_code)]
-    pub(crate) fn new(item: &rss::Item, show_id: i32) -> Result<Self, DataError> {
+    pub(crate) fn new(item: &Item, show_id: i32) -> Result<Self, DataError> {
         NewEpisodeMinimal::new(item, show_id).map(|ep| ep.into_new_episode(item))
     }
 
     #[allow(dead_code)]
     pub(crate) fn to_episode(&self) -> Result<Episode, DataError> {
         self.index()?;
-        dbqueries::get_episode_from_pk(&self.title, self.show_id).map_err(From::from)
+        dbqueries::get_episode_from_pk(&self.identity, self.show_id).map_err(From::from)
     }
 }
 
@@ -173,6 +183,10 @@ impl NewEpisode {
         self.guid.as_deref()
     }
 
+    pub(crate) fn identity(&self) -> &str {
+        &self.identity
+    }
+
     pub(crate) fn epoch(&self) -> i32 {
         self.epoch
     }
@@ -181,10 +195,6 @@ impl NewEpisode {
         self.duration
     }
 
-    pub(crate) fn play_position(&self) -> i32 {
-        self.play_position
-    }
-
     pub(crate) fn length(&self) -> Option<i32> {
         self.length
     }
@@ -192,6 +202,18 @@ impl NewEpisode {
     pub(crate) fn show_id(&self) -> i32 {
         self.show_id
     }
+
+    pub(crate) fn chapters_uri(&self) -> Option<&str> {
+        self.chapters_uri.as_deref()
+    }
+
+    pub(crate) fn transcript_uri(&self) -> Option<&str> {
+        self.transcript_uri.as_deref()
+    }
+
+    pub(crate) fn transcript_type(&self) -> Option<&str> {
+        self.transcript_type.as_deref()
+    }
 }
 
 #[derive(Insertable, AsChangeset)]
@@ -204,11 +226,17 @@ pub(crate) struct NewEpisodeMinimal {
     uri: Option<String>,
     length: Option<i32>,
     duration: Option<i32>,
-    #[builder(default = "0")]
-    play_position: i32,
     epoch: i32,
     guid: Option<String>,
+    #[builder(default = "self.default_identity()")]
+    identity: String,
     show_id: i32,
+    #[builder(default)]
+    chapters_uri: Option<String>,
+    #[builder(default)]
+    transcript_uri: Option<String>,
+    #[builder(default)]
+    transcript_type: Option<String>,
 }
 
 impl PartialEq<EpisodeMinimal> for NewEpisodeMinimal {
@@ -219,11 +247,14 @@ impl PartialEq<EpisodeMinimal> for NewEpisodeMinimal {
             && (self.epoch() == other.epoch())
             && (self.guid() == other.guid())
             && (self.show_id() == other.show_id())
+            && (self.chapters_uri() == other.chapters_uri())
+            && (self.transcript_uri() == other.transcript_uri())
+            && (self.transcript_type() == other.transcript_type())
     }
 }
 
 impl NewEpisodeMinimal {
-    pub(crate) fn new(item: &rss::Item, parent_id: i32) -> Result<Self, DataError> {
+    pub(crate) fn new(item: &Item, parent_id: i32) -> Result<Self, DataError> {
         if item.title().is_none() {
             let err = DataError::ParseEpisodeError {
                 reason: "No title specified for this Episode.".into(),
@@ -234,7 +265,7 @@ impl NewEpisodeMinimal {
         }
 
         let title = item.title().unwrap().trim().to_owned();
-        let guid = item.guid().map(|s| s.value().trim().to_owned());
+        let guid = item.guid().map(|s| s.trim().to_owned());
 
         // Get the mime type, the `http` url and the length from the enclosure
         // http://www.rssboard.org/rss-specification#ltenclosuregtSubelementOfLtitemgt
@@ -243,11 +274,11 @@ impl NewEpisodeMinimal {
         // Get the url
         let uri = enc
             .map(|s| url_cleaner(s.url().trim()))
-            // Fallback to Rss.Item.link if enclosure is None.
+            // Fallback to Item.link if enclosure is None.
             .or_else(|| item.link().map(|s| url_cleaner(s.trim())));
 
         // Get the size of the content, it should be in bytes
-        let length = enc.and_then(|x| x.length().parse().ok());
+        let length = enc.and_then(|x| x.length());
 
         // If url is still None return an Error as this behaviour is not
         // compliant with the RSS Spec.
@@ -260,13 +291,15 @@ impl NewEpisodeMinimal {
             return Err(err);
         };
 
-        // Default to rfc2822 representation of epoch 0.
-        let date = parse_rfc822(item.pub_date().unwrap_or("Thu, 1 Jan 1970 00:00:00 +0000"));
-        // Should treat information from the rss feeds as invalid by default.
-        // Case: "Thu, 05 Aug 2016 06:00:00 -0400" <-- Actually that was friday.
-        let epoch = date.map(|x| x.timestamp() as i32).unwrap_or(0);
+        // Default to epoch 0.
+        let epoch = item.published().map(|x| x.timestamp() as i32).unwrap_or(0);
 
-        let duration = parser::parse_itunes_duration(item.itunes_ext());
+        let duration = item.duration();
+        let chapters_uri = item.chapters_uri().map(str::to_owned);
+        let (transcript_uri, transcript_type) = item
+            .transcript()
+            .map(|(url, mime)| (url.to_owned(), mime.to_owned()))
+            .unzip();
 
         NewEpisodeMinimalBuilder::default()
             .title(title)
@@ -276,13 +309,16 @@ impl NewEpisodeMinimal {
             .epoch(epoch)
             .guid(guid)
             .show_id(parent_id)
+            .chapters_uri(chapters_uri)
+            .transcript_uri(transcript_uri)
+            .transcript_type(transcript_type)
             .build()
             .map_err(|err| DataError::BuilderError(format!("{err}")))
     }
 
     // TODO: TryInto is stabilizing in rustc v1.26!
     // ^ Jokes on you past self!
-    pub(crate) fn into_new_episode(self, item: &rss::Item) -> NewEpisode {
+    pub(crate) fn into_new_episode(self, item: &Item) -> NewEpisode {
         let description = item.description().map(|s| {
             let sanitized_html = ammonia::Builder::new()
                 // Remove `rel` attributes from `<a>` tags
@@ -299,8 +335,12 @@ impl NewEpisodeMinimal {
             .epoch(self.epoch)
             .show_id(self.show_id)
             .guid(self.guid)
+            .identity(self.identity)
             .length(self.length)
             .description(description)
+            .chapters_uri(self.chapters_uri)
+            .transcript_uri(self.transcript_uri)
+            .transcript_type(self.transcript_type)
             .build()
             .unwrap()
     }
@@ -320,6 +360,10 @@ impl NewEpisodeMinimal {
         self.guid.as_deref()
     }
 
+    pub(crate) fn identity(&self) -> &str {
+        &self.identity
+    }
+
     pub(crate) fn duration(&self) -> Option<i32> {
         self.duration
     }
@@ -331,6 +375,49 @@ impl NewEpisodeMinimal {
     pub(crate) fn show_id(&self) -> i32 {
         self.show_id
     }
+
+    pub(crate) fn chapters_uri(&self) -> Option<&str> {
+        self.chapters_uri.as_deref()
+    }
+
+    pub(crate) fn transcript_uri(&self) -> Option<&str> {
+        self.transcript_uri.as_deref()
+    }
+
+    pub(crate) fn transcript_type(&self) -> Option<&str> {
+        self.transcript_type.as_deref()
+    }
+}
+
+/// The key an episode is told apart by within its show.
+///
+/// Titles get edited by publishers, so prefer the `guid`, then the
+/// `uri` of the media file, and only fall back to the `title`.
+fn episode_identity(guid: Option<&str>, uri: Option<&str>, title: &str) -> String {
+    guid.filter(|s| !s.is_empty())
+        .or_else(|| uri.filter(|s| !s.is_empty()))
+        .unwrap_or(title)
+        .to_owned()
+}
+
+impl NewEpisodeBuilder {
+    fn default_identity(&self) -> String {
+        episode_identity(
+            self.guid.as_ref().and_then(Option::as_deref),
+            self.uri.as_ref().and_then(Option::as_deref),
+            self.title.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+impl NewEpisodeMinimalBuilder {
+    fn default_identity(&self) -> String {
+        episode_identity(
+            self.guid.as_ref().and_then(Option::as_deref),
+            self.uri.as_ref().and_then(Option::as_deref),
+            self.title.as_deref().unwrap_or_default(),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -342,7 +429,7 @@ mod tests {
     use anyhow::Result;
     use once_cell::sync::Lazy;
 
-    use rss::Channel;
+    use crate::syndication::Channel;
 
     use std::fs::File;
     use std::io::BufReader;
@@ -601,8 +688,8 @@ mod tests {
 
         let episode = channel.items().iter().nth(14).unwrap();
         let new_ep = NewEpisode::new(episode, 42)?;
-        new_ep.insert()?;
-        let ep = dbqueries::get_episode_from_pk(new_ep.title(), new_ep.show_id())?;
+        new_ep.insert_in(&Database::global())?;
+        let ep = dbqueries::get_episode_from_pk(new_ep.identity(), new_ep.show_id())?;
 
         assert_eq!(new_ep, ep);
         assert_eq!(&new_ep, &*EXPECTED_INTERCEPTED_1);
@@ -610,8 +697,8 @@ mod tests {
 
         let episode = channel.items().iter().nth(15).unwrap();
         let new_ep = NewEpisode::new(episode, 42)?;
-        new_ep.insert()?;
-        let ep = dbqueries::get_episode_from_pk(new_ep.title(), new_ep.show_id())?;
+        new_ep.insert_in(&Database::global())?;
+        let ep = dbqueries::get_episode_from_pk(new_ep.identity(), new_ep.show_id())?;
 
         assert_eq!(new_ep, ep);
         assert_eq!(&new_ep, &*EXPECTED_INTERCEPTED_2);
@@ -625,8 +712,8 @@ mod tests {
         let old = EXPECTED_INTERCEPTED_1.clone().to_episode()?;
 
         let updated = &*UPDATED_DURATION_INTERCEPTED_1;
-        updated.update(old.rowid())?;
-        let new = dbqueries::get_episode_from_pk(old.title(), old.show_id())?;
+        updated.update_in(&Database::global(), old.rowid())?;
+        let new = dbqueries::get_episode_from_pk(old.identity(), old.show_id())?;
 
         // Assert that updating does not change the rowid and show_id
         assert_ne!(old, new);
@@ -648,7 +735,7 @@ mod tests {
         // Second identical, This should take the early return path
         assert!(expected.index().is_ok());
         // Get the episode
-        let old = dbqueries::get_episode_from_pk(expected.title(), expected.show_id())?;
+        let old = dbqueries::get_episode_from_pk(expected.identity(), expected.show_id())?;
         // Assert that NewPodcast is equal to the Indexed one
         assert_eq!(*expected, old);
 
@@ -657,7 +744,7 @@ mod tests {
         // Update the podcast
         assert!(updated.index().is_ok());
         // Get the new Podcast
-        let new = dbqueries::get_episode_from_pk(expected.title(), expected.show_id())?;
+        let new = dbqueries::get_episode_from_pk(expected.identity(), expected.show_id())?;
         // Assert it's diff from the old one.
         assert_ne!(new, old);
         assert_eq!(*updated, new);
@@ -672,8 +759,8 @@ mod tests {
 
         // Assert insert() produces the same result that you would get with to_podcast()
         truncate_db()?;
-        expected.insert()?;
-        let old = dbqueries::get_episode_from_pk(expected.title(), expected.show_id())?;
+        expected.insert_in(&Database::global())?;
+        let old = dbqueries::get_episode_from_pk(expected.identity(), expected.show_id())?;
         let ep = expected.to_episode()?;
         assert_eq!(old, ep);
 
@@ -681,8 +768,8 @@ mod tests {
         truncate_db()?;
         let ep = expected.to_episode()?;
         // This should error as a unique constrain violation
-        assert!(expected.insert().is_err());
-        let old = dbqueries::get_episode_from_pk(expected.title(), expected.show_id())?;
+        assert!(expected.insert_in(&Database::global()).is_err());
+        let old = dbqueries::get_episode_from_pk(expected.identity(), expected.show_id())?;
         assert_eq!(old, ep);
         Ok(())
     }
@@ -23,9 +23,9 @@ use crate::errors::DataError;
 use crate::models::Show;
 use crate::models::{Index, Insert, Update};
 use crate::schema::shows;
+use crate::syndication::Channel;
 
-use crate::database::connection;
-use crate::dbqueries;
+use crate::database::Database;
 use crate::utils::{calculate_hash, u64_to_vec_u8, url_cleaner};
 
 #[cfg(test)]
@@ -52,14 +52,13 @@ pub(crate) struct NewShow {
 impl Insert<()> for NewShow {
     type Error = DataError;
 
-    fn insert(&self) -> Result<(), Self::Error> {
+    fn insert_in(&self, db: &Database) -> Result<(), Self::Error> {
         use crate::schema::shows::dsl::*;
-        let db = connection();
         let mut con = db.get()?;
 
         diesel::insert_into(shows)
             .values(self)
-            .execute(&mut con)
+            .execute(&mut *con)
             .map(|_| ())
             .map_err(From::from)
     }
@@ -68,15 +67,14 @@ impl Insert<()> for NewShow {
 impl Update<()> for NewShow {
     type Error = DataError;
 
-    fn update(&self, show_id: i32) -> Result<(), Self::Error> {
+    fn update_in(&self, db: &Database, show_id: i32) -> Result<(), Self::Error> {
         use crate::schema::shows::dsl::*;
-        let db = connection();
         let mut con = db.get()?;
 
         info!("Updating {}", self.title);
         diesel::update(shows.filter(id.eq(show_id)))
             .set(self)
-            .execute(&mut con)
+            .execute(&mut *con)
             .map(|_| ())
             .map_err(From::from)
     }
@@ -87,19 +85,19 @@ impl Update<()> for NewShow {
 impl Index<()> for NewShow {
     type Error = DataError;
 
-    fn index(&self) -> Result<(), DataError> {
-        let exists = dbqueries::podcast_exists(self.source_id)?;
+    fn index_in(&self, db: &Database) -> Result<(), DataError> {
+        let exists = db.podcast_exists(self.source_id)?;
 
         if exists {
-            let other = dbqueries::get_podcast_from_source_id(self.source_id)?;
+            let other = db.get_podcast_from_source_id(self.source_id)?;
 
             if self != &other {
-                self.update(other.id())
+                self.update_in(db, other.id())
             } else {
                 Ok(())
             }
         } else {
-            self.insert()
+            self.insert_in(db)
         }
     }
 }
@@ -125,8 +123,8 @@ impl PartialEq<Show> for NewShow {
 }
 
 impl NewShow {
-    /// Parses a `rss::Channel` into a `NewShow` Struct.
-    pub(crate) fn new(chan: &rss::Channel, source_id: i32) -> NewShow {
+    /// Parses a `Channel` into a `NewShow` Struct.
+    pub(crate) fn new(chan: &Channel, source_id: i32) -> NewShow {
         let title = chan.title().trim();
         let link = url_cleaner(chan.link().trim());
 
@@ -136,14 +134,18 @@ impl NewShow {
             .clean(chan.description().trim())
             .to_string();
 
-        // Try to get the itunes img first
-        let itunes_img = chan
-            .itunes_ext()
-            .and_then(|s| s.image().map(|url| url.trim()))
-            .map(|s| s.to_owned());
-        // If itunes is None, try to get the channel.image from the rss spec
-        let image_uri = itunes_img.or_else(|| chan.image().map(|s| s.url().trim().to_owned()));
+        let image_uri = chan.image_uri().map(|s| s.to_owned());
+        NewShow::from_metadata(title, &link, &description, image_uri, source_id)
+    }
 
+    /// Create a `NewShow` out of already cleaned up metadata.
+    pub(crate) fn from_metadata(
+        title: &str,
+        link: &str,
+        description: &str,
+        image_uri: Option<String>,
+        source_id: i32,
+    ) -> NewShow {
         let mut hash: Option<Vec<u8>> = None;
         if let Some(i) = &image_uri {
             hash = Some(u64_to_vec_u8(calculate_hash(i)));
@@ -162,9 +164,14 @@ impl NewShow {
     }
 
     // Look out for when tryinto lands into stable.
+    #[cfg(test)]
     pub(crate) fn to_podcast(&self) -> Result<Show, DataError> {
-        self.index()?;
-        dbqueries::get_podcast_from_source_id(self.source_id).map_err(From::from)
+        self.to_podcast_in(&Database::global())
+    }
+
+    pub(crate) fn to_podcast_in(&self, db: &Database) -> Result<Show, DataError> {
+        self.index_in(db)?;
+        db.get_podcast_from_source_id(self.source_id)
     }
 }
 
@@ -207,13 +214,14 @@ impl NewShow {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dbqueries;
 
     use anyhow::Result;
     use once_cell::sync::Lazy;
-    use rss::Channel;
 
     use crate::database::truncate_db;
     use crate::models::NewShowBuilder;
+    use crate::syndication::Channel;
 
     use std::fs::File;
     use std::io::BufReader;
@@ -407,7 +415,7 @@ mod tests {
         let channel = Channel::read_from(BufReader::new(file))?;
 
         let npd = NewShow::new(&channel, 42);
-        npd.insert()?;
+        npd.insert_in(&Database::global())?;
         let pd = dbqueries::get_podcast_from_source_id(42)?;
 
         assert_eq!(npd, pd);
@@ -426,7 +434,7 @@ mod tests {
         let old = EXPECTED_INTERCEPTED.to_podcast()?;
 
         let updated = &*UPDATED_DESC_INTERCEPTED;
-        updated.update(old.id())?;
+        updated.update_in(&Database::global(), old.id())?;
         let new = dbqueries::get_podcast_from_source_id(42)?;
 
         assert_ne!(old, new);
@@ -467,7 +475,7 @@ mod tests {
     fn test_to_podcast() -> Result<()> {
         // Assert insert() produces the same result that you would get with to_podcast()
         truncate_db()?;
-        EXPECTED_INTERCEPTED.insert()?;
+        EXPECTED_INTERCEPTED.insert_in(&Database::global())?;
         let old = dbqueries::get_podcast_from_source_id(42)?;
         let pd = EXPECTED_INTERCEPTED.to_podcast()?;
         assert_eq!(old, pd);
@@ -476,7 +484,7 @@ mod tests {
         truncate_db()?;
         let pd = EXPECTED_INTERCEPTED.to_podcast()?;
         // This should error as a unique constrain violation
-        assert!(EXPECTED_INTERCEPTED.insert().is_err());
+        assert!(EXPECTED_INTERCEPTED.insert_in(&Database::global()).is_err());
         let old = dbqueries::get_podcast_from_source_id(42)?;
         assert_eq!(old, pd);
         Ok(())
@@ -20,8 +20,7 @@
 use diesel::prelude::*;
 use url::Url;
 
-use crate::database::connection;
-use crate::dbqueries;
+use crate::database::{connection, Database};
 // use models::{Insert, Update};
 use crate::errors::DataError;
 use crate::models::Source;
@@ -48,21 +47,24 @@ impl NewSource {
         }
     }
 
-    pub(crate) fn insert_or_ignore(&self) -> Result<(), DataError> {
+    fn insert_or_ignore_in(&self, db: &Database) -> Result<(), DataError> {
         use crate::schema::source::dsl::*;
-        let db = connection();
         let mut con = db.get()?;
 
         diesel::insert_or_ignore_into(source)
             .values(self)
-            .execute(&mut con)
+            .execute(&mut *con)
             .map(|_| ())
             .map_err(From::from)
     }
 
     // Look out for when tryinto lands into stable.
     pub(crate) fn to_source(&self) -> Result<Source, DataError> {
-        self.insert_or_ignore()?;
-        dbqueries::get_source_from_uri(&self.uri).map_err(From::from)
+        self.to_source_in(&connection())
+    }
+
+    pub(crate) fn to_source_in(&self, db: &Database) -> Result<Source, DataError> {
+        self.insert_or_ignore_in(db)?;
+        db.get_source_from_uri(&self.uri)
     }
 }
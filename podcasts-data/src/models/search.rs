@@ -0,0 +1,153 @@
+// search.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Hits of a full-text search over shows and episodes.
+
+use crate::models::{EpisodeWidgetModel, Show};
+
+/// Marks the start of a matching term in the raw snippets of SQLite.
+pub(crate) const MATCH_START: char = '\u{E000}';
+/// Marks the end of a matching term in the raw snippets of SQLite.
+pub(crate) const MATCH_END: char = '\u{E001}';
+
+/// A show or an episode that matched a search.
+#[derive(Debug, Clone)]
+pub struct SearchHit<T> {
+    item: T,
+    snippet: String,
+}
+
+impl<T> SearchHit<T> {
+    pub(crate) fn new(item: T, raw_snippet: &str) -> Self {
+        SearchHit {
+            item,
+            snippet: clean_snippet(raw_snippet),
+        }
+    }
+
+    /// Get the show or episode that matched.
+    pub fn item(&self) -> &T {
+        &self.item
+    }
+
+    /// Get the text around the match.
+    ///
+    /// The text is escaped and the matching terms are wrapped in `<b>` tags,
+    /// so it can be used as Pango markup.
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+/// The results of `dbqueries::search`, best matches first.
+#[derive(Debug, Clone, Default)]
+pub struct SearchResults {
+    pub(crate) shows: Vec<SearchHit<Show>>,
+    pub(crate) episodes: Vec<SearchHit<EpisodeWidgetModel>>,
+}
+
+impl SearchResults {
+    /// Get the matching shows.
+    pub fn shows(&self) -> &[SearchHit<Show>] {
+        &self.shows
+    }
+
+    /// Get the matching episodes.
+    pub fn episodes(&self) -> &[SearchHit<EpisodeWidgetModel>] {
+        &self.episodes
+    }
+
+    /// Whether nothing matched.
+    pub fn is_empty(&self) -> bool {
+        self.shows.is_empty() && self.episodes.is_empty()
+    }
+}
+
+/// Turn what the user typed into an FTS5 query.
+///
+/// Every word has to match, either fully or as a prefix, and the FTS5
+/// syntax is escaped. Returns `None` if there is nothing to search for.
+pub(crate) fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Strip the html out of a raw snippet and turn the match markers into
+/// `<b>` tags, making sure they stay balanced.
+fn clean_snippet(raw: &str) -> String {
+    let text = ammonia::Builder::empty().clean(raw).to_string();
+    // Pango doesn't know about html entities
+    let text = text.replace("&nbsp;", " ");
+
+    let mut snippet = String::with_capacity(text.len());
+    let mut open = false;
+    for word in text.split_whitespace() {
+        if !snippet.is_empty() {
+            snippet.push(' ');
+        }
+        for c in word.chars() {
+            match c {
+                MATCH_START if !open => {
+                    snippet.push_str("<b>");
+                    open = true;
+                }
+                MATCH_END if open => {
+                    snippet.push_str("</b>");
+                    open = false;
+                }
+                MATCH_START | MATCH_END => (),
+                c => snippet.push(c),
+            }
+        }
+    }
+    if open {
+        snippet.push_str("</b>");
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("  "), None);
+        assert_eq!(fts_query("rust gnome"), Some("\"rust\"* \"gnome\"*".into()));
+        // Operators and quotes are searched for literally
+        assert_eq!(
+            fts_query("say \"NOT\" OR"),
+            Some("\"say\"* \"\"\"NOT\"\"\"* \"OR\"*".into())
+        );
+    }
+
+    #[test]
+    fn test_clean_snippet() {
+        let raw = format!(
+            "<p>Rust &amp; {MATCH_START}GNOME{MATCH_END}\n apps</p><a href=\"{MATCH_START}x\">"
+        );
+        assert_eq!(clean_snippet(&raw), "Rust &amp; <b>GNOME</b> apps");
+
+        let raw = format!("1 &lt; 2 {MATCH_START}open");
+        assert_eq!(clean_snippet(&raw), "1 &lt; 2 <b>open</b>");
+    }
+}
@@ -105,7 +105,7 @@ impl Show {
 
         diesel::update(shows.filter(id.eq(self.id)))
             .set(image_uri_hash.eq(&hash))
-            .execute(&mut con)
+            .execute(&mut *con)
             .map(|_| ())
             .map_err(From::from)
     }
@@ -117,7 +117,7 @@ impl Show {
         let mut con = db.get()?;
         diesel::update(shows.filter(id.eq(self.id)))
             .set(image_cached.eq(Utc::now().naive_utc()))
-            .execute(&mut con)
+            .execute(&mut *con)
             .map(|_| ())
             .map_err(From::from)
     }
@@ -217,7 +217,7 @@ impl ShowCoverModel {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database::truncate_db;
+    use crate::database::{truncate_db, Database};
     use crate::dbqueries;
     use crate::models::{Insert, NewShow, NewShowBuilder, Update};
     use anyhow::Result;
@@ -266,7 +266,7 @@ mod tests {
     fn should_update_timestamp_when_update_image_cached_is_called_after_the_timestamp_has_expired(
     ) -> Result<()> {
         truncate_db()?;
-        EXPECTED_INTERCEPTED.insert()?;
+        EXPECTED_INTERCEPTED.insert_in(&Database::global())?;
         let show = EXPECTED_INTERCEPTED.to_podcast()?;
         let original_timestamp = show.image_cached();
         show.update_image_cached().unwrap();
@@ -289,11 +289,11 @@ mod tests {
     fn should_update_hash_when_update_image_uri_hash_is_called_when_the_hash_is_invalid(
     ) -> Result<()> {
         truncate_db()?;
-        EXPECTED_INTERCEPTED.insert()?;
+        EXPECTED_INTERCEPTED.insert_in(&Database::global())?;
         let original = EXPECTED_INTERCEPTED.to_podcast()?;
         let original_hash: u64 = 2965280433145069220;
         let updated = &*UPDATED_IMAGE_URI_INTERCEPTED;
-        updated.update(original.id())?;
+        updated.update_in(&Database::global(), original.id())?;
         let show = dbqueries::get_podcast_from_id(original.id())?;
 
         let not_yet_updated_hash = updated.image_uri_hash().unwrap();
@@ -315,7 +315,7 @@ mod tests {
     fn should_update_timestamp_only_when_update_image_cached_values_is_called_after_the_timestamp_has_expired(
     ) -> Result<()> {
         truncate_db()?;
-        EXPECTED_INTERCEPTED.insert()?;
+        EXPECTED_INTERCEPTED.insert_in(&Database::global())?;
         let show = EXPECTED_INTERCEPTED.to_podcast()?;
         let original_timestamp = show.image_cached();
         show.update_image_cache_values().unwrap();
@@ -336,11 +336,11 @@ mod tests {
     fn should_update_timestamp_and_hash_when_update_image_cached_values_is_called_when_hash_is_invalid(
     ) -> Result<()> {
         truncate_db()?;
-        EXPECTED_INTERCEPTED.insert()?;
+        EXPECTED_INTERCEPTED.insert_in(&Database::global())?;
         let original = EXPECTED_INTERCEPTED.to_podcast()?;
         let original_timestamp = original.image_cached();
         let updated = &*UPDATED_IMAGE_URI_INTERCEPTED;
-        updated.update(original.id())?;
+        updated.update_in(&Database::global(), original.id())?;
         let show = dbqueries::get_podcast_from_id(original.id())?;
 
         let not_yet_updated_hash = show.image_uri_hash().unwrap();
@@ -0,0 +1,291 @@
+// show_settings.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use diesel::prelude::*;
+
+use crate::database::Database;
+use crate::errors::DataError;
+use crate::models::{EpisodeMinimal, Save};
+use crate::schema::show_settings;
+
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Reverse;
+
+/// What to do with the new episodes of a show, when its feed is refreshed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoDownload {
+    /// Leave them alone.
+    Off,
+    /// Download up to that many of the most recent new episodes.
+    Latest(u32),
+    /// Download every new episode.
+    AllNew,
+}
+
+/// How long downloaded episodes are kept around after being played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Retention {
+    /// Follow the global cleanup setting.
+    Default,
+    /// Never delete them.
+    Forever,
+    /// Delete them after that many days.
+    Days(u32),
+}
+
+/// The range of supported playback rates.
+const PLAYBACK_RATES: (f64, f64) = (0.5, 3.0);
+
+#[derive(Queryable, Insertable, Identifiable, AsChangeset, PartialEq)]
+#[diesel(table_name = show_settings)]
+#[diesel(primary_key(show_id))]
+#[diesel(treat_none_as_null = true)]
+#[derive(Debug, Clone)]
+/// Diesel Model of the show_settings table.
+pub struct ShowSettings {
+    show_id: i32,
+    auto_download: String,
+    auto_download_limit: i32,
+    playback_rate: Option<f64>,
+    skip_intro: i32,
+    skip_outro: i32,
+    retention: String,
+    retention_days: i32,
+}
+
+impl Save<ShowSettings> for ShowSettings {
+    type Error = DataError;
+
+    /// Helper method to easily save/"sync" current state of self to the
+    /// Database.
+    ///
+    /// The row is created if the show didn't have settings yet.
+    fn save_in(&self, db: &Database) -> Result<ShowSettings, Self::Error> {
+        use crate::schema::show_settings::dsl::*;
+        let mut con = db.get()?;
+
+        diesel::replace_into(show_settings)
+            .values(self)
+            .execute(&mut *con)?;
+        Ok(self.clone())
+    }
+}
+
+impl ShowSettings {
+    /// The default settings of a show.
+    pub(crate) fn new(show_id: i32) -> Self {
+        ShowSettings {
+            show_id,
+            auto_download: "off".to_owned(),
+            auto_download_limit: 1,
+            playback_rate: None,
+            skip_intro: 0,
+            skip_outro: 0,
+            retention: "default".to_owned(),
+            retention_days: 7,
+        }
+    }
+
+    /// Get the `Show` id the settings belong to.
+    pub fn show_id(&self) -> i32 {
+        self.show_id
+    }
+
+    /// Get the `AutoDownload` policy.
+    ///
+    /// Unknown values are treated as `Off`.
+    pub fn auto_download(&self) -> AutoDownload {
+        match self.auto_download.as_str() {
+            "latest" => AutoDownload::Latest(self.auto_download_limit.max(0) as u32),
+            "all" => AutoDownload::AllNew,
+            _ => AutoDownload::Off,
+        }
+    }
+
+    /// Set the `AutoDownload` policy.
+    pub fn set_auto_download(&mut self, policy: AutoDownload) {
+        let value = match policy {
+            AutoDownload::Off => "off",
+            AutoDownload::Latest(limit) => {
+                self.auto_download_limit = limit as i32;
+                "latest"
+            }
+            AutoDownload::AllNew => "all",
+        };
+        self.auto_download = value.to_owned();
+    }
+
+    /// Get the playback rate of the show, if it overrides the global one.
+    pub fn playback_rate(&self) -> Option<f64> {
+        self.playback_rate
+    }
+
+    /// Set the playback rate of the show, `None` to use the global one.
+    ///
+    /// The rate is clamped to the range the player supports.
+    pub fn set_playback_rate(&mut self, rate: Option<f64>) {
+        self.playback_rate = rate.map(|r| r.clamp(PLAYBACK_RATES.0, PLAYBACK_RATES.1));
+    }
+
+    /// Get how many seconds of intro to skip.
+    pub fn skip_intro(&self) -> u32 {
+        self.skip_intro.max(0) as u32
+    }
+
+    /// Set how many seconds of intro to skip.
+    pub fn set_skip_intro(&mut self, seconds: u32) {
+        self.skip_intro = seconds as i32;
+    }
+
+    /// Get how many seconds of outro to skip.
+    pub fn skip_outro(&self) -> u32 {
+        self.skip_outro.max(0) as u32
+    }
+
+    /// Set how many seconds of outro to skip.
+    pub fn set_skip_outro(&mut self, seconds: u32) {
+        self.skip_outro = seconds as i32;
+    }
+
+    /// Get the `Retention` policy of played episodes.
+    ///
+    /// Unknown values are treated as `Default`.
+    pub fn retention(&self) -> Retention {
+        match self.retention.as_str() {
+            "forever" => Retention::Forever,
+            "days" => Retention::Days(self.retention_days.max(0) as u32),
+            _ => Retention::Default,
+        }
+    }
+
+    /// Set the `Retention` policy of played episodes.
+    pub fn set_retention(&mut self, policy: Retention) {
+        let value = match policy {
+            Retention::Default => "default",
+            Retention::Forever => "forever",
+            Retention::Days(days) => {
+                self.retention_days = days as i32;
+                "days"
+            }
+        };
+        self.retention = value.to_owned();
+    }
+
+    /// Pick which of the newly indexed episodes should be downloaded.
+    ///
+    /// Returns their `rowid`s, most recent first.
+    pub fn auto_downloads(&self, new_episodes: &[EpisodeMinimal]) -> Vec<i32> {
+        let mut episodes: Vec<_> = new_episodes
+            .iter()
+            .filter(|ep| ep.show_id() == self.show_id && ep.uri().is_some())
+            .collect();
+        episodes.sort_by_key(|ep| Reverse(ep.epoch()));
+
+        let limit = match self.auto_download() {
+            AutoDownload::Off => 0,
+            AutoDownload::Latest(limit) => limit as usize,
+            AutoDownload::AllNew => episodes.len(),
+        };
+        episodes
+            .into_iter()
+            .take(limit)
+            .map(EpisodeMinimal::rowid)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    use crate::database::truncate_db;
+    use crate::dbqueries;
+    use crate::utils::get_feed;
+    use crate::Source;
+
+    #[test]
+    fn test_auto_downloads() -> Result<()> {
+        truncate_db()?;
+        let path = "tests/feeds/2018-01-20-Intercepted.xml";
+        let url = "https://web.archive.org/web/20180120083840if_/https://feeds.feedburner.\
+                   com/InterceptedWithJeremyScahill";
+        let source = Source::from_url(url)?;
+        let summary = get_feed(path, source.id()).index()?;
+        let new = summary.inserted();
+        assert_eq!(new.len(), 43);
+        // Nothing is new the second time around
+        assert!(get_feed(path, source.id()).index()?.inserted().is_empty());
+
+        let show = dbqueries::get_podcast_from_source_id(source.id())?;
+        let episodes = dbqueries::get_episodes_minimal_from_rowids(new)?;
+        let mut settings = dbqueries::get_show_settings(show.id())?;
+        assert_eq!(settings.auto_download(), AutoDownload::Off);
+        assert!(settings.auto_downloads(&episodes).is_empty());
+
+        settings.set_auto_download(AutoDownload::Latest(2));
+        settings.save()?;
+        let settings = dbqueries::get_show_settings(show.id())?;
+        assert_eq!(settings.auto_download(), AutoDownload::Latest(2));
+        let latest = settings.auto_downloads(&episodes);
+        let newest = episodes.iter().map(|ep| ep.epoch()).max().unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(
+            dbqueries::get_episode_from_rowid(latest[0])?.epoch(),
+            newest
+        );
+
+        let mut settings = settings;
+        settings.set_auto_download(AutoDownload::AllNew);
+        assert_eq!(settings.auto_downloads(&episodes).len(), 43);
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_settings() -> Result<()> {
+        truncate_db()?;
+        let mut settings = dbqueries::get_show_settings(1)?;
+        assert_eq!(settings, ShowSettings::new(1));
+        assert_eq!(settings.retention(), Retention::Default);
+
+        settings.set_playback_rate(Some(10.0));
+        settings.set_skip_intro(30);
+        settings.set_skip_outro(90);
+        settings.set_retention(Retention::Days(14));
+        settings.save()?;
+
+        let settings = dbqueries::get_show_settings(1)?;
+        assert_eq!(settings.playback_rate(), Some(3.0));
+        assert_eq!(settings.skip_intro(), 30);
+        assert_eq!(settings.skip_outro(), 90);
+        assert_eq!(settings.retention(), Retention::Days(14));
+
+        let mut settings = settings;
+        settings.set_playback_rate(None);
+        settings.set_retention(Retention::Forever);
+        settings.save()?;
+        let settings = dbqueries::get_show_settings(1)?;
+        assert_eq!(settings.playback_rate(), None);
+        assert_eq!(settings.retention(), Retention::Forever);
+        Ok(())
+    }
+}
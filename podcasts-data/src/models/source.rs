@@ -18,26 +18,30 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use diesel::SaveChangesDsl;
-use rss::Channel;
 use url::Url;
 
 use http::header::{
-    HeaderValue, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION,
-    USER_AGENT as USER_AGENT_HEADER,
+    HeaderValue, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, ETAG,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION, USER_AGENT as USER_AGENT_HEADER,
 };
 use http::StatusCode;
 
 use base64::engine::general_purpose;
 use base64::prelude::*;
 
-use crate::database::connection;
+use crate::database::Database;
 use crate::errors::*;
 use crate::feed::{Feed, FeedBuilder};
 use crate::models::{NewSource, Save};
 use crate::schema::source;
+use crate::syndication::{Channel, FeedFormat, UpdateHints};
 use crate::USER_AGENT;
 
+use chrono::Utc;
+
+use std::io::Read;
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Queryable, Identifiable, AsChangeset, PartialEq)]
 #[diesel(table_name = source)]
@@ -49,6 +53,20 @@ pub struct Source {
     uri: String,
     last_modified: Option<String>,
     http_etag: Option<String>,
+    failures: i32,
+    last_error: Option<String>,
+    last_success: Option<i32>,
+    next_attempt: i32,
+    gone: Option<i32>,
+    update_interval: Option<i32>,
+    skip_hours: i32,
+    skip_days: i32,
+    websub_hub: Option<String>,
+    self_url: Option<String>,
+    fetches: i32,
+    not_modified: i32,
+    bytes_fetched: i64,
+    last_fetch_ms: Option<i32>,
 }
 
 impl Save<Source> for Source {
@@ -56,11 +74,10 @@ impl Save<Source> for Source {
 
     /// Helper method to easily save/"sync" current state of self to the
     /// Database.
-    fn save(&self) -> Result<Source, Self::Error> {
-        let db = connection();
+    fn save_in(&self, db: &Database) -> Result<Source, Self::Error> {
         let mut con = db.get()?;
 
-        self.save_changes::<Source>(&mut con).map_err(From::from)
+        self.save_changes::<Source>(&mut *con).map_err(From::from)
     }
 }
 
@@ -105,6 +122,103 @@ impl Source {
         self.http_etag = value.map(|x| x.to_string());
     }
 
+    /// Get the number of refreshes that failed in a row.
+    pub fn failures(&self) -> i32 {
+        self.failures
+    }
+
+    /// Get the error of the last failed refresh, if it failed.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Get the epoch of the last successful refresh.
+    pub fn last_success(&self) -> Option<i32> {
+        self.last_success
+    }
+
+    /// Get the epoch before which the `Source` should not be refreshed again.
+    pub fn next_attempt(&self) -> i32 {
+        self.next_attempt
+    }
+
+    /// Get the epoch at which the server first answered `410 Gone`,
+    /// meaning the feed was deleted for good.
+    pub fn gone(&self) -> Option<i32> {
+        self.gone
+    }
+
+    /// Get how often the publisher asked for the feed to be refreshed.
+    pub fn update_hints(&self) -> UpdateHints {
+        UpdateHints::new(self.update_interval, self.skip_hours, self.skip_days)
+    }
+
+    /// Set the `UpdateHints` of the feed.
+    pub(crate) fn set_update_hints(&mut self, hints: UpdateHints) {
+        self.update_interval = hints.interval();
+        self.skip_hours = hints.skip_hours();
+        self.skip_days = hints.skip_days();
+    }
+
+    /// Get the url of the WebSub hub that announces the updates of the feed.
+    pub fn websub_hub(&self) -> Option<&str> {
+        self.websub_hub.as_deref()
+    }
+
+    /// Get the canonical url the feed declares for itself.
+    pub fn self_url(&self) -> Option<&str> {
+        self.self_url.as_deref()
+    }
+
+    /// Set the WebSub hub and the self url of the feed.
+    pub(crate) fn set_websub(&mut self, hub: Option<&str>, self_url: Option<&str>) {
+        self.websub_hub = hub.map(str::to_owned);
+        self.self_url = self_url.map(str::to_owned);
+    }
+
+    /// Account for a request that got the feed, or found it unchanged.
+    ///
+    /// This method does not sync the state of self in the database, call
+    /// .save() method explicitly
+    pub(crate) fn record_fetch(&mut self, bytes: usize, elapsed: Duration, not_modified: bool) {
+        self.fetches += 1;
+        if not_modified {
+            self.not_modified += 1;
+        }
+        self.bytes_fetched += bytes as i64;
+        self.last_fetch_ms = Some(elapsed.as_millis().min(i32::MAX as u128) as i32);
+    }
+
+    /// Record a successful refresh, and schedule the next one according
+    /// to the `UpdateHints` of the feed.
+    ///
+    /// This method does not sync the state of self in the database, call
+    /// .save() method explicitly
+    pub(crate) fn record_success(&mut self) {
+        let now = Utc::now().timestamp();
+        self.failures = 0;
+        self.last_error = None;
+        self.last_success = Some(now as i32);
+        self.next_attempt = self.update_hints().next_due(now) as i32;
+        self.gone = None;
+    }
+
+    /// Record a failed refresh, and back off from refreshing again.
+    ///
+    /// This method does not sync the state of self in the database, call
+    /// .save() method explicitly
+    pub(crate) fn record_failure(&mut self, error: &str, gone: bool) {
+        let now = Utc::now().timestamp() as i32;
+        self.failures += 1;
+        self.last_error = Some(error.to_owned());
+        self.next_attempt = now + refresh_backoff(self.failures).as_secs() as i32;
+        if !gone {
+            self.gone = None;
+        } else if self.gone.is_none() {
+            self.gone = Some(now);
+        }
+    }
+
     /// Extract Etag and LastModifier from res, and update self and the
     /// corresponding db row.
     fn update_etag(mut self, res: &reqwest::Response) -> Result<Self, DataError> {
@@ -131,7 +245,7 @@ impl Source {
     /// Clear the `HTTP` `Etag` and `Last-modified` headers.
     /// This method does not sync the state of self in the database, call
     /// .save() method explicitly
-    fn clear_etags(&mut self) {
+    pub(crate) fn clear_etags(&mut self) {
         debug!("Source etags before clear: {:#?}", &self);
         self.http_etag = None;
         self.last_modified = None;
@@ -176,22 +290,24 @@ impl Source {
         match code.as_u16() {
             304 => {
                 info!("304: Source, (id: {}), is up to date", self.id());
-                return Err(DataError::FeedNotModified(self));
+                return Err(DataError::FeedNotModified(Box::new(self)));
             }
             301 | 308 => {
                 info!("Feed was moved permanently.");
                 self = self.update_url(&res)?;
-                return Err(DataError::FeedRedirect(self));
+                return Err(DataError::FeedRedirect(Box::new(self)));
             }
             302 | 307 => {
                 info!("302/307: Temporary Redirect.");
-                return Err(DataError::FeedRedirect(self));
+                return Err(DataError::FeedRedirect(Box::new(self)));
             }
             401 => return Err(self.make_err("401: Unauthorized.", code)),
             403 => return Err(self.make_err("403: Forbidden.", code)),
             404 => return Err(self.make_err("404: Not found.", code)),
             408 => return Err(self.make_err("408: Request Timeout.", code)),
             410 => return Err(self.make_err("410: Feed was deleted..", code)),
+            400..=499 => return Err(self.make_err("Client error.", code)),
+            500..=599 => return Err(self.make_err("Server error.", code)),
             _ => info!("HTTP StatusCode: {}", code),
         };
 
@@ -239,16 +355,43 @@ impl Source {
     /// Consumes `self` and Returns the corresponding `Feed` Object.
     // Refactor into TryInto once it lands on stable.
     pub async fn into_feed(self, client: &reqwest::Client) -> Result<Feed, DataError> {
+        self.fetch(client).await.and_then(|fetched| fetched.feed)
+    }
+
+    /// Like `into_feed`, but also returns the url the `Feed` was served
+    /// from, if the request got redirected, and the size of its body.
+    ///
+    /// Once the body is received, failing to parse it is reported in the
+    /// `feed` of the `Fetched`, so that its size is still known.
+    pub(crate) async fn fetch(self, client: &reqwest::Client) -> Result<Fetched, DataError> {
         let id = self.id();
+        let requested = Url::parse(self.uri()).ok();
 
         let resp = self.get_response(client).await?;
-        let chan = response_to_channel(resp).await?;
-
-        FeedBuilder::default()
-            .channel(chan)
-            .source_id(id)
-            .build()
-            .map_err(|err| DataError::BuilderError(format!("{err}")))
+        let redirect = Some(resp.url())
+            .filter(|url| Some(*url) != requested.as_ref())
+            .map(Url::to_string);
+        let content_type = header_value(&resp, CONTENT_TYPE);
+        let encoding = header_value(&resp, CONTENT_ENCODING);
+        let body = resp.bytes().await?;
+
+        let feed = decode_body(encoding.as_deref(), &body)
+            .and_then(|data| {
+                let format = FeedFormat::detect(content_type.as_deref(), &data);
+                Channel::parse(&data, format)
+            })
+            .and_then(|chan| {
+                FeedBuilder::default()
+                    .channel(chan)
+                    .source_id(id)
+                    .build()
+                    .map_err(|err| DataError::BuilderError(format!("{err}")))
+            });
+        Ok(Fetched {
+            feed,
+            redirect,
+            bytes: body.len(),
+        })
     }
 
     async fn get_response(self, client: &reqwest::Client) -> Result<reqwest::Response, DataError> {
@@ -259,7 +402,7 @@ impl Source {
                 Err(err) => match err {
                     DataError::FeedRedirect(s) => {
                         info!("Following redirect...");
-                        source = s;
+                        source = *s;
                     }
                     e => return Err(e),
                 },
@@ -287,6 +430,8 @@ impl Source {
 
         // Set the UserAgent cause ppl still seem to check it for some reason...
         req = req.header(USER_AGENT_HEADER, HeaderValue::from_static(USER_AGENT));
+        // Decompressed by `decode_body`, so that the size on the wire is known
+        req = req.header(ACCEPT_ENCODING, HeaderValue::from_static("gzip, br"));
 
         if let Some(etag) = self.http_etag() {
             req = req.header(IF_NONE_MATCH, HeaderValue::from_str(etag).unwrap());
@@ -301,14 +446,97 @@ impl Source {
     }
 }
 
-async fn response_to_channel(res: reqwest::Response) -> Result<Channel, DataError> {
-    use bytes::buf::Buf;
-    let chunk = res.bytes().await?;
+/// The backoff before refreshing a `Source` that failed `failures` times in a row.
+///
+/// Starts at an hour and doubles on every failure, up to a week.
+pub(crate) fn refresh_backoff(failures: i32) -> Duration {
+    let exp = failures.clamp(1, 9) as u32 - 1;
+    Duration::from_secs((3600 * 2u64.pow(exp)).min(7 * 24 * 3600))
+}
+
+/// A `Feed` as it was fetched from its `Source`.
+#[derive(Debug)]
+pub(crate) struct Fetched {
+    pub(crate) feed: Result<Feed, DataError>,
+    pub(crate) redirect: Option<String>,
+    /// The size of the body as it was transferred, before decompressing it.
+    pub(crate) bytes: usize,
+}
+
+/// How the requests for the feed of a `Source` went.
+#[derive(Queryable, Debug, Clone, PartialEq, Eq)]
+pub struct FetchStats {
+    source_id: i32,
+    uri: String,
+    fetches: i32,
+    not_modified: i32,
+    bytes_fetched: i64,
+    last_fetch_ms: Option<i32>,
+}
+
+impl FetchStats {
+    /// Get the id of the `Source`.
+    pub fn source_id(&self) -> i32 {
+        self.source_id
+    }
+
+    /// Get the uri of the `Source`.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Get how many times the feed was requested and answered.
+    pub fn fetches(&self) -> i32 {
+        self.fetches
+    }
+
+    /// Get how many of the `fetches` found the feed unchanged.
+    pub fn not_modified(&self) -> i32 {
+        self.not_modified
+    }
+
+    /// Get the fraction of `fetches` answered with `304 Not Modified`.
+    pub fn not_modified_rate(&self) -> Option<f64> {
+        (self.fetches > 0).then(|| f64::from(self.not_modified) / f64::from(self.fetches))
+    }
+
+    /// Get the total size of the feed documents downloaded, as transferred.
+    pub fn bytes_fetched(&self) -> i64 {
+        self.bytes_fetched
+    }
+
+    /// Get how long the last request for the feed took.
+    pub fn last_fetch_duration(&self) -> Option<Duration> {
+        self.last_fetch_ms
+            .map(|ms| Duration::from_millis(ms.max(0) as u64))
+    }
+}
+
+fn header_value(res: &reqwest::Response, name: http::header::HeaderName) -> Option<String> {
+    res.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned)
+}
 
-    // Channel will do it's own decoding of strings
-    // based on what is specified in <?xml encoding="..."?>.
-    // So just pass it the raw byets.
-    Channel::read_from(chunk.reader()).map_err(From::from)
+/// Undo the `Content-Encoding` of a response body.
+fn decode_body(encoding: Option<&str>, body: &[u8]) -> Result<Vec<u8>, DataError> {
+    let mut data = Vec::new();
+    match encoding.map(str::trim) {
+        None | Some("identity") => data.extend_from_slice(body),
+        Some(enc) if enc.eq_ignore_ascii_case("gzip") || enc.eq_ignore_ascii_case("x-gzip") => {
+            flate2::read::MultiGzDecoder::new(body).read_to_end(&mut data)?;
+        }
+        Some(enc) if enc.eq_ignore_ascii_case("br") => {
+            brotli_decompressor::Decompressor::new(body, 4096).read_to_end(&mut data)?;
+        }
+        Some(enc) => {
+            return Err(DataError::Bail(format!(
+                "Unsupported Content-Encoding: {enc}"
+            )))
+        }
+    }
+    Ok(data)
 }
 
 #[cfg(test)]
@@ -366,4 +594,36 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_refresh_backoff() -> Result<()> {
+        assert_eq!(refresh_backoff(1).as_secs(), 3600);
+        assert_eq!(refresh_backoff(3).as_secs(), 4 * 3600);
+        assert_eq!(refresh_backoff(100).as_secs(), 7 * 24 * 3600);
+
+        truncate_db()?;
+        let mut source = Source::from_url("http://localhost/feed.xml")?;
+        source.record_failure("404 Not Found", false);
+        source.record_failure("410 Gone", true);
+        let gone = source.gone();
+        source.record_failure("410 Gone", true);
+        let source = source.save()?;
+        assert_eq!(source.failures(), 3);
+        assert_eq!(source.last_error(), Some("410 Gone"));
+        assert!(gone.is_some());
+        // Stays gone since the first time
+        assert_eq!(source.gone(), gone);
+        let now = Utc::now().timestamp() as i32;
+        assert!(source.next_attempt() >= now + 4 * 3600 - 1);
+
+        let mut source = dbqueries::get_source_from_id(source.id())?;
+        source.record_success();
+        let source = source.save()?;
+        assert_eq!(source.failures(), 0);
+        assert_eq!(source.last_error(), None);
+        assert_eq!(source.gone(), None);
+        assert_eq!(source.next_attempt(), 0);
+        assert!(source.last_success().is_some());
+        Ok(())
+    }
 }
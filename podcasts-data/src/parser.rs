@@ -18,20 +18,34 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use rss::extension::itunes::ITunesItemExtension;
+use rss::extension::Extension;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::errors::DataError;
+use crate::models::{NewChapter, NewChapterBuilder};
+use crate::transcript::TranscriptFormat;
+
+/// The namespace prefix of the [Podcasting 2.0](https://podcastindex.org/namespace/1.0)
+/// extensions.
+pub(crate) const PODCAST_NAMESPACE: &str = "podcast";
 
 /// Parses an Item Itunes extension and returns it's duration value in seconds.
-// FIXME: Rafactor
-#[allow(non_snake_case)]
 pub(crate) fn parse_itunes_duration(item: Option<&ITunesItemExtension>) -> Option<i32> {
-    let duration = item.map(|s| s.duration())??;
+    item.and_then(|s| s.duration()).map(parse_duration)
+}
 
+/// Parses an `<itunes:duration>` value into seconds.
+// FIXME: Rafactor
+#[allow(non_snake_case)]
+pub(crate) fn parse_duration(duration: &str) -> i32 {
     // FOR SOME FUCKING REASON, IN THE APPLE EXTENSION SPEC
     // THE DURATION CAN BE EITHER AN INT OF SECONDS OR
     // A STRING OF THE FOLLOWING FORMATS:
     // HH:MM:SS, H:MM:SS, MM:SS, M:SS
     // LIKE WHO THE FUCK THOUGH THAT WOULD BE A GOOD IDEA.
     if let Ok(NO_FUCKING_LOGIC) = duration.parse::<i32>() {
-        return Some(NO_FUCKING_LOGIC);
+        return NO_FUCKING_LOGIC;
     };
 
     let mut seconds = 0;
@@ -45,12 +59,146 @@ pub(crate) fn parse_itunes_duration(item: Option<&ITunesItemExtension>) -> Optio
         seconds += fk_apple[1].parse::<i32>().unwrap_or(0);
     }
 
-    Some(seconds)
+    seconds
+}
+
+/// Returns the first `<podcast:name>` element of an Item.
+fn podcast_item_extension<'a>(item: &'a rss::Item, name: &str) -> Option<&'a Extension> {
+    item.extensions().get(PODCAST_NAMESPACE)?.get(name)?.first()
+}
+
+/// Parses the `<podcast:chapters>` tag of an Item and returns the url
+/// of the chapters file.
+///
+/// Only the JSON chapters format is supported, so files advertised with
+/// any other mime type are ignored.
+pub(crate) fn parse_podcast_chapters(item: &rss::Item) -> Option<String> {
+    podcast_chapters_url(podcast_item_extension(item, "chapters")?.attrs())
+}
+
+/// Returns the chapters url from the attributes of a `<podcast:chapters>` tag.
+pub(crate) fn podcast_chapters_url(attrs: &BTreeMap<String, String>) -> Option<String> {
+    if let Some(mime) = attrs.get("type") {
+        if !mime.trim().starts_with("application/json") {
+            return None;
+        }
+    }
+
+    attrs
+        .get("url")
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty())
+}
+
+/// Parses the `<podcast:transcript>` tags of an Item and returns the url and
+/// the mime type of the preferred transcript.
+///
+/// Timed formats are preferred over plain html, and unsupported formats
+/// are ignored.
+pub(crate) fn parse_podcast_transcript(item: &rss::Item) -> Option<(String, String)> {
+    let tags = item
+        .extensions()
+        .get(PODCAST_NAMESPACE)?
+        .get("transcript")?;
+    podcast_transcript(tags.iter().map(Extension::attrs))
+}
+
+/// Picks the preferred transcript out of the attributes of the
+/// `<podcast:transcript>` tags of an Item.
+pub(crate) fn podcast_transcript<'a, I>(tags: I) -> Option<(String, String)>
+where
+    I: IntoIterator<Item = &'a BTreeMap<String, String>>,
+{
+    tags.into_iter()
+        .filter_map(|attrs| {
+            let url = attrs.get("url")?.trim();
+            let mime = attrs.get("type")?.trim();
+            let format = TranscriptFormat::from_mime(mime)?;
+            if url.is_empty() {
+                return None;
+            }
+            Some((format, url, mime))
+        })
+        .min_by_key(|(format, _, _)| *format)
+        .map(|(_, url, mime)| (url.to_owned(), mime.to_owned()))
+}
+
+/// Returns the new url of a feed from a `<podcast:moved>` tag, which carries
+/// it either as its value or in a `url` attribute.
+pub(crate) fn podcast_moved_url(
+    value: Option<&str>,
+    attrs: &BTreeMap<String, String>,
+) -> Option<String> {
+    attrs
+        .get("url")
+        .map(String::as_str)
+        .or(value)
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty())
+}
+
+/// Whether the value of a `<podcast:locked>` tag locks the feed.
+pub(crate) fn podcast_locked(value: Option<&str>) -> bool {
+    value.is_some_and(|v| v.trim().eq_ignore_ascii_case("yes"))
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonChapters {
+    chapters: Vec<JsonChapter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonChapter {
+    start_time: f64,
+    end_time: Option<f64>,
+    title: Option<String>,
+    img: Option<String>,
+    url: Option<String>,
+    toc: Option<bool>,
+}
+
+/// Parses a [JSON chapters](https://github.com/Podcastindex-org/podcast-namespace/blob/main/chapters/jsonChapters.md)
+/// file into `NewChapter`s for the episode with the `episode_id` rowid.
+///
+/// Chapters that are hidden from the table of contents are skipped, and
+/// the rest are sorted by their starting time.
+pub(crate) fn parse_chapters_json(
+    data: &[u8],
+    episode_id: i32,
+) -> Result<Vec<NewChapter>, DataError> {
+    let json: JsonChapters = serde_json::from_slice(data)?;
+
+    let mut chapters = json
+        .chapters
+        .into_iter()
+        .filter(|c| c.toc != Some(false) && c.start_time >= 0.0)
+        .map(|c| {
+            NewChapterBuilder::default()
+                .episode_id(episode_id)
+                .start_time(c.start_time as i32)
+                .end_time(c.end_time.map(|t| t as i32))
+                .title(c.title.map(|t| t.trim().to_owned()))
+                .image_uri(c.img)
+                .link(c.url)
+                .build()
+                .map_err(|err| DataError::BuilderError(format!("{err}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The start time is the primary key of a chapter so keep only the first one.
+    chapters.sort_by_key(|c| c.start_time());
+    chapters.dedup_by_key(|c| c.start_time());
+    Ok(chapters)
 }
 
 #[cfg(test)]
 mod tests {
     use rss::extension::itunes::ITunesItemExtensionBuilder;
+    use rss::Channel;
+
+    use std::fs::{self, File};
+    use std::io::BufReader;
 
     use super::*;
 
@@ -91,4 +239,93 @@ mod tests {
         let item = Some(&extension);
         assert_eq!(parse_itunes_duration(item), Some(6970));
     }
+
+    #[test]
+    fn test_podcast_chapters() -> Result<(), DataError> {
+        let file = File::open("tests/feeds/2026-10-18-PodcastNamespace.xml")?;
+        let channel = Channel::read_from(BufReader::new(file))?;
+        let items = channel.items();
+
+        assert_eq!(
+            parse_podcast_chapters(&items[0]),
+            Some(String::from(
+                "https://example.org/podcast/episode-3/chapters.json"
+            ))
+        );
+        // Only JSON chapters are supported
+        assert_eq!(parse_podcast_chapters(&items[1]), None);
+        // No chapters tag
+        assert_eq!(parse_podcast_chapters(&items[2]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_podcast_transcript() -> Result<(), DataError> {
+        let file = File::open("tests/feeds/2026-10-18-PodcastNamespace.xml")?;
+        let channel = Channel::read_from(BufReader::new(file))?;
+        let items = channel.items();
+
+        // JSON is preferred over the other timed formats
+        assert_eq!(
+            parse_podcast_transcript(&items[0]),
+            Some((
+                String::from("https://example.org/podcast/episode-3/transcript.json"),
+                String::from("application/json")
+            ))
+        );
+        // Unsupported formats are skipped
+        assert_eq!(
+            parse_podcast_transcript(&items[1]),
+            Some((
+                String::from("https://example.org/podcast/episode-2/transcript.html"),
+                String::from("text/html")
+            ))
+        );
+        assert_eq!(parse_podcast_transcript(&items[2]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_podcast_moved() {
+        let url = "https://example.org/feed.xml";
+        let attrs = BTreeMap::from([("url".to_owned(), url.to_owned())]);
+        assert_eq!(podcast_moved_url(None, &attrs).as_deref(), Some(url));
+        let value = format!("  {url}\n");
+        let moved = podcast_moved_url(Some(&value), &BTreeMap::new());
+        assert_eq!(moved.as_deref(), Some(url));
+        assert_eq!(podcast_moved_url(Some(" "), &BTreeMap::new()), None);
+
+        assert!(podcast_locked(Some("yes")));
+        assert!(podcast_locked(Some(" Yes ")));
+        assert!(!podcast_locked(Some("no")));
+        assert!(!podcast_locked(None));
+    }
+
+    #[test]
+    fn test_chapters_json() -> Result<(), DataError> {
+        let data = fs::read("tests/feeds/2026-10-18-PodcastNamespace-chapters.json")?;
+        let chapters = parse_chapters_json(&data, 42)?;
+
+        // One of the chapters is not part of the table of contents
+        assert_eq!(chapters.len(), 4);
+        let starts: Vec<_> = chapters.iter().map(|c| c.start_time()).collect();
+        assert_eq!(starts, vec![0, 95, 1200, 2713]);
+        assert_eq!(chapters[1].title(), Some("News"));
+
+        let expected = NewChapterBuilder::default()
+            .episode_id(42)
+            .start_time(0)
+            .end_time(Some(95))
+            .title(Some(String::from("Introduction")))
+            .image_uri(Some(String::from(
+                "https://example.org/podcast/episode-3/intro.jpg",
+            )))
+            .link(Some(String::from("https://example.org/podcast/episode-3")))
+            .build()
+            .unwrap();
+        assert_eq!(chapters[0], expected);
+
+        assert!(parse_chapters_json(b"{\"version\": \"1.2.0\"}", 42).is_err());
+        Ok(())
+    }
 }
@@ -20,54 +20,463 @@
 // FIXME:
 //! Docs.
 
-use crate::downloader::client_builder;
+use crate::dbqueries;
+use crate::downloader::{client_builder, CONNECT_TIMEOUT};
 use crate::errors::DataError;
-use crate::Source;
+use crate::models::Fetched;
+use crate::{Save, Source};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
+use url::Url;
+
+/// What refreshing a `Source` resulted in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefreshOutcome {
+    /// The feed did not change since the last refresh.
+    NotModified,
+    /// The feed was indexed.
+    Updated {
+        /// The number of episodes that were published since the last refresh.
+        new: usize,
+        /// The number of known episodes the publisher changed.
+        changed: usize,
+    },
+    /// The server responded with an error status.
+    HttpError(reqwest::StatusCode),
+    /// The feed could not be parsed.
+    ParseError(String),
+    /// The feed could not be fetched or indexed.
+    Failed(String),
+}
+
+impl RefreshOutcome {
+    fn from_error(err: &DataError) -> Self {
+        match err {
+            DataError::FeedNotModified(_) => RefreshOutcome::NotModified,
+            DataError::HttpStatusGeneral { status_code, .. } => {
+                RefreshOutcome::HttpError(*status_code)
+            }
+            DataError::RssError(_)
+            | DataError::AtomError(_)
+            | DataError::JsonError(_)
+            | DataError::XmlReaderError(_) => RefreshOutcome::ParseError(err.to_string()),
+            _ => RefreshOutcome::Failed(err.to_string()),
+        }
+    }
+
+    /// Whether the feed could not be refreshed.
+    pub fn is_failure(&self) -> bool {
+        !matches!(
+            self,
+            RefreshOutcome::NotModified | RefreshOutcome::Updated { .. }
+        )
+    }
+}
+
+/// The result of refreshing a single `Source`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceReport {
+    source_id: i32,
+    uri: String,
+    redirect: Option<String>,
+    moved_to: Option<String>,
+    outcome: RefreshOutcome,
+    /// Whether the server could not be connected to.
+    unreachable: bool,
+}
+
+impl SourceReport {
+    /// Get the id of the `Source`.
+    pub fn source_id(&self) -> i32 {
+        self.source_id
+    }
+
+    /// Get the url the `Source` was refreshed from.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Get the url the feed was served from, if the request got redirected.
+    pub fn redirect(&self) -> Option<&str> {
+        self.redirect.as_deref()
+    }
+
+    /// Get the url the feed announced as its new location, which the
+    /// `Source` now points to.
+    pub fn moved_to(&self) -> Option<&str> {
+        self.moved_to.as_deref()
+    }
+
+    /// Get the `RefreshOutcome`.
+    pub fn outcome(&self) -> &RefreshOutcome {
+        &self.outcome
+    }
+}
+
+/// The result of a `pipeline` run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshReport {
+    sources: Vec<SourceReport>,
+    downloads: Vec<i32>,
+}
+
+impl RefreshReport {
+    /// Get a `SourceReport` for each refreshed `Source`, in order.
+    pub fn sources(&self) -> &[SourceReport] {
+        &self.sources
+    }
+
+    /// Get the `SourceReport`s of the sources that could not be refreshed.
+    pub fn failures(&self) -> impl Iterator<Item = &SourceReport> {
+        self.sources.iter().filter(|s| s.outcome.is_failure())
+    }
+
+    /// Get the total number of new episodes.
+    pub fn new_episodes(&self) -> usize {
+        self.sources
+            .iter()
+            .map(|s| match s.outcome {
+                RefreshOutcome::Updated { new, .. } => new,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Get the `rowid`s of the new episodes that should be downloaded,
+    /// according to the `AutoDownload` policy of their show.
+    pub fn downloads(&self) -> &[i32] {
+        &self.downloads
+    }
+}
+
+/// How many feeds `pipeline_with_limits` fetches at once, and how long it
+/// waits for them.
+#[derive(Debug, Clone, Copy, Builder, PartialEq, Eq)]
+#[builder(default)]
+#[builder(derive(Debug))]
+pub struct PipelineLimits {
+    /// The maximum number of feeds fetched at the same time.
+    max_concurrent: usize,
+    /// The maximum number of feeds fetched at the same time from a single server.
+    max_per_host: usize,
+    /// How long to wait for a connection to a server.
+    connect_timeout: Duration,
+    /// How long to wait for a whole feed to be fetched.
+    timeout: Duration,
+}
+
+impl Default for PipelineLimits {
+    fn default() -> Self {
+        PipelineLimits {
+            max_concurrent: 8,
+            max_per_host: 2,
+            connect_timeout: CONNECT_TIMEOUT,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl PipelineLimits {
+    /// Get the maximum number of feeds fetched at the same time.
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Get the maximum number of feeds fetched at the same time from a single server.
+    pub fn max_per_host(&self) -> usize {
+        self.max_per_host
+    }
+
+    /// Get how long to wait for a connection to a server.
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Get how long to wait for a whole feed to be fetched.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
 
 /// The pipline to be run for indexing and updating a Podcast feed that originates from
 /// `Source.uri`.
 ///
 /// Messy temp diagram:
 /// Source -> GET Request -> Update Etags -> Check Status -> Parse `xml/Rss` ->
-/// Convert `rss::Channel` into `Feed` -> Index Podcast -> Index Episodes.
-pub async fn pipeline<S>(sources: S) -> Result<(), reqwest::Error>
+/// Convert `Channel` into `Feed` -> Index Podcast -> Index Episodes -> Auto-download.
+///
+/// Returns a `RefreshReport` with the outcome of every `Source`.
+pub async fn pipeline<S>(sources: S) -> Result<RefreshReport, reqwest::Error>
 where
     S: IntoIterator<Item = Source>,
 {
-    let client = client_builder().build()?;
+    pipeline_with_limits(sources, &PipelineLimits::default()).await
+}
 
+/// Like `pipeline`, but with custom `PipelineLimits`.
+pub async fn pipeline_with_limits<S>(
+    sources: S,
+    limits: &PipelineLimits,
+) -> Result<RefreshReport, reqwest::Error>
+where
+    S: IntoIterator<Item = Source>,
+{
+    let client = feed_client(limits)?;
+
+    // Every future waits for a slot of its server first, so that the feeds
+    // of a busy server don't hold up the global slots.
+    let slots = Semaphore::new(limits.max_concurrent.max(1));
+    let mut hosts: HashMap<String, Arc<Semaphore>> = HashMap::new();
     let handles: Vec<_> = sources
         .into_iter()
-        .map(|source| async {
-            let uri = source.uri().to_string();
-            match source.into_feed(&client).await {
-                Ok(feed) => match feed.index() {
-                    Ok(_) => (),
-                    Err(err) => error!(
-                        "Error while indexing content feed into the database: {} - {}",
-                        uri, err
-                    ),
-                },
-                // Avoid spamming the stderr when it's not an actual error
-                Err(DataError::FeedNotModified(_)) => (),
-                Err(err) => error!(
-                    "Error while fetching the latest xml feed: {} - {}",
-                    uri, err
-                ),
+        .map(|source| {
+            let host = host_key(source.uri());
+            let host_slots = hosts
+                .entry(host)
+                .or_insert_with(|| Arc::new(Semaphore::new(limits.max_per_host.max(1))))
+                .clone();
+            let client = &client;
+            let slots = &slots;
+            async move {
+                let _host_permit = host_slots.acquire().await;
+                let _permit = slots.acquire().await;
+                refresh_source(client, source).await
             }
         })
         .collect();
-    futures::future::join_all(handles).await;
+
+    let mut report = RefreshReport::default();
+    for (source, downloads) in futures::future::join_all(handles).await {
+        report.sources.push(source);
+        report.downloads.extend(downloads);
+    }
+
+    // Servers that can't be connected to only count as failing when others
+    // could be, so refreshing while offline doesn't back off from every feed.
+    if report.sources.iter().any(|s| !s.unreachable) {
+        for source in report.sources.iter().filter(|s| s.unreachable) {
+            if let Err(err) = record_health(source.source_id, &source.outcome, None) {
+                error!(
+                    "Failed to record the health of source {}: {}",
+                    source.source_id, err
+                );
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn feed_client(limits: &PipelineLimits) -> Result<reqwest::Client, reqwest::Error> {
+    client_builder()
+        .connect_timeout(limits.connect_timeout)
+        .timeout(limits.timeout)
+        .build()
+}
+
+/// Asks for a single `Source` to be refreshed on demand, for example when
+/// the WebSub hub of its feed announces an update.
+///
+/// Create one with `refresh_hook`, and hand it to whatever receives the
+/// notifications.
+#[derive(Debug, Clone)]
+pub struct RefreshHook {
+    topics: mpsc::UnboundedSender<String>,
+}
+
+impl RefreshHook {
+    /// Ask for the `Source` whose self url or uri is `topic` to be refreshed.
+    ///
+    /// Returns `false` if the `RefreshListener` is gone.
+    pub fn notify(&self, topic: &str) -> bool {
+        self.topics.send(topic.to_owned()).is_ok()
+    }
+}
+
+/// Refreshes the sources a `RefreshHook` asks for.
+#[derive(Debug)]
+pub struct RefreshListener {
+    topics: mpsc::UnboundedReceiver<String>,
+    client: reqwest::Client,
+}
+
+impl RefreshListener {
+    /// Wait for the next notification, and refresh its `Source`.
+    ///
+    /// The report is empty if no `Source` matches the notification.
+    /// Returns `None` once every `RefreshHook` is dropped.
+    pub async fn next(&mut self) -> Option<RefreshReport> {
+        let topic = self.topics.recv().await?;
+        let mut report = RefreshReport::default();
+        match dbqueries::get_source_from_topic(&topic) {
+            Ok(source) => {
+                let (source, downloads) = refresh_source(&self.client, source).await;
+                report.sources.push(source);
+                report.downloads = downloads;
+            }
+            Err(err) => warn!("Ignoring the notification about {}: {}", topic, err),
+        }
+        Some(report)
+    }
+}
+
+/// Create a connected `RefreshHook` and `RefreshListener`.
+pub fn refresh_hook() -> Result<(RefreshHook, RefreshListener), reqwest::Error> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let listener = RefreshListener {
+        topics: receiver,
+        client: feed_client(&PipelineLimits::default())?,
+    };
+    Ok((RefreshHook { topics: sender }, listener))
+}
+
+/// The server a `Source` is fetched from, as far as politeness is concerned.
+fn host_key(uri: &str) -> String {
+    Url::parse(uri)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| uri.to_owned())
+}
+
+/// Fetch and index a single `Source`.
+///
+/// Returns its `SourceReport` and the episodes to download.
+/// The health of the `Source` is recorded, unless its server could not be
+/// connected to, which is left to the caller.
+async fn refresh_source(client: &reqwest::Client, source: Source) -> (SourceReport, Vec<i32>) {
+    let source_id = source.id();
+    let uri = source.uri().to_string();
+    let mut redirect = None;
+    let mut moved_to = None;
+    let mut downloads = Vec::new();
+    // The size of the body, if the request got answered
+    let mut received = None;
+    let mut unreachable = false;
+
+    let started = Instant::now();
+    let fetched = source.fetch(client).await;
+    let elapsed = started.elapsed();
+
+    let outcome = match fetched {
+        Ok(Fetched {
+            feed: Err(err),
+            bytes,
+            ..
+        }) => {
+            received = Some(bytes);
+            error!("Error while parsing the latest xml feed: {} - {}", uri, err);
+            RefreshOutcome::from_error(&err)
+        }
+        Ok(Fetched {
+            feed: Ok(feed),
+            redirect: to,
+            bytes,
+        }) => {
+            redirect = to;
+            received = Some(bytes);
+            let indexed = feed.index().and_then(|summary| {
+                downloads = auto_downloads(summary.inserted())?;
+                Ok(summary)
+            });
+            match indexed {
+                Ok(summary) => {
+                    moved_to = summary.moved_to().map(str::to_owned);
+                    RefreshOutcome::Updated {
+                        new: summary.inserted().len(),
+                        changed: summary.updated(),
+                    }
+                }
+                Err(err) => {
+                    error!(
+                        "Error while indexing content feed into the database: {} - {}",
+                        uri, err
+                    );
+                    RefreshOutcome::from_error(&err)
+                }
+            }
+        }
+        // Avoid spamming the stderr when it's not an actual error
+        Err(DataError::FeedNotModified(_)) => {
+            received = Some(0);
+            RefreshOutcome::NotModified
+        }
+        Err(err) => {
+            error!(
+                "Error while fetching the latest xml feed: {} - {}",
+                uri, err
+            );
+            unreachable = matches!(err, DataError::ReqwestError(ref e) if e.is_connect());
+            RefreshOutcome::from_error(&err)
+        }
+    };
+
+    let fetch = received.map(|bytes| (bytes, elapsed));
+    if unreachable {
+        info!("Could not connect to {}", uri);
+    } else if let Err(err) = record_health(source_id, &outcome, fetch) {
+        error!(
+            "Failed to record the health of source {}: {}",
+            source_id, err
+        );
+    }
+
+    let report = SourceReport {
+        source_id,
+        uri,
+        redirect,
+        moved_to,
+        outcome,
+        unreachable,
+    };
+    (report, downloads)
+}
+
+/// Keep track of the consecutive failures of a `Source`, so that dead feeds
+/// get refreshed less and less often, along with the size and duration of
+/// the `fetch` when the request got answered.
+fn record_health(
+    source_id: i32,
+    outcome: &RefreshOutcome,
+    fetch: Option<(usize, Duration)>,
+) -> Result<(), DataError> {
+    // Refreshing may have changed the source, so don't reuse it
+    let mut source = dbqueries::get_source_from_id(source_id)?;
+    if let Some((bytes, elapsed)) = fetch {
+        source.record_fetch(bytes, elapsed, *outcome == RefreshOutcome::NotModified);
+    }
+    match outcome {
+        RefreshOutcome::NotModified | RefreshOutcome::Updated { .. } => source.record_success(),
+        RefreshOutcome::HttpError(status) => {
+            source.record_failure(&status.to_string(), *status == reqwest::StatusCode::GONE)
+        }
+        RefreshOutcome::ParseError(err) | RefreshOutcome::Failed(err) => {
+            source.record_failure(err, false)
+        }
+    }
+    source.save()?;
     Ok(())
 }
 
+/// Apply the `AutoDownload` policy of a show to the episodes a `Feed::index` inserted.
+fn auto_downloads(new_episodes: &[i32]) -> Result<Vec<i32>, DataError> {
+    let episodes = dbqueries::get_episodes_minimal_from_rowids(new_episodes)?;
+    match episodes.first() {
+        Some(ep) => Ok(dbqueries::get_show_settings(ep.show_id())?.auto_downloads(&episodes)),
+        None => Ok(Vec::new()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::database::truncate_db;
     use crate::dbqueries;
+    use crate::mock_server::MockServer;
     use crate::Source;
 
+    use std::fs;
+
     // (path, url) tuples.
     const URLS: &[&str] = &[
         "https://web.archive.org/web/20180120083840if_/https://feeds.feedburner.\
@@ -107,4 +516,288 @@ mod tests {
         assert_eq!(dbqueries::get_episodes()?.len(), 354);
         Ok(())
     }
+
+    #[test]
+    fn test_pipeline_report() -> Result<(), DataError> {
+        truncate_db()?;
+        let server = MockServer::start();
+        let feed = fs::read("tests/feeds/2026-10-18-Retitled.xml")?;
+        let updated = fs::read("tests/feeds/2026-10-18-Retitled-updated.xml")?;
+        server.route("/feed.xml", 200, &[("ETag", "\"v1\"")], &feed);
+        server.route("/moved.xml", 301, &[("Location", "/updated.xml")], b"");
+        server.route("/updated.xml", 200, &[], &updated);
+        server.route("/gone.xml", 410, &[], b"");
+        server.route("/error.xml", 503, &[], b"");
+        server.route("/page.html", 200, &[], b"<html><body>Hi</body></html>");
+
+        let paths = [
+            "/feed.xml",
+            "/moved.xml",
+            "/gone.xml",
+            "/error.xml",
+            "/page.html",
+        ];
+        let sources: Vec<_> = paths
+            .iter()
+            .map(|path| Source::from_url(&server.url(path)))
+            .collect::<Result<_, _>>()?;
+
+        let rt = tokio::runtime::Runtime::new()?;
+        let report = rt.block_on(pipeline(sources.clone()))?;
+        let outcomes: Vec<_> = report.sources().iter().map(|s| s.outcome()).collect();
+        assert_eq!(
+            outcomes[..2],
+            [
+                &RefreshOutcome::Updated { new: 3, changed: 0 },
+                &RefreshOutcome::Updated { new: 4, changed: 0 },
+            ]
+        );
+        assert_eq!(
+            outcomes[2..4],
+            [
+                &RefreshOutcome::HttpError(reqwest::StatusCode::GONE),
+                &RefreshOutcome::HttpError(reqwest::StatusCode::SERVICE_UNAVAILABLE),
+            ]
+        );
+        assert!(matches!(outcomes[4], RefreshOutcome::ParseError(_)));
+
+        assert_eq!(report.sources()[0].source_id(), sources[0].id());
+        assert_eq!(report.sources()[0].redirect(), None);
+        let redirect = server.url("/updated.xml");
+        assert_eq!(report.sources()[1].redirect(), Some(redirect.as_str()));
+        assert_eq!(report.new_episodes(), 7);
+        assert_eq!(report.failures().count(), 3);
+        // Auto-downloads are off by default
+        assert!(report.downloads().is_empty());
+
+        // The first feed is cached, and the second one got edited
+        server.route("/updated.xml", 200, &[], &feed);
+        let report = rt.block_on(pipeline(dbqueries::get_sources()?))?;
+        let outcome = |source: &Source| {
+            report
+                .sources()
+                .iter()
+                .find(|s| s.source_id() == source.id())
+                .map(SourceReport::outcome)
+        };
+        assert_eq!(outcome(&sources[0]), Some(&RefreshOutcome::NotModified));
+        assert_eq!(
+            outcome(&sources[1]),
+            Some(&RefreshOutcome::Updated { new: 0, changed: 3 })
+        );
+        assert_eq!(server.hits("/feed.xml"), 2);
+        Ok(())
+    }
+
+    /// Index one source per path, each answering `404` after a while.
+    fn slow_sources(server: &MockServer, name: &str, count: usize) -> Vec<Source> {
+        (0..count)
+            .map(|i| {
+                let path = format!("/{name}-{i}.xml");
+                server.delayed_route(&path, Duration::from_millis(200), 404, &[], b"");
+                Source::from_url(&server.url(&path)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pipeline_limits() -> Result<(), DataError> {
+        truncate_db()?;
+        let rt = tokio::runtime::Runtime::new()?;
+
+        let server = MockServer::start();
+        let sources = slow_sources(&server, "feed", 7);
+        let limits = PipelineLimitsBuilder::default()
+            .max_concurrent(3)
+            .max_per_host(10)
+            .build()
+            .unwrap();
+        let report = rt.block_on(pipeline_with_limits(sources, &limits))?;
+        assert_eq!(report.failures().count(), 7);
+        assert_eq!(server.max_in_flight(), 3);
+
+        let first = MockServer::start();
+        let second = MockServer::start();
+        let mut sources = slow_sources(&first, "first", 5);
+        sources.extend(slow_sources(&second, "second", 5));
+        let limits = PipelineLimitsBuilder::default()
+            .max_concurrent(10)
+            .max_per_host(2)
+            .build()
+            .unwrap();
+        let report = rt.block_on(pipeline_with_limits(sources, &limits))?;
+        assert_eq!(report.failures().count(), 10);
+        assert_eq!(first.max_in_flight(), 2);
+        assert_eq!(second.max_in_flight(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_timeout() -> Result<(), DataError> {
+        truncate_db()?;
+        let server = MockServer::start();
+        let feed = fs::read("tests/feeds/2026-10-18-Retitled.xml")?;
+        server.delayed_route("/hanging.xml", Duration::from_secs(5), 200, &[], &feed);
+        let source = Source::from_url(&server.url("/hanging.xml"))?;
+
+        let limits = PipelineLimitsBuilder::default()
+            .timeout(Duration::from_millis(500))
+            .build()
+            .unwrap();
+        let rt = tokio::runtime::Runtime::new()?;
+        let start = Instant::now();
+        let report = rt.block_on(pipeline_with_limits(vec![source], &limits))?;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            report.sources()[0].outcome(),
+            RefreshOutcome::Failed(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_source_health() -> Result<(), DataError> {
+        truncate_db()?;
+        let server = MockServer::start();
+        let feed = fs::read("tests/feeds/2026-10-18-Retitled.xml")?;
+        server.route("/feed.xml", 200, &[], &feed);
+        server.route("/gone.xml", 410, &[], b"");
+        let healthy = Source::from_url(&server.url("/feed.xml"))?;
+        let gone = Source::from_url(&server.url("/gone.xml"))?;
+
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(pipeline(vec![healthy.clone(), gone.clone()]))?;
+
+        let now = chrono::Utc::now().timestamp() as i32;
+        let healthy = dbqueries::get_source_from_id(healthy.id())?;
+        assert_eq!(healthy.failures(), 0);
+        assert!(healthy.last_success().is_some());
+        let gone = dbqueries::get_source_from_id(gone.id())?;
+        assert_eq!(gone.failures(), 1);
+        assert_eq!(gone.last_error(), Some("410 Gone"));
+        assert!(gone.gone().is_some());
+        assert!(gone.next_attempt() > now);
+
+        // The dead feed is left alone until its backoff expires
+        let due = dbqueries::get_due_sources(now)?;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id(), healthy.id());
+        let failing = dbqueries::get_failing_sources()?;
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].id(), gone.id());
+
+        // And forgotten once it comes back
+        server.route("/gone.xml", 200, &[], &feed);
+        rt.block_on(pipeline(vec![gone]))?;
+        assert!(dbqueries::get_failing_sources()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_offline() -> Result<(), DataError> {
+        truncate_db()?;
+        // Nothing listens on the port once the listener is dropped
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let offline = Source::from_url(&format!("http://{closed}/feed.xml"))?;
+
+        let rt = tokio::runtime::Runtime::new()?;
+        let report = rt.block_on(pipeline(vec![offline.clone()]))?;
+        assert!(report.sources()[0].outcome().is_failure());
+        // Nothing could be reached, so it doesn't count against the feed
+        let source = dbqueries::get_source_from_id(offline.id())?;
+        assert_eq!(source.failures(), 0);
+        assert_eq!(source.next_attempt(), 0);
+
+        // Unless another server answered
+        let server = MockServer::start();
+        let feed = fs::read("tests/feeds/2026-10-18-Retitled.xml")?;
+        server.route("/feed.xml", 200, &[], &feed);
+        let healthy = Source::from_url(&server.url("/feed.xml"))?;
+        rt.block_on(pipeline(vec![offline.clone(), healthy]))?;
+        let source = dbqueries::get_source_from_id(offline.id())?;
+        assert_eq!(source.failures(), 1);
+        assert!(source.next_attempt() > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_fetch_stats() -> Result<(), DataError> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        truncate_db()?;
+        let server = MockServer::start();
+        let feed = fs::read("tests/feeds/2026-10-18-Retitled.xml")?;
+        let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&feed)?;
+        let gz = gz.finish()?;
+        server.route(
+            "/feed.xml",
+            200,
+            &[("Content-Encoding", "gzip"), ("ETag", "\"v1\"")],
+            &gz,
+        );
+        let source = Source::from_url(&server.url("/feed.xml"))?;
+
+        let rt = tokio::runtime::Runtime::new()?;
+        let report = rt.block_on(pipeline(vec![source.clone()]))?;
+        // The compressed feed got decoded and indexed
+        assert!(report.new_episodes() > 0);
+
+        let stats = dbqueries::get_source_fetch_stats(source.id())?;
+        assert_eq!(stats.fetches(), 1);
+        assert_eq!(stats.not_modified(), 0);
+        // What went over the wire, not the decoded feed
+        assert_eq!(stats.bytes_fetched(), gz.len() as i64);
+        assert!(stats.last_fetch_duration().is_some());
+
+        // The second time around the etag matches
+        let source = dbqueries::get_source_from_id(source.id())?;
+        let report = rt.block_on(pipeline(vec![source.clone()]))?;
+        assert_eq!(report.sources()[0].outcome(), &RefreshOutcome::NotModified);
+
+        let stats = dbqueries::get_source_fetch_stats(source.id())?;
+        assert_eq!(stats.fetches(), 2);
+        assert_eq!(stats.not_modified(), 1);
+        assert_eq!(stats.not_modified_rate(), Some(0.5));
+        assert_eq!(stats.bytes_fetched(), gz.len() as i64);
+        assert_eq!(dbqueries::get_fetch_stats()?, vec![stats]);
+
+        // A feed that doesn't parse still cost its download
+        let broken = b"<html>Not a feed</html>";
+        server.route("/broken.xml", 200, &[], broken);
+        let source = Source::from_url(&server.url("/broken.xml"))?;
+        let report = rt.block_on(pipeline(vec![source.clone()]))?;
+        assert!(report.sources()[0].outcome().is_failure());
+
+        let stats = dbqueries::get_source_fetch_stats(source.id())?;
+        assert_eq!(stats.fetches(), 1);
+        assert_eq!(stats.bytes_fetched(), broken.len() as i64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_hook() -> Result<(), DataError> {
+        truncate_db()?;
+        let server = MockServer::start();
+        let feed = fs::read("tests/feeds/2026-10-18-Retitled-updated.xml")?;
+        server.route("/feed.xml", 200, &[], &feed);
+        let source = Source::from_url(&server.url("/feed.xml"))?;
+
+        let rt = tokio::runtime::Runtime::new()?;
+        let (hook, mut listener) = refresh_hook()?;
+        // Stands in for a hub notifying about an update
+        assert!(hook.notify(source.uri()));
+        assert!(hook.notify("https://example.org/unknown.xml"));
+        drop(hook);
+
+        let report = rt.block_on(listener.next()).unwrap();
+        assert_eq!(report.sources().len(), 1);
+        assert_eq!(report.sources()[0].source_id(), source.id());
+        assert_eq!(report.new_episodes(), 4);
+        let report = rt.block_on(listener.next()).unwrap();
+        assert!(report.sources().is_empty());
+        assert!(rt.block_on(listener.next()).is_none());
+        Ok(())
+    }
 }
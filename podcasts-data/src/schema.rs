@@ -1,7 +1,30 @@
 #![allow(warnings)]
 
 table! {
-    episodes (title, show_id) {
+    download_queue (episode_id) {
+        episode_id -> Integer,
+        download_dir -> Text,
+        state -> Text,
+        attempts -> Integer,
+        next_attempt -> Integer,
+        error -> Nullable<Text>,
+        queued_at -> Integer,
+    }
+}
+
+table! {
+    episode_chapters (episode_id, start_time) {
+        episode_id -> Integer,
+        start_time -> Integer,
+        end_time -> Nullable<Integer>,
+        title -> Nullable<Text>,
+        image_uri -> Nullable<Text>,
+        link -> Nullable<Text>,
+    }
+}
+
+table! {
+    episodes (identity, show_id) {
         rowid -> Integer,
         title -> Text,
         uri -> Nullable<Text>,
@@ -12,8 +35,33 @@ table! {
         duration -> Nullable<Integer>,
         play_position -> Integer,
         guid -> Nullable<Text>,
+        identity -> Text,
         played -> Nullable<Integer>,
         show_id -> Integer,
+        chapters_uri -> Nullable<Text>,
+        transcript_uri -> Nullable<Text>,
+        transcript_type -> Nullable<Text>,
+        removed_upstream -> Nullable<Integer>,
+    }
+}
+
+table! {
+    play_queue (episode_id) {
+        episode_id -> Integer,
+        position -> Integer,
+    }
+}
+
+table! {
+    show_settings (show_id) {
+        show_id -> Integer,
+        auto_download -> Text,
+        auto_download_limit -> Integer,
+        playback_rate -> Nullable<Double>,
+        skip_intro -> Integer,
+        skip_outro -> Integer,
+        retention -> Text,
+        retention_days -> Integer,
     }
 }
 
@@ -36,7 +84,29 @@ table! {
         uri -> Text,
         last_modified -> Nullable<Text>,
         http_etag -> Nullable<Text>,
+        failures -> Integer,
+        last_error -> Nullable<Text>,
+        last_success -> Nullable<Integer>,
+        next_attempt -> Integer,
+        gone -> Nullable<Integer>,
+        update_interval -> Nullable<Integer>,
+        skip_hours -> Integer,
+        skip_days -> Integer,
+        websub_hub -> Nullable<Text>,
+        self_url -> Nullable<Text>,
+        fetches -> Integer,
+        not_modified -> Integer,
+        bytes_fetched -> BigInt,
+        last_fetch_ms -> Nullable<Integer>,
     }
 }
 
-allow_tables_to_appear_in_same_query!(episodes, shows, source);
+allow_tables_to_appear_in_same_query!(
+    download_queue,
+    episode_chapters,
+    episodes,
+    play_queue,
+    show_settings,
+    shows,
+    source,
+);
@@ -0,0 +1,129 @@
+// atom_feed.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Normalize [Atom 1.0](https://www.rfc-editor.org/rfc/rfc4287) feeds.
+
+use atom_syndication::extension::{Extension, ExtensionMap};
+use atom_syndication::{Entry, Feed, Link};
+
+use super::{Channel, Enclosure, Item, UpdateHints};
+use crate::errors::DataError;
+use crate::parser::{self, PODCAST_NAMESPACE};
+
+pub(super) fn parse(data: &[u8]) -> Result<Channel, DataError> {
+    let feed = Feed::read_from(data)?;
+    Ok(Channel::from(feed))
+}
+
+/// Returns the `<prefix:name>` extension elements.
+fn extensions<'a>(map: &'a ExtensionMap, prefix: &str, name: &str) -> &'a [Extension] {
+    map.get(prefix)
+        .and_then(|m| m.get(name))
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn link_with_rel<'a>(links: &'a [Link], rel: &str) -> Option<&'a Link> {
+    links.iter().find(|l| l.rel() == rel)
+}
+
+impl From<Feed> for Channel {
+    fn from(feed: Feed) -> Self {
+        let itunes_img = extensions(feed.extensions(), "itunes", "image")
+            .first()
+            .and_then(|ext| ext.attrs().get("href"))
+            .map(|url| url.trim().to_owned());
+        let image_uri = itunes_img.or_else(|| {
+            feed.logo()
+                .or_else(|| feed.icon())
+                .map(|url| url.trim().to_owned())
+        });
+
+        let ext = feed.extensions();
+        let new_feed_url = extensions(ext, "itunes", "new-feed-url")
+            .first()
+            .and_then(Extension::value)
+            .map(|url| url.trim().to_owned())
+            .filter(|url| !url.is_empty())
+            .or_else(|| {
+                extensions(ext, PODCAST_NAMESPACE, "moved")
+                    .first()
+                    .and_then(|e| parser::podcast_moved_url(e.value(), e.attrs()))
+            });
+        let locked = parser::podcast_locked(
+            extensions(ext, PODCAST_NAMESPACE, "locked")
+                .first()
+                .and_then(Extension::value),
+        );
+
+        Channel {
+            title: feed.title().as_str().to_owned(),
+            link: link_with_rel(feed.links(), "alternate")
+                .map(|l| l.href().to_owned())
+                .unwrap_or_default(),
+            description: feed
+                .subtitle()
+                .map(|s| s.as_str().to_owned())
+                .unwrap_or_default(),
+            image_uri,
+            new_feed_url,
+            locked,
+            update_hints: UpdateHints::default(),
+            websub_hub: link_with_rel(feed.links(), "hub").map(|l| l.href().trim().to_owned()),
+            self_url: link_with_rel(feed.links(), "self").map(|l| l.href().trim().to_owned()),
+            items: feed.entries().iter().map(Item::from).collect(),
+        }
+    }
+}
+
+impl From<&Entry> for Item {
+    fn from(entry: &Entry) -> Self {
+        let title = entry.title().as_str();
+        let enclosure = link_with_rel(entry.links(), "enclosure").map(|l| {
+            let length = l.length().and_then(|len| len.trim().parse().ok());
+            Enclosure::new(l.href(), length, l.mime_type())
+        });
+        let description = entry
+            .content()
+            .and_then(|c| c.value())
+            .or_else(|| entry.summary().map(|s| s.as_str()));
+
+        let ext = entry.extensions();
+        let duration = extensions(ext, "itunes", "duration")
+            .first()
+            .and_then(Extension::value)
+            .map(|d| parser::parse_duration(d.trim()));
+        let chapters_uri = extensions(ext, PODCAST_NAMESPACE, "chapters")
+            .first()
+            .and_then(|e| parser::podcast_chapters_url(e.attrs()));
+        let transcripts = extensions(ext, PODCAST_NAMESPACE, "transcript");
+
+        Item {
+            title: Some(title.to_owned()).filter(|t| !t.trim().is_empty()),
+            guid: Some(entry.id().to_owned()).filter(|id| !id.trim().is_empty()),
+            link: link_with_rel(entry.links(), "alternate").map(|l| l.href().to_owned()),
+            description: description.map(str::to_owned),
+            published: Some(*entry.published().unwrap_or(entry.updated())),
+            enclosure,
+            duration,
+            chapters_uri,
+            transcript: parser::podcast_transcript(transcripts.iter().map(Extension::attrs)),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// json_feed.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Normalize [JSON Feed](https://www.jsonfeed.org/version/1.1/) documents.
+
+use chrono::DateTime;
+use serde::Deserialize;
+
+use super::{Channel, Enclosure, Item, UpdateHints};
+use crate::errors::DataError;
+
+#[derive(Debug, Deserialize)]
+struct JsonFeed {
+    title: String,
+    home_page_url: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
+    favicon: Option<String>,
+    feed_url: Option<String>,
+    #[serde(default)]
+    hubs: Vec<JsonHub>,
+    #[serde(default)]
+    items: Vec<JsonItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonHub {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonItem {
+    // Version 1.0 allowed numbers as ids
+    id: serde_json::Value,
+    url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    #[serde(default)]
+    attachments: Vec<JsonAttachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAttachment {
+    url: String,
+    mime_type: Option<String>,
+    size_in_bytes: Option<u64>,
+    duration_in_seconds: Option<f64>,
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<Channel, DataError> {
+    let feed: JsonFeed = serde_json::from_slice(data)?;
+    Ok(Channel::from(feed))
+}
+
+impl From<JsonFeed> for Channel {
+    fn from(feed: JsonFeed) -> Self {
+        Channel {
+            title: feed.title,
+            link: feed.home_page_url.unwrap_or_default(),
+            description: feed.description.unwrap_or_default(),
+            image_uri: feed.icon.or(feed.favicon),
+            new_feed_url: None,
+            locked: false,
+            update_hints: UpdateHints::default(),
+            websub_hub: feed
+                .hubs
+                .into_iter()
+                .find(|hub| hub.kind.eq_ignore_ascii_case("websub"))
+                .map(|hub| hub.url),
+            self_url: feed.feed_url,
+            items: feed.items.into_iter().map(Item::from).collect(),
+        }
+    }
+}
+
+impl From<JsonItem> for Item {
+    fn from(item: JsonItem) -> Self {
+        let guid = match item.id {
+            serde_json::Value::String(id) => Some(id),
+            serde_json::Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        };
+
+        // Podcasts only have a single media file, so pick the first audio
+        // or video attachment.
+        let attachment = item.attachments.into_iter().find(|a| {
+            a.mime_type
+                .as_deref()
+                .is_none_or(|m| m.starts_with("audio/") || m.starts_with("video/"))
+        });
+        let duration = attachment
+            .as_ref()
+            .and_then(|a| a.duration_in_seconds)
+            .map(|d| d as i32);
+        let enclosure = attachment.map(|a| {
+            let length = a.size_in_bytes.and_then(|s| i32::try_from(s).ok());
+            Enclosure::new(&a.url, length, a.mime_type.as_deref())
+        });
+
+        Item {
+            title: item.title,
+            guid,
+            link: item.url,
+            description: item.content_html.or(item.content_text).or(item.summary),
+            published: item
+                .date_published
+                .and_then(|date| DateTime::parse_from_rfc3339(date.trim()).ok()),
+            enclosure,
+            duration,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,445 @@
+// mod.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Format agnostic representation of a podcast feed.
+//!
+//! RSS 2.0, Atom 1.0 and JSON Feed documents are all normalized into a
+//! `Channel` with a list of `Item`s, which is what gets indexed.
+
+mod atom_feed;
+mod json_feed;
+mod rss_feed;
+
+use chrono::{DateTime, Datelike, FixedOffset, TimeZone, Timelike, Utc};
+
+use crate::errors::DataError;
+
+use std::io::BufRead;
+
+/// The syndication formats a `Source` can be served in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    /// [RSS 2.0](https://www.rssboard.org/rss-specification)
+    Rss,
+    /// [Atom 1.0](https://www.rfc-editor.org/rfc/rfc4287)
+    Atom,
+    /// [JSON Feed](https://www.jsonfeed.org/version/1.1/)
+    Json,
+}
+
+impl FeedFormat {
+    /// Maps the `Content-Type` of a response to a `FeedFormat`.
+    ///
+    /// Generic types like `text/xml` are ambiguous and return `None`.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "application/rss+xml" => Some(FeedFormat::Rss),
+            "application/atom+xml" => Some(FeedFormat::Atom),
+            "application/feed+json" | "application/json" => Some(FeedFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the start of the document.
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        let start = data.iter().position(|b| !b.is_ascii_whitespace())?;
+        if data[start] == b'{' {
+            return Some(FeedFormat::Json);
+        }
+
+        // The root element usually comes right after the prolog, so only
+        // look at the head of the document.
+        let head = &data[start..data.len().min(start + 4096)];
+        let find = |needle: &[u8]| head.windows(needle.len()).position(|w| w == needle);
+        let rss = [find(b"<rss"), find(b"<rdf:RDF")]
+            .into_iter()
+            .flatten()
+            .min();
+        match (rss, find(b"<feed")) {
+            (Some(rss), Some(atom)) if atom < rss => Some(FeedFormat::Atom),
+            (Some(_), _) => Some(FeedFormat::Rss),
+            (None, Some(_)) => Some(FeedFormat::Atom),
+            (None, None) => None,
+        }
+    }
+
+    /// Pick the format of a response, trusting the document over the
+    /// `Content-Type` header, and falling back to RSS.
+    pub fn detect(content_type: Option<&str>, data: &[u8]) -> Self {
+        Self::sniff(data)
+            .or_else(|| content_type.and_then(Self::from_content_type))
+            .unwrap_or(FeedFormat::Rss)
+    }
+}
+
+/// The media file of an `Item`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enclosure {
+    url: String,
+    length: Option<i32>,
+    mime_type: Option<String>,
+}
+
+impl Enclosure {
+    pub(crate) fn new(url: &str, length: Option<i32>, mime_type: Option<&str>) -> Self {
+        Enclosure {
+            url: url.trim().to_owned(),
+            length,
+            mime_type: mime_type.map(|m| m.trim().to_owned()),
+        }
+    }
+
+    /// Get the url of the media file.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Get the size of the media file in bytes.
+    pub fn length(&self) -> Option<i32> {
+        self.length
+    }
+
+    /// Get the mime type of the media file.
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+}
+
+/// A single entry of a `Channel`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Item {
+    title: Option<String>,
+    guid: Option<String>,
+    link: Option<String>,
+    description: Option<String>,
+    published: Option<DateTime<FixedOffset>>,
+    enclosure: Option<Enclosure>,
+    duration: Option<i32>,
+    chapters_uri: Option<String>,
+    transcript: Option<(String, String)>,
+}
+
+impl Item {
+    /// Get the title.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Get the unique identifier.
+    pub fn guid(&self) -> Option<&str> {
+        self.guid.as_deref()
+    }
+
+    /// Get the link to the web page of the item.
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+
+    /// Get the description, which might contain html.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Get the publication date.
+    pub fn published(&self) -> Option<DateTime<FixedOffset>> {
+        self.published
+    }
+
+    /// Get the media file.
+    pub fn enclosure(&self) -> Option<&Enclosure> {
+        self.enclosure.as_ref()
+    }
+
+    /// Get the duration in seconds.
+    pub fn duration(&self) -> Option<i32> {
+        self.duration
+    }
+
+    /// Get the url of the JSON chapters file.
+    pub fn chapters_uri(&self) -> Option<&str> {
+        self.chapters_uri.as_deref()
+    }
+
+    /// Get the url and the mime type of the preferred transcript.
+    pub fn transcript(&self) -> Option<(&str, &str)> {
+        self.transcript
+            .as_ref()
+            .map(|(url, mime)| (url.as_str(), mime.as_str()))
+    }
+}
+
+/// The longest a publisher can ask to wait between two refreshes.
+const MAX_UPDATE_INTERVAL: i32 = 24 * 3600;
+
+/// How often the publisher of a feed asks for it to be refreshed, from the
+/// RSS `<ttl>`, `<sy:updatePeriod>`, `<skipHours>` and `<skipDays>` tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateHints {
+    interval: Option<i32>,
+    skip_hours: i32,
+    skip_days: i32,
+}
+
+impl UpdateHints {
+    /// `skip_hours` and `skip_days` are bitmasks of the skipped hours of the
+    /// day in UTC, and of the skipped days of the week starting on Monday.
+    pub(crate) fn new(interval: Option<i32>, skip_hours: i32, skip_days: i32) -> Self {
+        UpdateHints {
+            interval: interval.map(|secs| secs.clamp(0, MAX_UPDATE_INTERVAL)),
+            skip_hours: skip_hours & 0xFF_FFFF,
+            skip_days: skip_days & 0x7F,
+        }
+    }
+
+    /// Get the minimum number of seconds between two refreshes.
+    pub fn interval(&self) -> Option<i32> {
+        self.interval
+    }
+
+    /// Get the bitmask of the hours of the day, in UTC, not to refresh in.
+    pub fn skip_hours(&self) -> i32 {
+        self.skip_hours
+    }
+
+    /// Get the bitmask of the days of the week, starting on Monday, not to
+    /// refresh in.
+    pub fn skip_days(&self) -> i32 {
+        self.skip_days
+    }
+
+    fn skips(&self, time: DateTime<Utc>) -> bool {
+        let day = time.weekday().num_days_from_monday();
+        self.skip_hours & (1 << time.hour()) != 0 || self.skip_days & (1 << day) != 0
+    }
+
+    /// Get the epoch a feed refreshed at `now` is due again, or `0` if it
+    /// is due whenever.
+    pub fn next_due(&self, now: i64) -> i64 {
+        // Skipping every hour of the week would never refresh the feed
+        let skips_all = self.skip_hours == 0xFF_FFFF || self.skip_days == 0x7F;
+        if self.interval.is_none() && (skips_all || self.skip_hours | self.skip_days == 0) {
+            return 0;
+        }
+
+        let mut due = now + i64::from(self.interval.unwrap_or_default());
+        if skips_all {
+            return due;
+        }
+        for _ in 0..7 * 24 {
+            match Utc.timestamp_opt(due, 0).single() {
+                Some(time) if self.skips(time) => due = due - due % 3600 + 3600,
+                _ => break,
+            }
+        }
+        due
+    }
+}
+
+/// A podcast feed, regardless of the format it was served in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Channel {
+    title: String,
+    link: String,
+    description: String,
+    image_uri: Option<String>,
+    new_feed_url: Option<String>,
+    locked: bool,
+    update_hints: UpdateHints,
+    websub_hub: Option<String>,
+    self_url: Option<String>,
+    items: Vec<Item>,
+}
+
+impl Channel {
+    /// Parse a feed of a known `FeedFormat`.
+    pub fn parse(data: &[u8], format: FeedFormat) -> Result<Self, DataError> {
+        match format {
+            FeedFormat::Rss => rss_feed::parse(data),
+            FeedFormat::Atom => atom_feed::parse(data),
+            FeedFormat::Json => json_feed::parse(data),
+        }
+    }
+
+    /// Read a whole feed and parse it, sniffing the format from its contents.
+    pub fn read_from<R: BufRead>(mut reader: R) -> Result<Self, DataError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::parse(&data, FeedFormat::detect(None, &data))
+    }
+
+    /// Get the title.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Get the link to the website of the podcast.
+    pub fn link(&self) -> &str {
+        &self.link
+    }
+
+    /// Get the description, which might contain html.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Get the url of the cover art.
+    pub fn image_uri(&self) -> Option<&str> {
+        self.image_uri.as_deref()
+    }
+
+    /// Get the url the publisher moved the feed to, from an
+    /// `<itunes:new-feed-url>` or `<podcast:moved>` tag.
+    pub fn new_feed_url(&self) -> Option<&str> {
+        self.new_feed_url.as_deref()
+    }
+
+    /// Whether the publisher locked the feed to its current host with
+    /// `<podcast:locked>`.
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Get how often the publisher asks for the feed to be refreshed.
+    pub fn update_hints(&self) -> UpdateHints {
+        self.update_hints
+    }
+
+    /// Get the url of the [WebSub](https://www.w3.org/TR/websub/) hub that
+    /// announces the updates of the feed.
+    pub fn websub_hub(&self) -> Option<&str> {
+        self.websub_hub.as_deref()
+    }
+
+    /// Get the canonical url of the feed, which is the topic it is known by
+    /// to its WebSub hub.
+    pub fn self_url(&self) -> Option<&str> {
+        self.self_url.as_deref()
+    }
+
+    /// Get the items of the feed.
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// Consume the `Channel` and return its items.
+    pub fn into_items(self) -> Vec<Item> {
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_sniff() {
+        let rss = b"<?xml version=\"1.0\"?>\n<rss version=\"2.0\"><channel/></rss>";
+        let atom =
+            b"\xEF\xBB\xBF<?xml version=\"1.0\"?><feed xmlns=\"http://www.w3.org/2005/Atom\"/>";
+        let rdf = b"<rdf:RDF><channel/></rdf:RDF>";
+        assert_eq!(FeedFormat::sniff(rss), Some(FeedFormat::Rss));
+        assert_eq!(FeedFormat::sniff(atom), Some(FeedFormat::Atom));
+        assert_eq!(FeedFormat::sniff(rdf), Some(FeedFormat::Rss));
+        assert_eq!(
+            FeedFormat::sniff(b"  \n{\"version\": 1}"),
+            Some(FeedFormat::Json)
+        );
+        assert_eq!(FeedFormat::sniff(b"<html></html>"), None);
+        assert_eq!(FeedFormat::sniff(b""), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        let json = "application/feed+json; charset=utf-8";
+        assert_eq!(FeedFormat::from_content_type(json), Some(FeedFormat::Json));
+        assert_eq!(FeedFormat::from_content_type("text/xml"), None);
+
+        // The body wins over a wrong header
+        let rss = b"<rss version=\"2.0\"></rss>";
+        assert_eq!(FeedFormat::detect(Some(json), rss), FeedFormat::Rss);
+        // and the header is used when the body is inconclusive
+        let atom = "application/atom+xml";
+        assert_eq!(FeedFormat::detect(Some(atom), b"garbage"), FeedFormat::Atom);
+        assert_eq!(FeedFormat::detect(None, b"garbage"), FeedFormat::Rss);
+    }
+
+    #[test]
+    fn test_formats_are_equivalent() -> Result<()> {
+        let read = |path: &str| -> Result<Channel> {
+            Ok(Channel::read_from(BufReader::new(File::open(path)?))?)
+        };
+        let rss = read("tests/feeds/2026-10-18-PodcastNamespace.xml")?;
+        let atom = read("tests/feeds/2026-10-18-PodcastNamespace-atom.xml")?;
+        let json = read("tests/feeds/2026-10-18-PodcastNamespace.json")?;
+
+        for channel in [&atom, &json] {
+            assert_eq!(channel.title(), rss.title());
+            assert_eq!(channel.link(), rss.link());
+            assert_eq!(channel.image_uri(), rss.image_uri());
+            assert_eq!(channel.items().len(), rss.items().len());
+
+            for (item, expected) in channel.items().iter().zip(rss.items()) {
+                assert_eq!(item.title(), expected.title());
+                assert_eq!(item.guid(), expected.guid());
+                assert_eq!(item.published(), expected.published());
+                assert_eq!(item.enclosure(), expected.enclosure());
+                assert_eq!(item.duration(), expected.duration());
+            }
+        }
+
+        // Podcast namespace tags work in Atom too
+        assert_eq!(
+            atom.items()[0].chapters_uri(),
+            rss.items()[0].chapters_uri()
+        );
+        assert_eq!(atom.items()[0].transcript(), rss.items()[0].transcript());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_hints() -> Result<()> {
+        let file = File::open("tests/feeds/2026-10-18-UpdateHints.xml")?;
+        let channel = Channel::read_from(BufReader::new(file))?;
+        let hints = channel.update_hints();
+        // Twice a day wins over the hourly ttl
+        assert_eq!(hints.interval(), Some(12 * 3600));
+        assert_eq!(hints.skip_hours(), 1 << 0 | 1 << 1 | 1 << 23);
+        assert_eq!(hints.skip_days(), 1 << 5 | 1 << 6);
+
+        // Friday 2026-10-16 12:00 UTC is due again on Monday at 02:00
+        let friday_noon = 1_792_152_000;
+        assert_eq!(hints.next_due(friday_noon), 1_792_375_200);
+        let interval_only = UpdateHints::new(hints.interval(), 0, 0);
+        assert_eq!(
+            interval_only.next_due(friday_noon),
+            1_792_152_000 + 12 * 3600
+        );
+        // Skipping the whole week is ignored
+        let skip_all = UpdateHints::new(Some(3600), 0, 0x7F);
+        assert_eq!(skip_all.next_due(friday_noon), friday_noon + 3600);
+        assert_eq!(UpdateHints::default().next_due(friday_noon), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+// rss_feed.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Normalize [RSS 2.0](https://www.rssboard.org/rss-specification) feeds.
+
+use chrono::Weekday;
+use rfc822_sanitizer::parse_from_rfc2822_with_fallback as parse_rfc822;
+use rss::extension::syndication::UpdatePeriod;
+use rss::extension::Extension;
+
+use super::{Channel, Enclosure, Item, UpdateHints};
+
+use crate::errors::DataError;
+use crate::parser::{self, PODCAST_NAMESPACE};
+
+/// The namespace uri of [Atom 1.0](https://www.rfc-editor.org/rfc/rfc4287) tags.
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+
+pub(super) fn parse(data: &[u8]) -> Result<Channel, DataError> {
+    // Channel will do it's own decoding of strings
+    // based on what is specified in <?xml encoding="..."?>.
+    // So just pass it the raw byets.
+    let chan = rss::Channel::read_from(data)?;
+    Ok(Channel::from(chan))
+}
+
+impl From<rss::Channel> for Channel {
+    fn from(chan: rss::Channel) -> Self {
+        // Try to get the itunes img first
+        let itunes_img = chan
+            .itunes_ext()
+            .and_then(|s| s.image().map(|url| url.trim()))
+            .map(|s| s.to_owned());
+        // If itunes is None, try to get the channel.image from the rss spec
+        let image_uri = itunes_img.or_else(|| chan.image().map(|s| s.url().trim().to_owned()));
+
+        let podcast_ext = |name| {
+            chan.extensions()
+                .get(PODCAST_NAMESPACE)
+                .and_then(|m| m.get(name))
+                .and_then(|tags| tags.first())
+        };
+        let new_feed_url = chan
+            .itunes_ext()
+            .and_then(|s| s.new_feed_url())
+            .map(|url| url.trim().to_owned())
+            .filter(|url| !url.is_empty())
+            .or_else(|| {
+                podcast_ext("moved").and_then(|e| parser::podcast_moved_url(e.value(), e.attrs()))
+            });
+        let locked = parser::podcast_locked(podcast_ext("locked").and_then(Extension::value));
+        let update_hints = update_hints(&chan);
+        let atom_link = |rel| atom_link(&chan, rel);
+        let websub_hub = atom_link("hub");
+        let self_url = atom_link("self");
+
+        Channel {
+            title: chan.title().to_owned(),
+            link: chan.link().to_owned(),
+            description: chan.description().to_owned(),
+            image_uri,
+            new_feed_url,
+            locked,
+            update_hints,
+            websub_hub,
+            self_url,
+            items: chan.items().iter().map(Item::from).collect(),
+        }
+    }
+}
+
+/// Returns the url of the first `<atom:link>` tag with the given `rel`.
+///
+/// The Atom namespace is looked up by its uri, as feeds use all sorts of
+/// prefixes for it.
+fn atom_link(chan: &rss::Channel, rel: &str) -> Option<String> {
+    chan.namespaces()
+        .iter()
+        .filter(|(_, uri)| uri.as_str() == ATOM_NAMESPACE)
+        .filter_map(|(prefix, _)| chan.extensions().get(prefix)?.get("link"))
+        .flatten()
+        .find(|link| link.attrs().get("rel").map(String::as_str) == Some(rel))
+        .and_then(|link| link.attrs().get("href"))
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty())
+}
+
+/// Parse the `<ttl>`, `<sy:updatePeriod>`, `<skipHours>` and `<skipDays>`
+/// tags, taking the longest of the intervals they ask for.
+fn update_hints(chan: &rss::Channel) -> UpdateHints {
+    let ttl = chan
+        .ttl()
+        .and_then(|minutes| minutes.trim().parse::<i32>().ok())
+        .map(|minutes| minutes.saturating_mul(60));
+    let period = chan.syndication_ext().map(|sy| {
+        let secs = match sy.period() {
+            UpdatePeriod::Hourly => 3600,
+            UpdatePeriod::Daily => 24 * 3600,
+            UpdatePeriod::Weekly => 7 * 24 * 3600,
+            UpdatePeriod::Monthly => 30 * 24 * 3600,
+            UpdatePeriod::Yearly => 365 * 24 * 3600,
+        };
+        secs / sy.frequency().clamp(1, 3600) as i32
+    });
+
+    // Some feeds count the hours from 1 to 24
+    let skip_hours = chan
+        .skip_hours()
+        .iter()
+        .filter_map(|hour| hour.trim().parse::<u32>().ok())
+        .filter(|hour| *hour <= 24)
+        .fold(0, |mask, hour| mask | 1 << (hour % 24));
+    let skip_days = chan
+        .skip_days()
+        .iter()
+        .filter_map(|day| day.trim().parse::<Weekday>().ok())
+        .fold(0, |mask, day| mask | 1 << day.num_days_from_monday());
+
+    UpdateHints::new(ttl.max(period), skip_hours, skip_days)
+}
+
+impl From<&rss::Item> for Item {
+    fn from(item: &rss::Item) -> Self {
+        let enclosure = item.enclosure().map(|enc| {
+            // Get the size of the content, it should be in bytes
+            let length = enc.length().parse().ok();
+            Enclosure::new(enc.url(), length, Some(enc.mime_type()))
+        });
+
+        Item {
+            title: item.title().map(str::to_owned),
+            guid: item.guid().map(|s| s.value().to_owned()),
+            link: item.link().map(str::to_owned),
+            description: item.description().map(str::to_owned),
+            // Should treat information from the rss feeds as invalid by default.
+            // Case: "Thu, 05 Aug 2016 06:00:00 -0400" <-- Actually that was friday.
+            published: item.pub_date().and_then(|date| parse_rfc822(date).ok()),
+            enclosure,
+            duration: parser::parse_itunes_duration(item.itunes_ext()),
+            chapters_uri: parser::parse_podcast_chapters(item),
+            transcript: parser::parse_podcast_transcript(item),
+        }
+    }
+}
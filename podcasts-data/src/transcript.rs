@@ -0,0 +1,430 @@
+// transcript.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Parse episode transcripts advertised with the
+//! [`<podcast:transcript>`](https://github.com/Podcastindex-org/podcast-namespace/blob/main/transcripts/transcripts.md)
+//! tag into a common list of cues.
+
+use serde::Deserialize;
+
+use crate::errors::DataError;
+
+/// The file formats a transcript can be published in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TranscriptFormat {
+    // Variants are ordered by preference, timed formats first.
+    /// The Podcasting 2.0 JSON format.
+    Json,
+    /// WebVTT captions.
+    WebVtt,
+    /// SubRip captions.
+    Srt,
+    /// A plain html document, without any timing information.
+    Html,
+}
+
+impl TranscriptFormat {
+    /// Determine the format from the mime type of the `<podcast:transcript>` tag.
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        // Ignore parameters like "; charset=utf-8"
+        let mime = mime.split(';').next()?.trim().to_lowercase();
+        match mime.as_str() {
+            "application/json" => Some(TranscriptFormat::Json),
+            "text/vtt" => Some(TranscriptFormat::WebVtt),
+            "application/srt" | "application/x-subrip" | "text/srt" => Some(TranscriptFormat::Srt),
+            "text/html" => Some(TranscriptFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// The file extension used when caching a transcript of this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscriptFormat::Json => "json",
+            TranscriptFormat::WebVtt => "vtt",
+            TranscriptFormat::Srt => "srt",
+            TranscriptFormat::Html => "html",
+        }
+    }
+}
+
+/// A single line of a `Transcript`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    start: u64,
+    end: u64,
+    speaker: Option<String>,
+    text: String,
+}
+
+impl Cue {
+    /// Offset from the start of the episode in milliseconds.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Offset from the start of the episode in milliseconds.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// The name of the person speaking, if the transcript provides it.
+    pub fn speaker(&self) -> Option<&str> {
+        self.speaker.as_deref()
+    }
+
+    /// The text of the cue.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// The parsed transcript of an episode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    format: TranscriptFormat,
+    cues: Vec<Cue>,
+}
+
+impl Transcript {
+    /// Parse the contents of a transcript file.
+    pub fn parse(format: TranscriptFormat, data: &str) -> Result<Self, DataError> {
+        let cues = match format {
+            TranscriptFormat::Json => parse_json(data)?,
+            TranscriptFormat::WebVtt => parse_vtt(data),
+            TranscriptFormat::Srt => parse_srt(data),
+            TranscriptFormat::Html => parse_html(data),
+        };
+
+        Ok(Transcript { format, cues })
+    }
+
+    /// The format the transcript was parsed from.
+    pub fn format(&self) -> TranscriptFormat {
+        self.format
+    }
+
+    /// The cues of the transcript, ordered by their start.
+    pub fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    /// Whether the cues carry timing information that can be synced with playback.
+    pub fn is_timed(&self) -> bool {
+        self.format != TranscriptFormat::Html
+    }
+
+    /// Returns the index of the cue that is spoken at `position` milliseconds.
+    pub fn cue_at(&self, position: u64) -> Option<usize> {
+        if !self.is_timed() {
+            return None;
+        }
+
+        self.cues
+            .iter()
+            .rposition(|cue| cue.start <= position)
+            .filter(|i| position < self.cues[*i].end)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonTranscript {
+    segments: Vec<JsonSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSegment {
+    speaker: Option<String>,
+    start_time: f64,
+    end_time: f64,
+    body: String,
+}
+
+fn parse_json(data: &str) -> Result<Vec<Cue>, DataError> {
+    let json: JsonTranscript = serde_json::from_str(data)?;
+
+    let mut cues: Vec<_> = json
+        .segments
+        .into_iter()
+        .filter(|s| s.start_time >= 0.0 && !s.body.trim().is_empty())
+        .map(|s| Cue {
+            start: (s.start_time * 1000.0) as u64,
+            end: (s.end_time * 1000.0) as u64,
+            speaker: s.speaker.map(|s| s.trim().to_owned()),
+            text: s.body.trim().to_owned(),
+        })
+        .collect();
+
+    cues.sort_by_key(|c| c.start);
+    Ok(cues)
+}
+
+/// Parse a timestamp of the form `[HH:]MM:SS.mmm`, with either `.` or `,`
+/// as the fraction separator, into milliseconds.
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let (clock, fraction) = s.trim().split_once(['.', ','])?;
+    let millis: u64 = format!("{:0<3}", fraction).get(..3)?.parse().ok()?;
+
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+
+    Some(seconds * 1000 + millis)
+}
+
+/// Parse a `start --> end` line, ignoring any WebVTT cue settings after it.
+fn parse_timing(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Split the contents of caption files into blocks separated by empty lines.
+fn blocks(data: &str) -> impl Iterator<Item = Vec<&str>> {
+    let mut blocks = Vec::new();
+    let mut current = Vec::new();
+
+    for line in data.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks.into_iter()
+}
+
+/// Build a cue out of a block, skipping the optional identifier
+/// before the timing line.
+fn block_to_cue(block: &[&str]) -> Option<Cue> {
+    let timing = block.iter().position(|l| l.contains("-->"))?;
+    let (start, end) = parse_timing(block[timing])?;
+    let text = block[timing + 1..].join("\n");
+
+    let (speaker, text) = split_voice(&text);
+    let text = strip_tags(&text);
+    if text.is_empty() {
+        return None;
+    }
+
+    Some(Cue {
+        start,
+        end,
+        speaker,
+        text,
+    })
+}
+
+/// Extract the speaker of a WebVTT `<v Speaker>` voice span.
+fn split_voice(text: &str) -> (Option<String>, String) {
+    if let Some(rest) = text.strip_prefix("<v") {
+        if let Some((tag, text)) = rest.split_once('>') {
+            // Skip classes, as in `<v.loud Speaker>`
+            let speaker = tag.split_once(char::is_whitespace).map(|(_, s)| s.trim());
+            let speaker = speaker.filter(|s| !s.is_empty()).map(String::from);
+            return (speaker, text.to_owned());
+        }
+    }
+
+    // SRT and some WebVTT files use "Speaker: text" instead
+    (None, text.to_owned())
+}
+
+/// Remove any markup, like `<i>` or `<c.yellow>`, and decode the basic entities.
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => (),
+        }
+    }
+
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .trim()
+        .to_owned()
+}
+
+fn parse_srt(data: &str) -> Vec<Cue> {
+    blocks(data).filter_map(|b| block_to_cue(&b)).collect()
+}
+
+fn parse_vtt(data: &str) -> Vec<Cue> {
+    blocks(data)
+        // Skip the header, and NOTE, STYLE and REGION blocks
+        .filter(|b| b.iter().any(|l| l.contains("-->")))
+        .filter(|b| !b[0].starts_with("NOTE"))
+        .filter_map(|b| block_to_cue(&b))
+        .collect()
+}
+
+fn parse_html(data: &str) -> Vec<Cue> {
+    // Keep paragraphs and line breaks apart after stripping the markup
+    let data = data
+        .replace("</p>", "</p>\n")
+        .replace("<br", "\n<br")
+        .replace("</cite>", "</cite>\n");
+
+    let clean = ammonia::Builder::empty().clean(&data).to_string();
+    clean
+        .lines()
+        .map(strip_tags)
+        .filter(|l| !l.is_empty())
+        .map(|text| Cue {
+            start: 0,
+            end: 0,
+            speaker: None,
+            text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn test_transcript_format() {
+        assert_eq!(
+            TranscriptFormat::from_mime("application/json"),
+            Some(TranscriptFormat::Json)
+        );
+        assert_eq!(
+            TranscriptFormat::from_mime("text/vtt; charset=utf-8"),
+            Some(TranscriptFormat::WebVtt)
+        );
+        assert_eq!(
+            TranscriptFormat::from_mime("application/x-subrip"),
+            Some(TranscriptFormat::Srt)
+        );
+        assert_eq!(
+            TranscriptFormat::from_mime("text/html"),
+            Some(TranscriptFormat::Html)
+        );
+        assert_eq!(TranscriptFormat::from_mime("text/plain"), None);
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(parse_timestamp("00:01.500"), Some(1500));
+        assert_eq!(parse_timestamp("01:02:03,004"), Some(3_723_004));
+        assert_eq!(parse_timestamp("1:02:03.4"), Some(3_723_400));
+        assert_eq!(parse_timestamp("01:02:03"), None);
+        assert_eq!(parse_timestamp("aa:02.000"), None);
+    }
+
+    #[test]
+    fn test_srt() -> Result<()> {
+        let data = fs::read_to_string("tests/feeds/2026-10-18-PodcastNamespace-transcript.srt")?;
+        let transcript = Transcript::parse(TranscriptFormat::Srt, &data)?;
+
+        let cues = transcript.cues();
+        assert_eq!(cues.len(), 3);
+        assert_eq!(cues[0].start(), 0);
+        assert_eq!(cues[0].end(), 4_200);
+        assert_eq!(cues[0].text(), "Welcome to the Podcast Namespace Sampler.");
+        assert_eq!(
+            cues[1].text(),
+            "Today we talk about chapters\nand transcripts."
+        );
+        assert_eq!(cues[2].start(), 3_601_000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vtt() -> Result<()> {
+        let data = fs::read_to_string("tests/feeds/2026-10-18-PodcastNamespace-transcript.vtt")?;
+        let transcript = Transcript::parse(TranscriptFormat::WebVtt, &data)?;
+
+        let cues = transcript.cues();
+        assert_eq!(cues.len(), 3);
+        assert_eq!(cues[0].speaker(), Some("Alice"));
+        assert_eq!(cues[0].text(), "Welcome to the Podcast Namespace Sampler.");
+        assert_eq!(cues[1].start(), 4_200);
+        assert_eq!(cues[1].end(), 9_000);
+        assert_eq!(cues[1].speaker(), Some("Bob"));
+        assert_eq!(
+            cues[1].text(),
+            "Today we talk about chapters & transcripts."
+        );
+        assert_eq!(cues[2].speaker(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> Result<()> {
+        let data = fs::read_to_string("tests/feeds/2026-10-18-PodcastNamespace-transcript.json")?;
+        let transcript = Transcript::parse(TranscriptFormat::Json, &data)?;
+
+        let cues = transcript.cues();
+        assert_eq!(cues.len(), 3);
+        assert_eq!(cues[0].speaker(), Some("Alice"));
+        assert_eq!(cues[0].start(), 500);
+        assert_eq!(cues[2].text(), "Bye!");
+
+        assert_eq!(transcript.cue_at(0), None);
+        assert_eq!(transcript.cue_at(500), Some(0));
+        assert_eq!(transcript.cue_at(5_000), Some(1));
+        // In the gap between two cues
+        assert_eq!(transcript.cue_at(9_500), None);
+
+        assert!(Transcript::parse(TranscriptFormat::Json, "{}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_html() -> Result<()> {
+        let data = "<p><cite>Alice:</cite> Welcome to the show.</p>\
+                    <p>Today we talk about <b>chapters</b> &amp; transcripts.</p>";
+        let transcript = Transcript::parse(TranscriptFormat::Html, data)?;
+
+        assert!(!transcript.is_timed());
+        let texts: Vec<_> = transcript.cues().iter().map(|c| c.text()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Alice:",
+                "Welcome to the show.",
+                "Today we talk about chapters & transcripts."
+            ]
+        );
+        assert_eq!(transcript.cue_at(0), None);
+        Ok(())
+    }
+}
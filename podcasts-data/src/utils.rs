@@ -25,9 +25,10 @@ use url::{Position, Url};
 
 use crate::dbqueries;
 use crate::errors::{DataError, DownloadError};
-use crate::models::{EpisodeCleanerModel, Save, Show};
+use crate::models::{EpisodeCleanerModel, Retention, Save, Show};
 use crate::xdg_dirs::{DL_DIR, PODCASTS_CACHE};
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
@@ -89,14 +90,30 @@ fn update_download_status(mut ep: EpisodeCleanerModel) {
 }
 
 /// Delete watched `episodes` that have exceeded their lifetime after played.
+///
+/// Shows with their own `Retention` policy override `cleanup_date`.
 fn played_cleaner(cleanup_date: DateTime<Utc>) -> Result<(), DataError> {
     let episodes = dbqueries::get_played_cleaner_episodes()?;
+    let retention: HashMap<_, _> = dbqueries::get_all_show_settings()?
+        .into_iter()
+        .map(|settings| (settings.show_id(), settings.retention()))
+        .collect();
     let now_utc = cleanup_date.timestamp() as i32;
+    let now = Utc::now();
 
     episodes
         .into_iter()
-        .filter(|ep| ep.local_uri().is_some() && ep.played().is_some())
-        .for_each(|ep| clean_played(now_utc, ep));
+        .filter(|(_, ep)| ep.local_uri().is_some() && ep.played().is_some())
+        .for_each(|(show_id, ep)| {
+            let limit = match retention.get(&show_id) {
+                Some(Retention::Forever) => return,
+                Some(Retention::Days(days)) => {
+                    (now - chrono::Duration::days(i64::from(*days))).timestamp() as i32
+                }
+                _ => now_utc,
+            };
+            clean_played(limit, ep)
+        });
     Ok(())
 }
 
@@ -214,11 +231,11 @@ use std::convert::TryInto;
 use std::hash::{Hash, Hasher};
 
 #[cfg(test)]
-/// Helper function that open a local file, parse the rss::Channel and gives back a Feed object.
+/// Helper function that open a local file, parse the Channel and gives back a Feed object.
 /// Alternative Feed constructor to be used for tests.
 pub fn get_feed(file_path: &str, id: i32) -> Feed {
     use crate::feed::FeedBuilder;
-    use rss::Channel;
+    use crate::syndication::Channel;
     use std::io::BufReader;
 
     // open the xml file
@@ -345,6 +362,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_played_cleaner_retention() -> Result<()> {
+        let _tmp_dir = helper_db()?;
+        let mut episode = dbqueries::get_episode_cleaner_from_pk("foo_bar", 0)?;
+        let cleanup_date = Utc::now() - Duration::seconds(1000);
+        let epoch = (Utc::now() - Duration::days(10)).timestamp() as i32;
+        episode.set_played(Some(epoch));
+        episode.save()?;
+        let valid_path = episode.local_uri().unwrap().to_owned();
+
+        // The show keeps its episodes around
+        let mut settings = dbqueries::get_show_settings(0)?;
+        settings.set_retention(Retention::Forever);
+        settings.save()?;
+        played_cleaner(cleanup_date)?;
+        assert!(Path::new(&valid_path).exists());
+
+        settings.set_retention(Retention::Days(30));
+        settings.save()?;
+        played_cleaner(cleanup_date)?;
+        assert!(Path::new(&valid_path).exists());
+
+        settings.set_retention(Retention::Days(7));
+        settings.save()?;
+        played_cleaner(cleanup_date)?;
+        assert!(!Path::new(&valid_path).exists());
+        Ok(())
+    }
+
     #[test]
     fn test_url_cleaner() -> Result<()> {
         let good_url = "http://traffic.megaphone.fm/FL8608731318.mp3?updated=1484685184";
@@ -28,8 +28,11 @@ use gettextrs::{bindtextdomain, setlocale, textdomain, LocaleCategory};
 use anyhow::Result;
 use fragile::Fragile;
 use podcasts_data::dbqueries;
+use podcasts_data::errors::DataError;
+use podcasts_data::pipeline::RefreshReport;
 use podcasts_data::{Episode, Show, Source};
 
+use crate::manager;
 use crate::settings;
 use crate::stacks::PopulatedState;
 use crate::utils;
@@ -42,7 +45,7 @@ use std::env;
 use std::sync::Arc;
 
 use crate::config::{APP_ID, LOCALEDIR};
-use crate::i18n::i18n;
+use crate::i18n::{i18n, ni18n_f};
 
 // FIXME: port Optionals to OnceCell
 #[derive(Debug)]
@@ -123,6 +126,15 @@ impl ApplicationImpl for PdApplicationPrivate {
         // Garbage collect watched episodes from the disk
         utils::cleanup(cleanup_date);
 
+        manager::set_max_parallel_downloads(settings::get_max_parallel_downloads(&settings));
+        settings.connect_changed(Some("max-parallel-downloads"), |settings, _| {
+            manager::set_max_parallel_downloads(settings::get_max_parallel_downloads(settings));
+        });
+        // Resume the downloads of the previous session
+        manager::restore()
+            .map_err(|err| error!("Failed to restore the download queue: {}", err))
+            .ok();
+
         self.settings.replace(Some(settings));
     }
 }
@@ -154,12 +166,15 @@ pub(crate) enum Action {
     MarkAllPlayerNotification(Arc<Show>),
     UpdateFeed(Option<Vec<Source>>),
     ShowUpdateNotif,
-    FeedRefreshed,
+    FeedRefreshed(Option<Arc<RefreshReport>>),
+    AutoDownload(Vec<i32>),
     StopUpdating,
     RemoveShow(Arc<Show>),
     ErrorNotification(String),
     InitEpisode(i32),
     InitEpisodeAt(i32, i32),
+    InitEpisodeChapters(i32),
+    RefreshQueue,
     InitSecondaryMenu(Fragile<gio::MenuModel>),
     EmptyState,
     PopulatedState,
@@ -200,6 +215,36 @@ impl PdApplication {
                     }
                 })
                 .build(),
+            gio::ActionEntryBuilder::new("queue-episode")
+                .parameter_type(Some(i32_variant_type))
+                .activate(|app: &Self, _, id_variant_option| {
+                    app.update_queue(id_variant_option, dbqueries::queue_episode);
+                })
+                .build(),
+            gio::ActionEntryBuilder::new("queue-episode-next")
+                .parameter_type(Some(i32_variant_type))
+                .activate(|app: &Self, _, id_variant_option| {
+                    app.update_queue(id_variant_option, dbqueries::queue_episode_next);
+                })
+                .build(),
+            gio::ActionEntryBuilder::new("dequeue-episode")
+                .parameter_type(Some(i32_variant_type))
+                .activate(|app: &Self, _, id_variant_option| {
+                    app.update_queue(id_variant_option, dbqueries::dequeue_episode);
+                })
+                .build(),
+            gio::ActionEntryBuilder::new("move-queued-episode-up")
+                .parameter_type(Some(i32_variant_type))
+                .activate(|app: &Self, _, id_variant_option| {
+                    app.update_queue(id_variant_option, |id| move_queued_episode_by(id, -1));
+                })
+                .build(),
+            gio::ActionEntryBuilder::new("move-queued-episode-down")
+                .parameter_type(Some(i32_variant_type))
+                .activate(|app: &Self, _, id_variant_option| {
+                    app.update_queue(id_variant_option, |id| move_queued_episode_by(id, 1));
+                })
+                .build(),
             gio::ActionEntryBuilder::new("undo-mark-all")
                 .parameter_type(Some(i32_variant_type))
                 .activate(|app: &Self, _, id_variant_option| {
@@ -306,6 +351,20 @@ impl PdApplication {
         Ok(())
     }
 
+    /// Change the Up Next queue with `f`, using the episode rowid of the action target.
+    fn update_queue<F>(&self, id_variant_option: Option<&glib::Variant>, f: F)
+    where
+        F: FnOnce(i32) -> Result<Vec<i32>, DataError>,
+    {
+        let id_variant = id_variant_option.expect("missing action_target_value");
+        let id = id_variant.get::<i32>().expect("invalid variant type");
+        if let Err(err) = f(id) {
+            error!("Failed to update the Up Next queue: {}", err);
+            return;
+        }
+        send!(self.imp().sender, Action::RefreshQueue);
+    }
+
     fn go_back_on_deck(&self) -> bool {
         let data = self.imp();
         let w = data.window.borrow();
@@ -316,6 +375,7 @@ impl PdApplication {
     fn setup_accels(&self) {
         self.set_accels_for_action("app.quit", &["<primary>q"]);
         self.set_accels_for_action("win.refresh", &["<primary>r"]);
+        self.set_accels_for_action("win.search", &["<primary>f"]);
         self.set_accels_for_action("app.go-back", &["Escape"]);
     }
 
@@ -356,7 +416,8 @@ impl PdApplication {
                     .switch_visible(PopulatedState::View, gtk::StackTransitionType::SlideRight);
             }
             Action::GoToEpisodeDescription(show, ep) => {
-                let description_widget = EpisodeDescription::new(ep, show, window.sender().clone());
+                let description_widget =
+                    EpisodeDescription::new(ep, show, window.player(), window.sender().clone());
                 window.push_page(&description_widget);
             }
             Action::GoToShow(pd) => {
@@ -415,10 +476,27 @@ impl PdApplication {
                 );
                 window.set_updating_timeout(Some(updating_timeout));
             }
-            Action::FeedRefreshed => {
+            Action::FeedRefreshed(report) => {
                 let sender = data.sender.clone();
                 send!(sender, Action::StopUpdating);
                 send!(sender, Action::RefreshAllViews);
+                if let Some(toast) = report.as_deref().and_then(refreshed_notif) {
+                    self.send_toast(toast);
+                }
+            }
+            Action::AutoDownload(episodes) => {
+                let allowed = data
+                    .settings
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(settings::can_auto_download);
+                if allowed {
+                    manager::add_new_episodes(&episodes)
+                        .map_err(|err| error!("Failed to auto-download episodes: {}", err))
+                        .ok();
+                } else {
+                    info!("Skipping {} automatic downloads", episodes.len());
+                }
             }
             Action::InitEpisode(rowid) => {
                 let res = window.init_episode(rowid, None);
@@ -428,6 +506,12 @@ impl PdApplication {
                 let res = window.init_episode(rowid, Some(second));
                 debug_assert!(res.is_ok());
             }
+            Action::RefreshQueue => window.player().borrow().refresh_queue(),
+            Action::InitEpisodeChapters(rowid) => {
+                if let Err(err) = window.init_episode_chapters(rowid) {
+                    error!("Failed to load the chapters of episode {}: {}", rowid, err);
+                }
+            }
             Action::InitSecondaryMenu(s) => {
                 let menu = &s.get();
                 window.headerbar().set_secondary_menu(menu);
@@ -507,3 +591,49 @@ impl PdApplication {
             .add_toast(toast);
     }
 }
+
+/// Move a queued episode `offset` places towards the end of the queue.
+/// Sums up a refresh, unless it neither found new episodes nor failed.
+fn refreshed_notif(report: &RefreshReport) -> Option<adw::Toast> {
+    let new = report.new_episodes() as u32;
+    let failures = report.failures().count() as u32;
+    let text = match (new, failures) {
+        (0, 0) => return None,
+        (_, 0) => ni18n_f(
+            "{} new episode",
+            "{} new episodes",
+            new,
+            &[&new.to_string()],
+        ),
+        (0, _) => ni18n_f(
+            "{} feed could not be refreshed",
+            "{} feeds could not be refreshed",
+            failures,
+            &[&failures.to_string()],
+        ),
+        (_, _) => ni18n_f(
+            "{} new episode, some feeds could not be refreshed",
+            "{} new episodes, some feeds could not be refreshed",
+            new,
+            &[&new.to_string()],
+        ),
+    };
+
+    let toast = adw::Toast::new(&text);
+    if failures > 0 {
+        toast.set_button_label(Some(&i18n("Details")));
+        toast.set_action_name(Some("win.feed-health"));
+    }
+    Some(toast)
+}
+
+fn move_queued_episode_by(rowid: i32, offset: isize) -> Result<Vec<i32>, DataError> {
+    let queue = dbqueries::get_play_queue()?;
+    match queue.iter().position(|id| *id == rowid) {
+        Some(index) => {
+            let index = index.saturating_add_signed(offset);
+            dbqueries::move_queued_episode(rowid, index)
+        }
+        None => Ok(queue),
+    }
+}
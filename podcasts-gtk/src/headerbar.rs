@@ -41,6 +41,7 @@ pub(crate) struct Header {
     pub(crate) container: adw::HeaderBar,
     pub(crate) switch: adw::ViewSwitcher,
     back: gtk::Button,
+    search: gtk::Button,
     hamburger: gtk::MenuButton,
     add: AddPopover,
     dots: gtk::MenuButton,
@@ -175,6 +176,7 @@ impl Default for Header {
         let header = builder.object("headerbar").unwrap();
         let switch: adw::ViewSwitcher = builder.object("switch").unwrap();
         let back = builder.object("back").unwrap();
+        let search = builder.object("search").unwrap();
 
         // The hamburger menu
         let hamburger: gtk::MenuButton = builder.object("hamburger").unwrap();
@@ -199,6 +201,7 @@ impl Default for Header {
             container: header,
             switch,
             back,
+            search,
             hamburger,
             add,
             dots,
@@ -246,6 +249,7 @@ impl Header {
 
     pub(crate) fn switch_to_back(&self) {
         self.add.toggle.set_visible(false);
+        self.search.set_visible(false);
         self.back.set_visible(true);
         self.hamburger.set_visible(false);
         self.dots.set_visible(true);
@@ -253,6 +257,7 @@ impl Header {
 
     pub(crate) fn switch_to_normal(&self) {
         self.add.toggle.set_visible(true);
+        self.search.set_visible(true);
         self.back.set_visible(false);
         self.hamburger.set_visible(true);
         self.dots.set_visible(false);
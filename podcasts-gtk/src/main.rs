@@ -57,7 +57,9 @@ mod headerbar;
 mod window;
 
 mod manager;
+mod mpris;
 mod settings;
+mod sleep_timer;
 
 mod episode_description_parser;
 mod i18n;
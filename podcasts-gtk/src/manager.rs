@@ -18,12 +18,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use anyhow::{anyhow, Result};
+use chrono::Utc;
 use once_cell::sync::Lazy;
 
 use podcasts_data::dbqueries;
 use podcasts_data::downloader::{get_episode, DownloadProgress};
+use podcasts_data::utils::get_download_dir;
+use podcasts_data::{DownloadState, QueuedDownload, Save};
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 
 // This is messy, undocumented and hacky af.
@@ -78,38 +82,133 @@ impl DownloadProgress for Progress {
     }
 }
 
-pub(crate) fn add(id: i32, directory: String) -> Result<()> {
-    // Create a new `Progress` struct to keep track of dl progress.
-    let prog = Arc::new(Mutex::new(Progress::default()));
+/// How many downloads are allowed to run at the same time.
+static MAX_PARALLEL_DOWNLOADS: AtomicUsize = AtomicUsize::new(3);
+
+/// Serializes picking downloads off the queue, so tasks finishing at the
+/// same time don't start more downloads than allowed.
+static SCHEDULER: Mutex<()> = Mutex::new(());
+
+pub(crate) fn set_max_parallel_downloads(max: usize) {
+    MAX_PARALLEL_DOWNLOADS.store(max.max(1), Ordering::Relaxed);
+    schedule();
+}
 
+/// Get the `Progress` of a download, creating it if needed.
+fn track(id: i32) -> Result<Arc<Mutex<Progress>>> {
     match ACTIVE_DOWNLOADS.write() {
-        Ok(mut guard) => guard.insert(id, prog.clone()),
-        Err(err) => return Err(anyhow!("ActiveDownloads: {}.", err)),
-    };
+        Ok(mut guard) => Ok(guard.entry(id).or_default().clone()),
+        Err(err) => Err(anyhow!("ActiveDownloads: {}.", err)),
+    }
+}
 
-    crate::RUNTIME.spawn(async move {
-        if let Ok(mut episode) = dbqueries::get_episode_widget_from_rowid(id) {
-            let id = episode.rowid();
+fn untrack(id: i32) {
+    if let Ok(mut m) = ACTIVE_DOWNLOADS.write() {
+        let progress = m.remove(&id);
+        debug!("Removed: {:?}", progress);
+    }
+}
 
-            get_episode(&mut episode, directory.as_str(), Some(prog))
-                .await
-                .map_err(|err| error!("Download Failed: {}", err))
-                .ok();
+pub(crate) fn add(id: i32, directory: String) -> Result<()> {
+    dbqueries::enqueue_download(id, &directory)?;
+    // Create a new `Progress` struct to keep track of dl progress.
+    track(id)?;
+    schedule();
+    Ok(())
+}
 
-            if let Ok(mut m) = ACTIVE_DOWNLOADS.write() {
-                let progress = m.remove(&id);
-                debug!("Removed: {:?}", progress);
-            }
+/// Queue the new episodes picked by the `AutoDownload` policy of their show.
+pub(crate) fn add_new_episodes(episodes: &[i32]) -> Result<()> {
+    for &id in episodes {
+        let episode = dbqueries::get_episode_from_rowid(id)?;
+        let show = dbqueries::get_podcast_from_id(episode.show_id())?;
+        info!("Auto-downloading {}", episode.title());
+        add(id, get_download_dir(show.title())?)?;
+    }
+    Ok(())
+}
 
-            // if let Ok(m) = ACTIVE_DOWNLOADS.read() {
-            //     debug!("ACTIVE DOWNLOADS: {:#?}", m);
-            // }
+/// Stop a download, or take it off the queue if it hasn't started yet.
+pub(crate) fn cancel(id: i32) -> Result<()> {
+    if let Ok(guard) = ACTIVE_DOWNLOADS.read() {
+        if let Some(Ok(mut m)) = guard.get(&id).map(|prog| prog.lock()) {
+            m.cancel();
         }
-    });
+    }
+
+    let _guard = SCHEDULER
+        .lock()
+        .map_err(|err| anyhow!("Scheduler: {}.", err))?;
+    let mut download = dbqueries::get_queued_download(id)?;
+    if download.state() == DownloadState::Queued {
+        download.set_state(DownloadState::Paused);
+        download.save()?;
+        untrack(id);
+    }
+    Ok(())
+}
 
+/// Pick up the downloads that were queued or running when the app quit.
+pub(crate) fn restore() -> Result<()> {
+    for download in dbqueries::restore_download_queue()? {
+        track(download.episode_id())?;
+    }
+    schedule();
     Ok(())
 }
 
+/// Start as many queued downloads as the parallel limit allows.
+fn schedule() {
+    start_due_downloads()
+        .map_err(|err| error!("Failed to start queued downloads: {}", err))
+        .ok();
+}
+
+fn start_due_downloads() -> Result<()> {
+    let _guard = SCHEDULER
+        .lock()
+        .map_err(|err| anyhow!("Scheduler: {}.", err))?;
+    let running = dbqueries::count_running_downloads()? as usize;
+    let free = MAX_PARALLEL_DOWNLOADS
+        .load(Ordering::Relaxed)
+        .saturating_sub(running);
+    if free == 0 {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp() as i32;
+    for mut download in dbqueries::get_due_downloads(now, free as i64)? {
+        let prog = track(download.episode_id())?;
+        download.set_state(DownloadState::Running);
+        download.save()?;
+        crate::RUNTIME.spawn(run(download, prog));
+    }
+    Ok(())
+}
+
+async fn run(mut download: QueuedDownload, prog: Arc<Mutex<Progress>>) {
+    let id = download.episode_id();
+    let result = match dbqueries::get_episode_widget_from_rowid(id) {
+        Ok(mut episode) => get_episode(&mut episode, download.download_dir(), Some(prog)).await,
+        Err(err) => Err(err.into()),
+    };
+
+    let retry = download.finish(&result);
+
+    download
+        .save()
+        .map_err(|err| error!("Failed to update the download queue: {}", err))
+        .ok();
+    untrack(id);
+    schedule();
+
+    if let Some(delay) = retry {
+        info!("Retrying the download of episode {} in {:?}", id, delay);
+        tokio::time::sleep(delay).await;
+        schedule();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,9 +244,9 @@ mod tests {
 
         // Get the podcast
         let pd = dbqueries::get_podcast_from_source_id(sid)?;
-        let title = "Coming soon... The Tip Off";
+        let guid = "tag:soundcloud,2010:tracks/327539708";
         // Get an episode
-        let episode: Episode = dbqueries::get_episode_from_pk(title, pd.id())?;
+        let episode: Episode = dbqueries::get_episode_from_pk(guid, pd.id())?;
 
         let download_dir = get_download_dir(pd.title())?;
         let dir2 = download_dir.clone();
@@ -182,9 +281,9 @@ mod tests {
 
         // Get the podcast
         let pd = dbqueries::get_podcast_from_source_id(sid)?;
-        let title = "Introducing Steal the Stars";
+        let guid = "gid://art19-episode-locator/V0/S6kmOE2cviFS0HD-IUYOPRO0fvjTPYmCsMDe5bjABnA";
         // Get an episode
-        let mut episode = dbqueries::get_episode_from_pk(title, pd.id())?.into();
+        let mut episode = dbqueries::get_episode_from_pk(guid, pd.id())?.into();
         let download_dir = get_download_dir(pd.title())?;
 
         rt.block_on(get_episode(&mut episode, &download_dir, None))?;
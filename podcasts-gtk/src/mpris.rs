@@ -0,0 +1,463 @@
+// mpris.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The MPRIS server of the player.
+//!
+//! Besides the usual player controls, the Up Next queue is exposed as the
+//! `TrackList` of the player, with the playing episode as the current track.
+
+use gtk::glib;
+use mpris_server::zbus::{self, fdo};
+use mpris_server::{
+    LocalPlayerInterface, LocalRootInterface, LocalServer, LocalTrackListInterface, LoopStatus,
+    Metadata, PlaybackRate, PlaybackStatus, Property, Time, TrackId, TrackListSignal, Volume,
+};
+use url::Url;
+
+use podcasts_data::{dbqueries, downloader, EpisodeWidgetModel, ShowCoverModel};
+
+use crate::config::APP_ID;
+use crate::i18n::i18n;
+
+use std::cell::{Cell, RefCell};
+
+const TRACK_PREFIX: &str = "/org/gnome/Podcasts/Track/";
+
+type Callback<T> = RefCell<Option<Box<dyn Fn(T)>>>;
+
+fn track_id(rowid: i32) -> TrackId {
+    TrackId::try_from(format!("{TRACK_PREFIX}{rowid}")).expect("Invalid MPRIS track id")
+}
+
+fn track_rowid(track: &TrackId) -> Option<i32> {
+    track.as_str().strip_prefix(TRACK_PREFIX)?.parse().ok()
+}
+
+/// Build the MPRIS `Metadata` of an episode.
+pub(crate) fn episode_metadata(episode: &EpisodeWidgetModel, show: &ShowCoverModel) -> Metadata {
+    let mut metadata = Metadata::new();
+    metadata.set_trackid(Some(track_id(episode.rowid())));
+    metadata.set_artist(Some(vec![show.title().to_string()]));
+    metadata.set_title(Some(episode.title().to_string()));
+    metadata.set_length(episode.duration().map(|d| Time::from_secs(d.into())));
+
+    // Set the cover if it is already cached.
+    if let Some(path) = downloader::check_for_cached_cover(show)
+        .as_ref()
+        .and_then(|p| p.to_str())
+    {
+        metadata.set_art_url(Url::from_file_path(path).ok());
+    } else {
+        // fallback: set the cover to the http url if it isn't cached, yet.
+        // TODO we could trigger an async download of the cover here
+        // and update the metadata when it's done.
+        metadata.set_art_url(show.image_uri());
+    }
+    metadata
+}
+
+fn rowid_metadata(rowid: i32) -> Option<Metadata> {
+    let episode = dbqueries::get_episode_widget_from_rowid(rowid).ok()?;
+    let show = dbqueries::get_podcast_cover_from_id(episode.show_id()).ok()?;
+    Some(episode_metadata(&episode, &show))
+}
+
+pub(crate) struct MprisImp {
+    playback_status: Cell<PlaybackStatus>,
+    metadata: RefCell<Metadata>,
+    can_play: Cell<bool>,
+    can_pause: Cell<bool>,
+    can_seek: Cell<bool>,
+    can_go_next: Cell<bool>,
+    tracks: RefCell<Vec<TrackId>>,
+    on_play_pause: Callback<PlaybackStatus>,
+    on_play: Callback<()>,
+    on_pause: Callback<()>,
+    on_next: Callback<()>,
+    on_seek: Callback<Time>,
+    on_go_to: Callback<i32>,
+    on_raise: Callback<()>,
+}
+
+impl MprisImp {
+    fn call<T>(callback: &Callback<T>, value: T) {
+        if let Some(f) = callback.borrow().as_ref() {
+            f(value);
+        }
+    }
+}
+
+impl Default for MprisImp {
+    fn default() -> Self {
+        MprisImp {
+            playback_status: Cell::new(PlaybackStatus::Stopped),
+            metadata: RefCell::new(Metadata::new()),
+            can_play: Cell::new(false),
+            can_pause: Cell::new(false),
+            can_seek: Cell::new(false),
+            can_go_next: Cell::new(false),
+            tracks: RefCell::new(Vec::new()),
+            on_play_pause: RefCell::new(None),
+            on_play: RefCell::new(None),
+            on_pause: RefCell::new(None),
+            on_next: RefCell::new(None),
+            on_seek: RefCell::new(None),
+            on_go_to: RefCell::new(None),
+            on_raise: RefCell::new(None),
+        }
+    }
+}
+
+#[mpris_server::async_trait(?Send)]
+impl LocalRootInterface for MprisImp {
+    async fn raise(&self) -> fdo::Result<()> {
+        Self::call(&self.on_raise, ());
+        Ok(())
+    }
+
+    async fn quit(&self) -> fdo::Result<()> {
+        Ok(())
+    }
+
+    async fn can_quit(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn fullscreen(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn set_fullscreen(&self, _fullscreen: bool) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn can_set_fullscreen(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn can_raise(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn has_track_list(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn identity(&self) -> fdo::Result<String> {
+        Ok(i18n("Podcasts"))
+    }
+
+    async fn desktop_entry(&self) -> fdo::Result<String> {
+        Ok(APP_ID.to_string())
+    }
+
+    async fn supported_uri_schemes(&self) -> fdo::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn supported_mime_types(&self) -> fdo::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+#[mpris_server::async_trait(?Send)]
+impl LocalPlayerInterface for MprisImp {
+    async fn next(&self) -> fdo::Result<()> {
+        Self::call(&self.on_next, ());
+        Ok(())
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        Ok(())
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        Self::call(&self.on_pause, ());
+        Ok(())
+    }
+
+    async fn play_pause(&self) -> fdo::Result<()> {
+        Self::call(&self.on_play_pause, self.playback_status.get());
+        Ok(())
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        Self::call(&self.on_pause, ());
+        Ok(())
+    }
+
+    async fn play(&self) -> fdo::Result<()> {
+        Self::call(&self.on_play, ());
+        Ok(())
+    }
+
+    async fn seek(&self, offset: Time) -> fdo::Result<()> {
+        Self::call(&self.on_seek, offset);
+        Ok(())
+    }
+
+    async fn set_position(&self, _track_id: TrackId, _position: Time) -> fdo::Result<()> {
+        Ok(())
+    }
+
+    async fn open_uri(&self, _uri: String) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("OpenUri is not supported".into()))
+    }
+
+    async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
+        Ok(self.playback_status.get())
+    }
+
+    async fn loop_status(&self) -> fdo::Result<LoopStatus> {
+        Ok(LoopStatus::None)
+    }
+
+    async fn set_loop_status(&self, _loop_status: LoopStatus) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(1.0)
+    }
+
+    async fn set_rate(&self, _rate: PlaybackRate) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn shuffle(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn set_shuffle(&self, _shuffle: bool) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn metadata(&self) -> fdo::Result<Metadata> {
+        Ok(self.metadata.borrow().clone())
+    }
+
+    async fn volume(&self) -> fdo::Result<Volume> {
+        Ok(1.0)
+    }
+
+    async fn set_volume(&self, _volume: Volume) -> zbus::Result<()> {
+        Ok(())
+    }
+
+    async fn position(&self) -> fdo::Result<Time> {
+        Ok(Time::ZERO)
+    }
+
+    async fn minimum_rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(1.0)
+    }
+
+    async fn maximum_rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(1.0)
+    }
+
+    async fn can_go_next(&self) -> fdo::Result<bool> {
+        Ok(self.can_go_next.get())
+    }
+
+    async fn can_go_previous(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn can_play(&self) -> fdo::Result<bool> {
+        Ok(self.can_play.get())
+    }
+
+    async fn can_pause(&self) -> fdo::Result<bool> {
+        Ok(self.can_pause.get())
+    }
+
+    async fn can_seek(&self) -> fdo::Result<bool> {
+        Ok(self.can_seek.get())
+    }
+
+    async fn can_control(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+}
+
+#[mpris_server::async_trait(?Send)]
+impl LocalTrackListInterface for MprisImp {
+    async fn get_tracks_metadata(&self, track_ids: Vec<TrackId>) -> fdo::Result<Vec<Metadata>> {
+        Ok(track_ids
+            .iter()
+            .filter_map(track_rowid)
+            .filter_map(rowid_metadata)
+            .collect())
+    }
+
+    async fn add_track(
+        &self,
+        _uri: String,
+        _after_track: TrackId,
+        _set_as_current: bool,
+    ) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("AddTrack is not supported".into()))
+    }
+
+    async fn remove_track(&self, _track_id: TrackId) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(
+            "RemoveTrack is not supported".into(),
+        ))
+    }
+
+    async fn go_to(&self, track_id: TrackId) -> fdo::Result<()> {
+        if let Some(rowid) = track_rowid(&track_id) {
+            Self::call(&self.on_go_to, rowid);
+        }
+        Ok(())
+    }
+
+    async fn tracks(&self) -> fdo::Result<Vec<TrackId>> {
+        Ok(self.tracks.borrow().clone())
+    }
+
+    async fn can_edit_tracks(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+}
+
+/// The MPRIS server, which is only usable from the main thread.
+pub(crate) struct Mpris {
+    server: LocalServer<MprisImp>,
+}
+
+impl Mpris {
+    /// Create the server and start serving it on the main context.
+    pub(crate) fn new() -> Self {
+        let server = LocalServer::new_with_track_list(APP_ID, MprisImp::default());
+
+        let task = server.init_and_run();
+        crate::MAINCONTEXT.spawn_local_with_priority(glib::source::Priority::LOW, async move {
+            if let Err(err) = task.await {
+                error!("Failed to run MPRIS server: {err:?}");
+            }
+        });
+
+        Mpris { server }
+    }
+
+    fn imp(&self) -> &MprisImp {
+        self.server.imp()
+    }
+
+    async fn set_flag(
+        &self,
+        flag: &Cell<bool>,
+        value: bool,
+        property: Property,
+    ) -> zbus::Result<()> {
+        if flag.replace(value) != value {
+            self.server.properties_changed(property).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn set_playback_status(&self, status: PlaybackStatus) -> zbus::Result<()> {
+        if self.imp().playback_status.replace(status) != status {
+            self.server
+                .properties_changed(Property::PlaybackStatus)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn set_metadata(&self, metadata: Metadata) -> zbus::Result<()> {
+        self.imp().metadata.replace(metadata);
+        self.server.properties_changed(Property::Metadata).await
+    }
+
+    pub(crate) async fn set_can_play(&self, value: bool) -> zbus::Result<()> {
+        self.set_flag(&self.imp().can_play, value, Property::CanPlay)
+            .await
+    }
+
+    pub(crate) async fn set_can_pause(&self, value: bool) -> zbus::Result<()> {
+        self.set_flag(&self.imp().can_pause, value, Property::CanPause)
+            .await
+    }
+
+    pub(crate) async fn set_can_seek(&self, value: bool) -> zbus::Result<()> {
+        self.set_flag(&self.imp().can_seek, value, Property::CanSeek)
+            .await
+    }
+
+    pub(crate) async fn set_can_go_next(&self, value: bool) -> zbus::Result<()> {
+        self.set_flag(&self.imp().can_go_next, value, Property::CanGoNext)
+            .await
+    }
+
+    /// Replace the `TrackList` with the playing episode followed by the queue.
+    pub(crate) async fn set_tracks(&self, current: Option<i32>, queue: &[i32]) -> zbus::Result<()> {
+        let tracks: Vec<TrackId> = current
+            .into_iter()
+            .chain(queue.iter().copied().filter(|id| Some(*id) != current))
+            .map(track_id)
+            .collect();
+        self.imp().tracks.replace(tracks.clone());
+
+        let current_track = current.map(track_id).unwrap_or(TrackId::NO_TRACK);
+        self.server
+            .track_list_emit(TrackListSignal::TrackListReplaced {
+                tracks,
+                current_track,
+            })
+            .await
+    }
+
+    pub(crate) fn connect_play_pause(&self, f: impl Fn(PlaybackStatus) + 'static) {
+        self.imp().on_play_pause.replace(Some(Box::new(f)));
+    }
+
+    pub(crate) fn connect_play(&self, f: impl Fn(()) + 'static) {
+        self.imp().on_play.replace(Some(Box::new(f)));
+    }
+
+    pub(crate) fn connect_pause(&self, f: impl Fn(()) + 'static) {
+        self.imp().on_pause.replace(Some(Box::new(f)));
+    }
+
+    pub(crate) fn connect_next(&self, f: impl Fn(()) + 'static) {
+        self.imp().on_next.replace(Some(Box::new(f)));
+    }
+
+    pub(crate) fn connect_seek(&self, f: impl Fn(Time) + 'static) {
+        self.imp().on_seek.replace(Some(Box::new(f)));
+    }
+
+    /// `f` is called with the rowid of the episode picked from the `TrackList`.
+    pub(crate) fn connect_go_to(&self, f: impl Fn(i32) + 'static) {
+        self.imp().on_go_to.replace(Some(Box::new(f)));
+    }
+
+    pub(crate) fn connect_raise(&self, f: impl Fn(()) + 'static) {
+        self.imp().on_raise.replace(Some(Box::new(f)));
+    }
+}
+
+impl std::fmt::Debug for Mpris {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mpris").finish()
+    }
+}
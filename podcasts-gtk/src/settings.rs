@@ -16,7 +16,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
-use gio::{prelude::SettingsExt, Settings};
+use gio::{prelude::NetworkMonitorExt, prelude::SettingsExt, Settings};
 use gtk::gio;
 use gtk::prelude::GtkWindowExt;
 
@@ -93,6 +93,16 @@ pub(crate) fn get_cleanup_date(settings: &Settings) -> DateTime<Utc> {
     Utc::now() - duration
 }
 
+pub(crate) fn get_max_parallel_downloads(settings: &Settings) -> usize {
+    settings.int("max-parallel-downloads").max(1) as usize
+}
+
+/// Whether new episodes can be downloaded without the user asking for it.
+pub(crate) fn can_auto_download(settings: &Settings) -> bool {
+    !settings.boolean("auto-download-unmetered-only")
+        || !gio::NetworkMonitor::default().is_network_metered()
+}
+
 pub(crate) fn time_period_to_duration(time: i64, period: &str) -> Duration {
     match period {
         "weeks" => Duration::weeks(time),
@@ -0,0 +1,195 @@
+// sleep_timer.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Stop playback after a while, fading the volume out first.
+
+use std::time::{Duration, Instant};
+
+/// How long the volume takes to fade out before the player stops.
+const FADE_OUT: Duration = Duration::from_secs(10);
+/// Ticks further apart than this mean that playback was paused in between.
+const MAX_TICK: Duration = Duration::from_secs(1);
+
+/// When the sleep timer goes off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum SleepMode {
+    #[default]
+    Off,
+    /// After this many minutes of playback.
+    Minutes(u32),
+    /// Once the current episode is over.
+    EndOfEpisode,
+}
+
+impl SleepMode {
+    /// Parse the target of the `sleep.set` action.
+    pub(crate) fn from_target(target: &str) -> Self {
+        match target {
+            "episode" => SleepMode::EndOfEpisode,
+            minutes => minutes
+                .parse()
+                .ok()
+                .filter(|m| *m > 0)
+                .map_or(SleepMode::Off, SleepMode::Minutes),
+        }
+    }
+
+    /// The target of the `sleep.set` action for this mode.
+    pub(crate) fn target(&self) -> String {
+        match self {
+            SleepMode::Off => "off".to_string(),
+            SleepMode::Minutes(minutes) => minutes.to_string(),
+            SleepMode::EndOfEpisode => "episode".to_string(),
+        }
+    }
+}
+
+/// What the player should do after a tick of the timer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SleepTick {
+    Idle,
+    /// Scale the volume by this factor.
+    Fade(f64),
+    /// Stop playing.
+    Sleep,
+}
+
+/// Counts down while the player is playing.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SleepTimer {
+    mode: SleepMode,
+    /// Playing time left for `SleepMode::Minutes`.
+    left: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl SleepTimer {
+    pub(crate) fn mode(&self) -> SleepMode {
+        self.mode
+    }
+
+    pub(crate) fn set(&mut self, mode: SleepMode) {
+        self.mode = mode;
+        self.left = match mode {
+            SleepMode::Minutes(minutes) => Duration::from_secs(u64::from(minutes) * 60),
+            _ => Duration::ZERO,
+        };
+        self.last_tick = None;
+    }
+
+    /// Playing time left before the timer goes off, if it is running.
+    ///
+    /// `until_end` is the playing time left in the current episode.
+    pub(crate) fn left(&self, until_end: Option<Duration>) -> Option<Duration> {
+        match self.mode {
+            SleepMode::Off => None,
+            SleepMode::Minutes(_) => Some(self.left),
+            SleepMode::EndOfEpisode => until_end,
+        }
+    }
+
+    /// Count down the playing time since the last tick.
+    ///
+    /// The end of the episode already stops the player, so only the
+    /// `Minutes` mode ever asks to `Sleep`.
+    pub(crate) fn tick(&mut self, now: Instant, until_end: Option<Duration>) -> SleepTick {
+        let elapsed = self
+            .last_tick
+            .map(|last| now.saturating_duration_since(last))
+            .filter(|elapsed| *elapsed <= MAX_TICK)
+            .unwrap_or_default();
+        self.last_tick = Some(now);
+
+        if let SleepMode::Minutes(_) = self.mode {
+            self.left = self.left.saturating_sub(elapsed);
+            if self.left.is_zero() {
+                return SleepTick::Sleep;
+            }
+        }
+
+        match self.left(until_end) {
+            Some(left) if left < FADE_OUT => {
+                SleepTick::Fade(left.as_secs_f64() / FADE_OUT.as_secs_f64())
+            }
+            _ => SleepTick::Idle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleep_mode_target() {
+        for mode in [
+            SleepMode::Off,
+            SleepMode::Minutes(30),
+            SleepMode::EndOfEpisode,
+        ] {
+            assert_eq!(SleepMode::from_target(&mode.target()), mode);
+        }
+        assert_eq!(SleepMode::from_target("0"), SleepMode::Off);
+        assert_eq!(SleepMode::from_target("soon"), SleepMode::Off);
+    }
+
+    #[test]
+    fn test_sleep_after_minutes() {
+        let mut timer = SleepTimer::default();
+        timer.set(SleepMode::Minutes(1));
+        let start = Instant::now();
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+
+        assert_eq!(timer.tick(at(0.0), None), SleepTick::Idle);
+        let mut secs = 0.0;
+        while secs < 50.0 {
+            secs += 0.5;
+            assert_eq!(timer.tick(at(secs), None), SleepTick::Idle);
+        }
+
+        // A pause doesn't count towards the timer
+        secs += 600.0;
+        assert_eq!(timer.tick(at(secs), None), SleepTick::Idle);
+        assert_eq!(timer.left(None), Some(Duration::from_secs(10)));
+
+        for _ in 0..10 {
+            secs += 0.5;
+            timer.tick(at(secs), None);
+        }
+        assert_eq!(timer.tick(at(secs), None), SleepTick::Fade(0.5));
+        while timer.tick(at(secs), None) != SleepTick::Sleep {
+            secs += 0.5;
+            assert!(secs < 670.0);
+        }
+    }
+
+    #[test]
+    fn test_sleep_at_end_of_episode() {
+        let mut timer = SleepTimer::default();
+        let now = Instant::now();
+        let until_end = Some(Duration::from_secs(5));
+        assert_eq!(timer.tick(now, until_end), SleepTick::Idle);
+
+        timer.set(SleepMode::EndOfEpisode);
+        let far = Some(Duration::from_secs(300));
+        assert_eq!(timer.tick(now, far), SleepTick::Idle);
+        assert_eq!(timer.tick(now, until_end), SleepTick::Fade(0.5));
+        assert_eq!(timer.tick(now, Some(Duration::ZERO)), SleepTick::Fade(0.0));
+    }
+}
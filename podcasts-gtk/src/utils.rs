@@ -38,6 +38,7 @@ use serde_json::Value;
 use tokio::sync::oneshot::error::TryRecvError;
 use url::Url;
 
+use podcasts_data::archive;
 use podcasts_data::dbqueries;
 use podcasts_data::downloader;
 use podcasts_data::downloader::client_builder;
@@ -45,7 +46,7 @@ use podcasts_data::errors::DownloadError;
 use podcasts_data::opml;
 use podcasts_data::pipeline::pipeline;
 use podcasts_data::utils::checkup;
-use podcasts_data::Source;
+use podcasts_data::{Database, Source};
 
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex, RwLock};
@@ -313,6 +314,21 @@ pub(crate) fn schedule_refresh(source: Option<Vec<Source>>, sender: Sender<Actio
     send!(sender, Action::UpdateFeed(source));
 }
 
+/// Schedule a refresh of the sources that are due, according to the update
+/// hints of their feeds and their failures.
+/// Meant for the periodic refreshes, the user can always refresh everything
+/// with `schedule_refresh`.
+pub(crate) fn schedule_due_refresh(sender: Sender<Action>) {
+    let now = Utc::now().timestamp() as i32;
+    match dbqueries::get_due_sources(now) {
+        Ok(sources) if sources.is_empty() => {
+            info!("No source of feeds is due for a refresh, returning");
+        }
+        Ok(sources) => schedule_refresh(Some(sources), sender),
+        Err(err) => error!("Failed to get the sources due for a refresh: {}", err),
+    }
+}
+
 /// Update the rss feed(s) originating from `source`.
 /// If `source` is None, Fetches all the `Source` entries in the database and updates them.
 /// Do not call this function directly unless you are sure no other updates are running.
@@ -321,20 +337,28 @@ pub(crate) fn refresh_feed(source: Option<Vec<Source>>, sender: Sender<Action>)
     send!(sender, Action::ShowUpdateNotif);
 
     crate::RUNTIME.spawn(clone!(@strong sender => async move {
-        if let Some(s) = source {
+        let result = if let Some(s) = source {
             // Refresh only specified feeds
-            if let Err(err) = pipeline(s).await {
-                error!("failed to fetch feed {}", err);
-            }
-            send!(sender, Action::FeedRefreshed);
+            pipeline(s).await
         } else {
-            // Refresh all the feeds
+            // Refresh all the feeds, even the ones that aren't due
             let sources = dbqueries::get_sources().map(|s| s.into_iter()).unwrap();
-            if let Err(err) = pipeline(sources).await {
+            pipeline(sources).await
+        };
+
+        let report = match result {
+            Ok(report) => Some(Arc::new(report)),
+            Err(err) => {
                 error!("failed to fetch feed {}", err);
+                None
+            }
+        };
+        if let Some(downloads) = report.as_ref().map(|r| r.downloads()) {
+            if !downloads.is_empty() {
+                send!(sender, Action::AutoDownload(downloads.to_vec()));
             }
-            send!(sender, Action::FeedRefreshed);
         }
+        send!(sender, Action::FeedRefreshed(report));
     }));
 }
 
@@ -594,6 +618,117 @@ pub(crate) fn on_export_clicked(window: &gtk::ApplicationWindow, sender: &Sender
     );
 }
 
+/// The filters of the file dialogs of library archives.
+fn library_filters() -> gio::ListStore {
+    let filter = FileFilter::new();
+    FileFilter::set_name(&filter, Some(i18n("Podcasts library").as_str()));
+    filter.add_mime_type("application/json");
+
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+    filters.append(&filter);
+    filters
+}
+
+pub(crate) fn on_import_library_clicked(window: &gtk::ApplicationWindow, sender: &Sender<Action>) {
+    let dialog = gtk::FileDialog::builder()
+        .title(i18n("Select the library you want to restore."))
+        .accept_label(i18n("_Import"))
+        .filters(&library_filters())
+        .build();
+
+    dialog.open(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(@strong sender, @strong dialog => move |result| {
+            if let Ok(file) = result {
+                if let Some(path) = file.peek_path() {
+                    gio::spawn_blocking(clone!(@strong sender => move || {
+                        match archive::import_from_file(&Database::global(), path) {
+                            // Refresh the restored shows to fetch what changed since
+                            Ok(sources) => schedule_refresh(Some(sources), sender),
+                            Err(err) => {
+                                error!("Failed to import the library: {}", err);
+                                let text = i18n("Failed to import the library");
+                                send!(sender, Action::ErrorNotification(text));
+                            }
+                        }
+                    }));
+                }
+            }
+        }),
+    );
+}
+
+pub(crate) fn on_export_library_clicked(window: &gtk::ApplicationWindow, sender: &Sender<Action>) {
+    let dialog = gtk::FileDialog::builder()
+        .title(i18n("Export library to…"))
+        .accept_label(i18n("_Export"))
+        .initial_name(format!(
+            "{}.json",
+            // Translators: This is the string of the suggested name for the exported library
+            i18n("gnome-podcasts-library")
+        ))
+        .filters(&library_filters())
+        .build();
+
+    dialog.save(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(@strong sender, @strong dialog => move |result| {
+        if let Ok(file) = result {
+            if let Some(path) = file.peek_path() {
+                debug!("File selected: {:?}", path);
+                gio::spawn_blocking(clone!(@strong sender => move || {
+                    if let Err(err) = archive::export_to_file(&Database::global(), path) {
+                        error!("Failed to export the library: {}", err);
+                        let text = i18n("Failed to export the library");
+                        send!(sender, Action::ErrorNotification(text));
+                    }
+                }));
+            }
+        }}),
+    );
+}
+
+pub(crate) fn on_backup_clicked(window: &gtk::ApplicationWindow, sender: &Sender<Action>) {
+    let filter = FileFilter::new();
+    FileFilter::set_name(&filter, Some(i18n("SQLite database").as_str()));
+    filter.add_mime_type("application/vnd.sqlite3");
+    filter.add_mime_type("application/x-sqlite3");
+
+    let filters = gio::ListStore::new::<gtk::FileFilter>();
+    filters.append(&filter);
+
+    let dialog = gtk::FileDialog::builder()
+        .title(i18n("Back up database to…"))
+        .accept_label(i18n("_Back Up"))
+        .initial_name(format!(
+            "{}.db",
+            // Translators: This is the string of the suggested name for the database backup
+            i18n("gnome-podcasts-backup")
+        ))
+        .filters(&filters)
+        .build();
+
+    dialog.save(
+        Some(window),
+        gio::Cancellable::NONE,
+        clone!(@strong sender, @strong dialog => move |result| {
+        if let Ok(file) = result {
+            if let Some(path) = file.peek_path() {
+                debug!("File selected: {:?}", path);
+                gio::spawn_blocking(clone!(@strong sender => move || {
+                    if let Err(err) = archive::backup_to_file(&Database::global(), path) {
+                        error!("Failed to back up the database: {}", err);
+                        let text = i18n("Failed to back up the database");
+                        send!(sender, Action::ErrorNotification(text));
+                    }
+                }));
+            }
+        }}),
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,6 +37,7 @@ use podcasts_data::EpisodeWidgetModel;
 
 use crate::app::Action;
 use crate::manager;
+use crate::widgets::episode_menu::update_queue_menu;
 
 use std::sync::{Arc, Mutex, TryLockError};
 use std::time::Duration;
@@ -84,6 +85,8 @@ pub struct EpisodeWidgetPriv {
     total_size: TemplateChild<gtk::Label>,
     #[template_child]
     played_checkmark: TemplateChild<gtk::Image>,
+    #[template_child]
+    removed_badge: TemplateChild<gtk::Image>,
 
     // Buttons
     #[template_child]
@@ -92,6 +95,8 @@ pub struct EpisodeWidgetPriv {
     download: TemplateChild<gtk::Button>,
     #[template_child]
     cancel: TemplateChild<gtk::Button>,
+    #[template_child]
+    queue_button: TemplateChild<gtk::MenuButton>,
 }
 
 impl EpisodeWidgetPriv {
@@ -223,11 +228,11 @@ impl EpisodeWidgetPriv {
             // Wire the cancel button
             self
                 .cancel
-                .connect_clicked(clone!(@strong prog, @weak self as this, @strong sender => move |_| {
+                .connect_clicked(clone!(@weak self as this, @strong sender => move |_| {
                     // Cancel the download
-                    if let Ok(mut m) = prog.lock() {
-                        m.cancel();
-                    }
+                    manager::cancel(id)
+                        .map_err(|err| error!("Failed to cancel the download: {}", err))
+                        .ok();
 
                     // Cancel is not instant so we have to wait a bit
                     glib::timeout_add_local(Duration::from_millis(50), clone!(@weak this, @strong sender => @default-return glib::ControlFlow::Break, move || {
@@ -330,7 +335,16 @@ impl EpisodeWidgetPriv {
         self.set_duration(episode.duration());
 
         // Set the total_size label.
-        self.set_size(episode.length())
+        self.set_size(episode.length());
+
+        self.init_queue_button(episode.rowid());
+    }
+
+    fn init_queue_button(&self, rowid: i32) {
+        let menu = gtk::gio::Menu::new();
+        self.queue_button.set_menu_model(Some(&menu));
+        self.queue_button
+            .set_create_popup_func(move |_| update_queue_menu(&menu, rowid));
     }
 
     fn set_title(&self, episode: &EpisodeWidgetModel) {
@@ -343,6 +357,9 @@ impl EpisodeWidgetPriv {
             self.title.remove_css_class("dim-label");
             self.played_checkmark.set_visible(false);
         }
+
+        self.removed_badge
+            .set_visible(episode.removed_upstream().is_some());
     }
 
     // Set the date label of the episode widget.
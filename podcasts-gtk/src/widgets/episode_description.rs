@@ -17,20 +17,26 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use glib::clone;
 use gtk::glib;
+use gtk::prelude::*;
 use gtk::CompositeTemplate;
 
 use glib::subclass::InitializingObject;
-use glib::Sender;
+use glib::{Sender, SignalHandlerId};
+use podcasts_data::downloader;
+use podcasts_data::transcript::{Cue, Transcript};
 use podcasts_data::{Episode, Show};
 
 use crate::app::Action;
 use crate::utils::{self};
+use crate::widgets::player::PlayerWrapper;
 use crate::widgets::EpisodeMenu;
 
 use crate::episode_description_parser;
 use adw::subclass::prelude::*;
 use chrono::prelude::*;
+use std::cell::RefCell;
 use std::sync::Arc;
 
 #[derive(Debug, CompositeTemplate, Default)]
@@ -48,10 +54,24 @@ pub struct EpisodeDescriptionPriv {
     episode_duration: TemplateChild<gtk::Label>,
     #[template_child]
     description: TemplateChild<gtk::Label>,
+    #[template_child]
+    scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+    #[template_child]
+    transcript_box: TemplateChild<gtk::Box>,
+    #[template_child]
+    transcript: TemplateChild<gtk::ListBox>,
+    cues: RefCell<Option<Transcript>>,
+    position_handler: RefCell<Option<(gtk::Adjustment, SignalHandlerId)>>,
 }
 
 impl EpisodeDescriptionPriv {
-    fn init(&self, sender: Sender<Action>, ep: Arc<Episode>, show: Arc<Show>) {
+    fn init(
+        &self,
+        sender: Sender<Action>,
+        ep: Arc<Episode>,
+        show: Arc<Show>,
+        player: PlayerWrapper,
+    ) {
         self.set_description(&ep);
         self.set_duration(&ep);
         self.episode_title.set_text(ep.title());
@@ -61,6 +81,10 @@ impl EpisodeDescriptionPriv {
         let id = ep.rowid();
         let menu = EpisodeMenu::new(&sender, ep, show);
         self.menu_button.set_menu_model(Some(&menu.menu));
+        // The queue can change while the page is open
+        self.menu_button
+            .set_create_popup_func(move |_| menu.update_queue_section());
+        self.load_transcript(id, player, sender.clone());
 
         self.description.connect_activate_link(move |_, url| {
             if let Some(seconds_str) = url.strip_prefix("jump:") {
@@ -114,6 +138,96 @@ impl EpisodeDescriptionPriv {
         self.episode_duration.set_text(&duration_date);
     }
 
+    fn load_transcript(&self, id: i32, player: PlayerWrapper, sender: Sender<Action>) {
+        crate::MAINCONTEXT.spawn_local_with_priority(
+            glib::source::Priority::LOW,
+            clone!(@weak self as this => async move {
+                match crate::RUNTIME.spawn(downloader::cache_transcript(id)).await {
+                    Ok(Ok(Some(transcript))) => this.set_transcript(id, transcript, &player, sender),
+                    Ok(Ok(None)) => (),
+                    Ok(Err(err)) => warn!("Failed to load transcript of episode {}: {}", id, err),
+                    Err(err) => error!("Transcript task failed: {}", err),
+                }
+            }),
+        );
+    }
+
+    fn set_transcript(
+        &self,
+        id: i32,
+        transcript: Transcript,
+        player: &PlayerWrapper,
+        sender: Sender<Action>,
+    ) {
+        if transcript.cues().is_empty() {
+            return;
+        }
+
+        let timed = transcript.is_timed();
+        for cue in transcript.cues() {
+            self.transcript.append(&transcript_row(cue, timed));
+        }
+        self.transcript_box.set_visible(true);
+
+        if !timed {
+            self.transcript.set_selection_mode(gtk::SelectionMode::None);
+            return;
+        }
+
+        let starts: Vec<u64> = transcript.cues().iter().map(Cue::start).collect();
+        self.transcript.connect_row_activated(move |_, row| {
+            let start = usize::try_from(row.index())
+                .ok()
+                .and_then(|i| starts.get(i));
+            if let Some(start) = start {
+                send!(sender, Action::InitEpisodeAt(id, (start / 1000) as i32));
+            }
+        });
+        self.cues.replace(Some(transcript));
+
+        // Follow the player while it is playing this episode.
+        let adjustment = player.position_adjustment();
+        let handler = adjustment.connect_value_changed(
+            clone!(@weak self as this, @strong player => move |adj| {
+                if player.playing_episode() == Some(id) {
+                    this.highlight_cue((adj.value() * 1000.0) as u64);
+                }
+            }),
+        );
+        if player.playing_episode() == Some(id) {
+            self.highlight_cue((adjustment.value() * 1000.0) as u64);
+        }
+        self.position_handler.replace(Some((adjustment, handler)));
+    }
+
+    /// Select the row of the cue at `position` ms and scroll it into view.
+    fn highlight_cue(&self, position: u64) {
+        let index = self.cues.borrow().as_ref().and_then(|t| t.cue_at(position));
+        let row = index.and_then(|i| self.transcript.row_at_index(i as i32));
+        match row {
+            Some(row) if self.transcript.selected_row().as_ref() != Some(&row) => {
+                self.transcript.select_row(Some(&row));
+                self.scroll_to_row(&row);
+            }
+            Some(_) => (),
+            None => self.transcript.unselect_all(),
+        }
+    }
+
+    fn scroll_to_row(&self, row: &gtk::ListBoxRow) {
+        let window = &*self.scrolled_window;
+        let point = row.compute_point(window, &gtk::graphene::Point::new(0.0, 0.0));
+        if let Some(point) = point {
+            let adjustment = window.vadjustment();
+            let y = f64::from(point.y());
+            let page = adjustment.page_size();
+            // Only scroll when the cue leaves the visible area.
+            if y < 0.0 || y + f64::from(row.height()) > page {
+                adjustment.set_value(adjustment.value() + y - page / 3.0);
+            }
+        }
+    }
+
     fn set_cover(&self, show_id: i32) {
         utils::set_image_from_path(&self.cover, show_id, 64)
             .map_err(|err| error!("Failed to set a cover: {}", err))
@@ -137,7 +251,13 @@ impl ObjectSubclass for EpisodeDescriptionPriv {
 }
 
 impl WidgetImpl for EpisodeDescriptionPriv {}
-impl ObjectImpl for EpisodeDescriptionPriv {}
+impl ObjectImpl for EpisodeDescriptionPriv {
+    fn dispose(&self) {
+        if let Some((adjustment, handler)) = self.position_handler.take() {
+            adjustment.disconnect(handler);
+        }
+    }
+}
 impl NavigationPageImpl for EpisodeDescriptionPriv {
     fn shown(&self) {
         self.description.set_selectable(true);
@@ -151,9 +271,64 @@ glib::wrapper! {
 }
 
 impl EpisodeDescription {
-    pub(crate) fn new(ep: Arc<Episode>, show: Arc<Show>, sender: Sender<Action>) -> Self {
+    pub(crate) fn new(
+        ep: Arc<Episode>,
+        show: Arc<Show>,
+        player: PlayerWrapper,
+        sender: Sender<Action>,
+    ) -> Self {
         let widget: Self = glib::Object::new();
-        widget.imp().init(sender, ep, show);
+        widget.imp().init(sender, ep, show, player);
         widget
     }
 }
+
+fn transcript_row(cue: &Cue, timed: bool) -> gtk::ListBoxRow {
+    let content = gtk::Box::builder()
+        .spacing(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+
+    if timed {
+        let time = gtk::Label::builder()
+            .label(format_timestamp(cue.start() / 1000))
+            .valign(gtk::Align::Start)
+            .css_classes(["dim-label", "numeric"])
+            .build();
+        content.append(&time);
+    }
+
+    let text = gtk::Label::builder()
+        .wrap(true)
+        .xalign(0.0)
+        .hexpand(true)
+        .build();
+    match cue.speaker() {
+        Some(speaker) => text.set_markup(&format!(
+            "<b>{}</b> {}",
+            glib::markup_escape_text(speaker),
+            glib::markup_escape_text(cue.text())
+        )),
+        None => text.set_text(cue.text()),
+    }
+    content.append(&text);
+
+    gtk::ListBoxRow::builder()
+        .child(&content)
+        .activatable(timed)
+        .selectable(timed)
+        .build()
+}
+
+fn format_timestamp(seconds: u64) -> String {
+    let minutes = (seconds / 60) % 60;
+    let hours = seconds / 3600;
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds % 60)
+    }
+}
@@ -24,16 +24,20 @@ use gtk::{gio, glib};
 
 use glib::Sender;
 
+use podcasts_data::dbqueries;
 use podcasts_data::Episode;
 use podcasts_data::Show;
 
 use crate::app::Action;
+use crate::i18n::i18n;
 
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub(crate) struct EpisodeMenu {
     pub(crate) menu: gio::MenuModel,
+    queue: gio::Menu,
+    rowid: i32,
     go_to_show: gio::SimpleAction,
     copy_episode_url: gio::SimpleAction,
     group: gio::SimpleActionGroup,
@@ -42,13 +46,17 @@ pub(crate) struct EpisodeMenu {
 impl Default for EpisodeMenu {
     fn default() -> Self {
         let builder = gtk::Builder::from_resource("/org/gnome/Podcasts/gtk/episode_menu.ui");
-        let menu = builder.object("episode_menu").unwrap();
+        let menu: gio::Menu = builder.object("episode_menu").unwrap();
+        let queue = gio::Menu::new();
+        menu.prepend_section(None, &queue);
         let go_to_show = gio::SimpleAction::new("go-to-show", None);
         let copy_episode_url = gio::SimpleAction::new("copy-episode-url", None);
         let group = gio::SimpleActionGroup::new();
 
         EpisodeMenu {
-            menu,
+            menu: menu.upcast(),
+            queue,
+            rowid: 0,
             go_to_show,
             copy_episode_url,
             group,
@@ -58,7 +66,11 @@ impl Default for EpisodeMenu {
 
 impl EpisodeMenu {
     pub fn new(sender: &Sender<Action>, ep: Arc<Episode>, show: Arc<Show>) -> Self {
-        let s = Self::default();
+        let s = EpisodeMenu {
+            rowid: ep.rowid(),
+            ..Self::default()
+        };
+        s.update_queue_section();
         s.init(sender, ep, show);
         s
     }
@@ -75,6 +87,11 @@ impl EpisodeMenu {
         win.insert_action_group("episode", Some(&self.group));
     }
 
+    /// Show the Up Next actions that apply to the episode right now.
+    pub(crate) fn update_queue_section(&self) {
+        update_queue_menu(&self.queue, self.rowid);
+    }
+
     fn connect_go_to_show(&self, sender: &Sender<Action>, show: Arc<Show>) {
         self.go_to_show
             .connect_activate(clone!(@strong sender, @strong show => move |_,_| {
@@ -97,6 +114,38 @@ impl EpisodeMenu {
     }
 }
 
+/// Fill `menu` with the Up Next actions for the episode with `rowid`,
+/// depending on where it currently is in the queue.
+pub(crate) fn update_queue_menu(menu: &gio::Menu, rowid: i32) {
+    let queue = dbqueries::get_play_queue()
+        .map_err(|err| error!("Failed to load the Up Next queue: {}", err))
+        .unwrap_or_default();
+
+    menu.remove_all();
+    let append = |label: String, action: &str| {
+        let item = gio::MenuItem::new(Some(&label), None);
+        item.set_action_and_target_value(Some(action), Some(&rowid.to_variant()));
+        menu.append_item(&item);
+    };
+
+    match queue.iter().position(|id| *id == rowid) {
+        None => {
+            append(i18n("Play Next"), "app.queue-episode-next");
+            append(i18n("Add to Queue"), "app.queue-episode");
+        }
+        Some(index) => {
+            if index > 0 {
+                append(i18n("Play Next"), "app.queue-episode-next");
+                append(i18n("Move Up"), "app.move-queued-episode-up");
+            }
+            if index + 1 < queue.len() {
+                append(i18n("Move Down"), "app.move-queued-episode-down");
+            }
+            append(i18n("Remove from Queue"), "app.dequeue-episode");
+        }
+    }
+}
+
 fn copy_text(text: &str) -> Option<()> {
     let display = gtk::gdk::Display::default()?;
     let clipboard = display.clipboard();
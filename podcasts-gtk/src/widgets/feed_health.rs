@@ -0,0 +1,166 @@
+// feed_health.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::clone;
+use glib::subclass::InitializingObject;
+use glib::Sender;
+use gtk::{glib, CompositeTemplate};
+
+use podcasts_data::{dbqueries, Source};
+
+use crate::app::Action;
+use crate::i18n::{i18n, ni18n_f};
+
+use std::cell::{Cell, OnceCell};
+use std::sync::Arc;
+
+#[derive(Debug, CompositeTemplate, Default)]
+#[template(resource = "/org/gnome/Podcasts/gtk/feed_health.ui")]
+pub struct FeedHealthPagePriv {
+    #[template_child]
+    stack: TemplateChild<gtk::Stack>,
+    #[template_child]
+    failures_group: TemplateChild<adw::PreferencesGroup>,
+    sender: OnceCell<Sender<Action>>,
+    /// How many failing feeds are still listed.
+    remaining: Cell<usize>,
+}
+
+impl FeedHealthPagePriv {
+    fn init(&self, sender: Sender<Action>) {
+        self.sender.set(sender).unwrap();
+        let sources = match dbqueries::get_failing_sources() {
+            Ok(sources) => sources,
+            Err(err) => {
+                error!("Failed to load the failing feeds: {}", err);
+                Vec::new()
+            }
+        };
+        if sources.is_empty() {
+            self.stack.set_visible_child_name("healthy");
+            return;
+        }
+
+        self.remaining.set(sources.len());
+        for source in sources {
+            self.failures_group.add(&self.failure_row(source));
+        }
+        self.stack.set_visible_child_name("failures");
+    }
+
+    fn failure_row(&self, source: Source) -> adw::ActionRow {
+        // Feeds that never got indexed have no show to name them by
+        let show = dbqueries::get_podcast_from_source_id(source.id()).ok();
+        let title = show
+            .as_ref()
+            .map_or_else(|| source.uri().to_string(), |show| show.title().to_string());
+
+        let row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&title).as_str())
+            .subtitle(glib::markup_escape_text(&describe(&source)).as_str())
+            .subtitle_lines(3)
+            .tooltip_text(source.uri())
+            .build();
+        row.add_prefix(&gtk::Image::from_icon_name("dialog-warning-symbolic"));
+
+        let button = gtk::Button::builder()
+            .label(i18n("Unsubscribe"))
+            .valign(gtk::Align::Center)
+            .build();
+        if source.gone().is_some() {
+            button.add_css_class("destructive-action");
+        }
+        let sender = self.sender.get().unwrap().clone();
+        let obj = self.obj();
+        let id = source.id();
+        button.connect_clicked(clone!(@weak obj, @weak row => move |_| {
+            match show {
+                Some(ref show) => send!(sender, Action::RemoveShow(Arc::new(show.clone()))),
+                None => {
+                    if let Err(err) = dbqueries::remove_source(id) {
+                        error!("Failed to remove source {}: {}", id, err);
+                        return;
+                    }
+                }
+            }
+            obj.imp().remove_row(&row);
+        }));
+        row.add_suffix(&button);
+        row
+    }
+
+    fn remove_row(&self, row: &adw::ActionRow) {
+        self.failures_group.remove(row);
+        self.remaining.set(self.remaining.get().saturating_sub(1));
+        if self.remaining.get() == 0 {
+            self.stack.set_visible_child_name("healthy");
+        }
+    }
+}
+
+fn describe(source: &Source) -> String {
+    if source.gone().is_some() {
+        return i18n("The feed was removed by its publisher");
+    }
+    let failures = source.failures() as u32;
+    ni18n_f(
+        "Failed to refresh {} time in a row: {}",
+        "Failed to refresh {} times in a row: {}",
+        failures,
+        &[
+            &failures.to_string(),
+            source.last_error().unwrap_or_default(),
+        ],
+    )
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FeedHealthPagePriv {
+    const NAME: &'static str = "PdFeedHealthPage";
+    type Type = FeedHealthPage;
+    type ParentType = adw::NavigationPage;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl WidgetImpl for FeedHealthPagePriv {}
+impl ObjectImpl for FeedHealthPagePriv {}
+impl NavigationPageImpl for FeedHealthPagePriv {}
+
+glib::wrapper! {
+    pub struct FeedHealthPage(ObjectSubclass<FeedHealthPagePriv>)
+        @extends adw::NavigationPage, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl FeedHealthPage {
+    pub(crate) fn new(sender: Sender<Action>) -> Self {
+        let widget: Self = glib::Object::new();
+        widget.imp().init(sender);
+        widget
+    }
+}
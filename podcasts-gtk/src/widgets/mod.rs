@@ -24,11 +24,14 @@ mod empty_view;
 mod episode;
 mod episode_description;
 pub(crate) mod episode_menu;
+mod feed_health;
 mod home_view;
 pub(crate) mod player;
 mod read_more_label;
+mod search_page;
 mod show;
 pub(crate) mod show_menu;
+mod show_settings;
 mod shows_view;
 
 pub(crate) use self::aboutdialog::about_dialog;
@@ -38,10 +41,13 @@ pub(crate) use self::empty_view::EmptyView;
 pub(crate) use self::episode::EpisodeWidget;
 pub(crate) use self::episode_description::EpisodeDescription;
 pub(crate) use self::episode_menu::EpisodeMenu;
+pub(crate) use self::feed_health::FeedHealthPage;
 pub(crate) use self::home_view::HomeView;
 pub(crate) use self::read_more_label::ReadMoreLabel;
+pub(crate) use self::search_page::SearchPage;
 pub(crate) use self::show::ShowWidget;
 pub(crate) use self::show_menu::ShowMenu;
+pub(crate) use self::show_settings::ShowSettingsWindow;
 pub(crate) use self::shows_view::ShowsView;
 
 #[cfg(test)]
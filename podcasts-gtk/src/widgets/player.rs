@@ -31,12 +31,14 @@ use chrono::{prelude::*, NaiveTime};
 use fragile::Fragile;
 use glib::Sender;
 use once_cell::sync::Lazy;
-use url::Url;
 
-use podcasts_data::{dbqueries, downloader, EpisodeWidgetModel, ShowCoverModel, USER_AGENT};
+use podcasts_data::{
+    dbqueries, downloader, Chapter, EpisodeWidgetModel, ShowCoverModel, USER_AGENT,
+};
 
 use crate::app::Action;
-use crate::config::APP_ID;
+use crate::mpris::{self, Mpris};
+use crate::sleep_timer::{SleepMode, SleepTick, SleepTimer};
 use crate::utils::set_image_from_path;
 
 use std::cell::{RefCell, RefMut};
@@ -45,10 +47,11 @@ use std::ops::Deref;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::Mutex;
+use std::time::Instant;
 
 use crate::i18n::i18n;
 
-use mpris_server::{Metadata, PlaybackStatus, Player};
+use mpris_server::PlaybackStatus;
 
 #[derive(Debug, Clone, Copy)]
 enum SeekDirection {
@@ -74,11 +77,16 @@ struct PlayerInfo {
     show_small: gtk::Label,
     episode_small: gtk::Label,
     cover_small: gtk::Image,
-    mpris: Rc<Player>,
+    mpris: Rc<Mpris>,
     restore_position: i32,
     finished_restore: bool,
+    /// Seconds to skip when starting an episode from the top.
+    skip_intro: u32,
+    /// Seconds before the end at which the episode counts as finished.
+    skip_outro: u32,
     ep: Option<EpisodeWidgetModel>,
     episode_id: RefCell<Option<i32>>,
+    chapters: Vec<Chapter>,
 }
 
 impl PlayerInfo {
@@ -101,22 +109,7 @@ impl PlayerInfo {
         self.set_show_title(podcast);
         self.set_episode_title(episode);
 
-        let mut metadata = Metadata::new();
-        metadata.set_artist(Some(vec![podcast.title().to_string()]));
-        metadata.set_title(Some(episode.title().to_string()));
-
-        // Set the cover if it is already cached.
-        if let Some(path) = downloader::check_for_cached_cover(podcast)
-            .as_ref()
-            .and_then(|p| p.to_str())
-        {
-            metadata.set_art_url(Url::from_file_path(path).ok());
-        } else {
-            // fallback: set the cover to the http url if it isn't cached, yet.
-            // TODO we could trigger an async download of the cover here
-            // and update the metadata when it's done.
-            metadata.set_art_url(podcast.image_uri());
-        }
+        let metadata = mpris::episode_metadata(episode, podcast);
 
         crate::MAINCONTEXT.spawn_local_with_priority(
             glib::source::Priority::LOW,
@@ -252,6 +245,15 @@ impl PlayerRate {
         PlayerRate { action, btn }
     }
 
+    /// The rate currently picked in the menu.
+    fn selected(&self) -> f64 {
+        self.action
+            .state()
+            .and_then(|v| v.get::<String>())
+            .and_then(|rate| rate.parse().ok())
+            .unwrap_or(1.0)
+    }
+
     fn connect_signals(&self, widget: &Rc<RefCell<PlayerWidget>>) {
         let group = gio::SimpleActionGroup::new();
         self.action
@@ -278,6 +280,60 @@ impl PlayerRate {
     }
 }
 
+#[derive(Debug, Clone)]
+struct PlayerSleep {
+    action: gio::SimpleAction,
+    btn: gtk::MenuButton,
+}
+
+impl PlayerSleep {
+    fn new() -> Self {
+        let builder = gtk::Builder::from_resource("/org/gnome/Podcasts/gtk/player_sleep.ui");
+
+        let variant_type = glib::VariantTy::new("s").expect("Could not parse variant type");
+        let target = SleepMode::Off.target();
+        let action = gio::SimpleAction::new_stateful("set", Some(variant_type), &target.into());
+        let btn: gtk::MenuButton = builder.object("sleep_button").unwrap();
+
+        PlayerSleep { action, btn }
+    }
+
+    /// Show how long is left before the timer goes off.
+    fn update(&self, mode: SleepMode, left: Option<std::time::Duration>) {
+        let label = match mode {
+            SleepMode::Off => {
+                self.btn.set_icon_name("preferences-system-time-symbolic");
+                return;
+            }
+            SleepMode::EndOfEpisode => i18n("End of Episode"),
+            SleepMode::Minutes(_) => {
+                format_duration(left.unwrap_or_default().as_secs().try_into().unwrap_or(0))
+            }
+        };
+        // Setting the label rebuilds the button, so only do it when it changes
+        if self.btn.label().as_deref() != Some(label.as_str()) {
+            self.btn.set_label(&label);
+        }
+    }
+
+    fn connect_signals(&self, widget: &Rc<RefCell<PlayerWidget>>) {
+        let group = gio::SimpleActionGroup::new();
+        self.action
+            .connect_activate(clone!(@weak widget => move |_, target| {
+                let target = target.and_then(|v| v.get::<String>()).unwrap_or_default();
+                widget
+                    .borrow_mut()
+                    .set_sleep_timer(SleepMode::from_target(&target));
+            }));
+        group.add_action(&self.action);
+        widget
+            .borrow()
+            .dialog
+            .dialog
+            .insert_action_group("sleep", Some(&group));
+    }
+}
+
 #[derive(Debug, Clone)]
 struct PlayerControls {
     play: gtk::Button,
@@ -303,8 +359,13 @@ struct PlayerDialog {
     forward: gtk::Button,
     rewind: gtk::Button,
     rate: PlayerRate,
+    sleep: PlayerSleep,
     show: gtk::Label,
     episode: gtk::Label,
+    chapters: gtk::Box,
+    chapter: gtk::Label,
+    previous_chapter: gtk::Button,
+    next_chapter: gtk::Button,
 }
 
 impl PlayerDialog {
@@ -324,8 +385,14 @@ impl PlayerDialog {
         let bottom: gtk::Box = builder.object("bottom").unwrap();
         let show = builder.object("show_label").unwrap();
         let episode = builder.object("episode_label").unwrap();
+        let chapters = builder.object("chapters").unwrap();
+        let chapter = builder.object("chapter_label").unwrap();
+        let previous_chapter = builder.object("previous_chapter").unwrap();
+        let next_chapter = builder.object("next_chapter").unwrap();
 
         bottom.prepend(&rate.btn);
+        let sleep = PlayerSleep::new();
+        bottom.append(&sleep.btn);
 
         PlayerDialog {
             dialog,
@@ -339,8 +406,13 @@ impl PlayerDialog {
             forward,
             rewind,
             rate,
+            sleep,
             show,
             episode,
+            chapters,
+            chapter,
+            previous_chapter,
+            next_chapter,
         }
     }
 
@@ -368,6 +440,9 @@ pub(crate) struct PlayerWidget {
     timer: PlayerTimes,
     info: PlayerInfo,
     rate: PlayerRate,
+    sleep_timer: SleepTimer,
+    /// The volume from before the sleep timer started fading out.
+    sleep_volume: Option<f64>,
     sender: Option<Sender<Action>>,
 }
 
@@ -380,26 +455,7 @@ impl Default for PlayerWidget {
         // window. Make sure it doesn't do that.
         player.set_video_track_enabled(false);
 
-        let mpris = Rc::new(
-            Player::builder(APP_ID)
-                .identity(i18n("Podcasts"))
-                .desktop_entry(APP_ID)
-                .can_raise(true)
-                .can_pause(false)
-                .can_play(false)
-                .can_seek(false)
-                .can_set_fullscreen(false)
-                .can_go_next(false)
-                .can_go_previous(false)
-                .build(),
-        );
-
-        let mpris_task = mpris.init_and_run();
-        crate::MAINCONTEXT.spawn_local_with_priority(glib::source::Priority::LOW, async move {
-            if let Err(err) = mpris_task.await {
-                error!("Failed to run MPRIS server: {err:?}");
-            }
-        });
+        let mpris = Rc::new(Mpris::new());
 
         let mut config = player.config();
         config.set_user_agent(USER_AGENT);
@@ -460,7 +516,10 @@ impl Default for PlayerWidget {
             cover_small,
             restore_position: 0,
             finished_restore: false,
+            skip_intro: 0,
+            skip_outro: 0,
             episode_id: RefCell::new(None),
+            chapters: Vec::new(),
         };
         info.create_bindings();
 
@@ -489,6 +548,8 @@ impl Default for PlayerWidget {
             timer,
             info,
             rate,
+            sleep_timer: SleepTimer::default(),
+            sleep_volume: None,
             sender: None,
         }
     }
@@ -508,13 +569,31 @@ impl PlayerWidget {
     pub(crate) fn initialize_episode(&mut self, rowid: i32, second: Option<i32>) -> Result<()> {
         let ep = dbqueries::get_episode_widget_from_rowid(rowid)?;
         let pd = dbqueries::get_podcast_cover_from_id(ep.show_id())?;
+        let settings = dbqueries::get_show_settings(ep.show_id())?;
 
         self.dialog.initialize_episode(&ep, &pd);
 
+        if *self.info.episode_id.borrow() != Some(rowid) {
+            self.set_chapters(Vec::new());
+            self.fetch_chapters(rowid);
+        }
+
         self.info.restore_position = second.unwrap_or(ep.play_position());
         self.info.finished_restore = false;
+        // Jumping to a specific spot shouldn't skip past it
+        self.info.skip_intro = match second {
+            Some(_) => 0,
+            None => settings.skip_intro(),
+        };
+        self.info.skip_outro = settings.skip_outro();
         self.info.init(&ep, &pd);
 
+        // The episode is no longer up next once it's playing
+        if let Err(err) = dbqueries::dequeue_episode(rowid) {
+            error!("Failed to remove episode {} from the queue: {}", rowid, err);
+        }
+        self.refresh_queue();
+
         // Currently that will always be the case since the play button is
         // only shown if the file is downloaded
         if let Some(ref path) = ep.local_uri() {
@@ -537,6 +616,12 @@ impl PlayerWidget {
                 // play the file
                 self.play();
 
+                // Shows can override the rate picked in the menu
+                let rate = settings
+                    .playback_rate()
+                    .unwrap_or_else(|| self.rate.selected());
+                self.on_rate_changed(rate);
+
                 return Ok(());
             }
             // TODO: log an error
@@ -547,6 +632,104 @@ impl PlayerWidget {
         Ok(())
     }
 
+    /// Fetch the chapters of the episode in the background.
+    ///
+    /// Once they are stored `Action::InitEpisodeChapters` is sent so they can
+    /// be loaded into the player.
+    fn fetch_chapters(&self, rowid: i32) {
+        let sender = match self.sender {
+            Some(ref sender) => sender.clone(),
+            None => return,
+        };
+
+        crate::RUNTIME.spawn(async move {
+            match downloader::cache_chapters(rowid).await {
+                Ok(chapters) if !chapters.is_empty() => {
+                    send!(sender, Action::InitEpisodeChapters(rowid));
+                }
+                Ok(_) => (),
+                Err(err) => warn!("Failed to fetch the chapters of episode {}: {}", rowid, err),
+            }
+        });
+    }
+
+    /// Load the stored chapters of the episode, if it is still the one playing.
+    pub(crate) fn initialize_chapters(&mut self, rowid: i32) -> Result<()> {
+        if *self.info.episode_id.borrow() != Some(rowid) {
+            return Ok(());
+        }
+
+        let chapters = dbqueries::get_episode_chapters(rowid)?;
+        self.set_chapters(chapters);
+        Ok(())
+    }
+
+    fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        for slider in &[&self.timer.slider, &self.dialog.slider] {
+            slider.clear_marks();
+            // A mark at the very start of the episode is just noise.
+            chapters
+                .iter()
+                .filter(|c| c.start_time() > 0)
+                .for_each(|c| {
+                    slider.add_mark(f64::from(c.start_time()), gtk::PositionType::Bottom, None)
+                });
+        }
+
+        self.dialog.chapters.set_visible(!chapters.is_empty());
+        self.info.chapters = chapters;
+
+        let seconds = self.player.position().map(|p| p.seconds()).unwrap_or(0);
+        self.update_chapter_label(seconds);
+    }
+
+    /// Returns the index of the chapter that plays at `seconds`.
+    fn current_chapter(&self, seconds: u64) -> Option<usize> {
+        self.info
+            .chapters
+            .iter()
+            .rposition(|c| c.start_time() as u64 <= seconds)
+    }
+
+    fn update_chapter_label(&self, seconds: u64) {
+        let title = self
+            .current_chapter(seconds)
+            .and_then(|i| self.info.chapters[i].title())
+            .unwrap_or_default();
+
+        if self.dialog.chapter.text().as_str() != title {
+            self.dialog.chapter.set_text(title);
+            self.dialog.chapter.set_tooltip_text(Some(title));
+        }
+    }
+
+    /// Seek to the start of the next or the previous chapter.
+    ///
+    /// Seeking backwards more than a few seconds into a chapter restarts it,
+    /// like the previous track button of a music player.
+    fn seek_chapter(&self, direction: SeekDirection) -> Option<()> {
+        let seconds = self.player.position()?.seconds();
+        let current = self.current_chapter(seconds);
+
+        let target = match (direction, current) {
+            (SeekDirection::Forward, Some(i)) => self.info.chapters.get(i + 1)?,
+            (SeekDirection::Forward, None) => self.info.chapters.first()?,
+            (SeekDirection::Backwards, Some(i)) => {
+                let start = self.info.chapters[i].start_time();
+                if seconds >= u64::try_from(start).ok()? + 3 || i == 0 {
+                    &self.info.chapters[i]
+                } else {
+                    &self.info.chapters[i - 1]
+                }
+            }
+            (SeekDirection::Backwards, None) => return None,
+        };
+
+        let start: u64 = target.start_time().try_into().ok()?;
+        self.player.seek(ClockTime::from_seconds(start));
+        Some(())
+    }
+
     fn connect_update_slider(
         slider: &gtk::Scale,
         player: WeakRef<gst_play::Play>,
@@ -591,8 +774,13 @@ impl PlayerWidget {
 
     /// Seek to the `play_position` stored in the episode.
     /// Returns Some(()) if the restore was successful and None otherwise.
+    /// Seek to where the episode was left off, or past the intro of the
+    /// show when starting from the top.
     fn restore_play_position(&self) -> Option<()> {
-        let pos = self.info.restore_position;
+        let pos = match self.info.restore_position {
+            0 => i32::try_from(self.info.skip_intro).ok()?,
+            pos => pos,
+        };
         let s: u64 = pos.try_into().ok()?;
         if pos != 0 {
             self.player.seek(ClockTime::from_seconds(s));
@@ -602,6 +790,146 @@ impl PlayerWidget {
         }
     }
 
+    /// Finish the episode early once it reaches the outro of the show.
+    fn check_outro(&mut self, position: Position, sender: &Sender<Action>) {
+        let outro = u64::from(self.info.skip_outro);
+        if outro == 0 || !self.info.finished_restore || self.info.ep.is_none() {
+            return;
+        }
+
+        let duration = match self.player.duration() {
+            Some(duration) => duration.seconds(),
+            None => return,
+        };
+        if duration > outro && position.seconds() + outro >= duration {
+            info!("Skipping the last {} seconds of the episode", outro);
+            self.finish_episode(sender);
+        }
+    }
+
+    /// Publish the Up Next queue over MPRIS.
+    pub(crate) fn refresh_queue(&self) {
+        let queue = dbqueries::get_play_queue()
+            .map_err(|err| error!("Failed to load the Up Next queue: {}", err))
+            .unwrap_or_default();
+        let current = *self.info.episode_id.borrow();
+
+        crate::MAINCONTEXT.spawn_local_with_priority(
+            glib::source::Priority::LOW,
+            clone!(@weak self.info.mpris as mpris => async move {
+                if let Err(err) = mpris.set_tracks(current, &queue).await {
+                    warn!("Failed to set MPRIS tracks: {err:?}");
+                }
+                if let Err(err) = mpris.set_can_go_next(!queue.is_empty()).await {
+                    warn!("Failed to set MPRIS next capability: {err:?}");
+                }
+            }),
+        );
+    }
+
+    /// Start the first downloaded episode of the Up Next queue.
+    ///
+    /// Returns `false` if there was nothing to play.
+    fn play_next(&self, sender: &Sender<Action>) -> bool {
+        let queue = dbqueries::get_play_queue()
+            .map_err(|err| error!("Failed to load the Up Next queue: {}", err))
+            .unwrap_or_default();
+        let downloaded = |id: &i32| {
+            dbqueries::get_episode_local_uri_from_id(*id)
+                .ok()
+                .flatten()
+                .is_some_and(|path| Path::new(&path).exists())
+        };
+
+        match queue.into_iter().find(downloaded) {
+            Some(rowid) => {
+                send!(sender, Action::InitEpisode(rowid));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start, change or cancel the sleep timer.
+    fn set_sleep_timer(&mut self, mode: SleepMode) {
+        self.restore_volume();
+        self.sleep_timer.set(mode);
+
+        let position = self.player.position().map_or(0, |p| p.seconds());
+        let left = self.sleep_timer.left(self.until_end(position));
+        self.dialog.sleep.action.set_state(&mode.target().into());
+        self.dialog.sleep.update(mode, left);
+    }
+
+    /// Count down the sleep timer, fading out and pausing when it's over.
+    fn update_sleep_timer(&mut self, position: Position) {
+        let until_end = self.until_end(position.seconds());
+        match self.sleep_timer.tick(Instant::now(), until_end) {
+            SleepTick::Idle => self.restore_volume(),
+            SleepTick::Fade(factor) => {
+                let volume = *self
+                    .sleep_volume
+                    .get_or_insert_with(|| self.player.volume());
+                self.player.set_volume(volume * factor);
+            }
+            SleepTick::Sleep => {
+                info!("The sleep timer is over");
+                self.pause();
+                self.set_sleep_timer(SleepMode::Off);
+                return;
+            }
+        }
+
+        let left = self.sleep_timer.left(until_end);
+        self.dialog.sleep.update(self.sleep_timer.mode(), left);
+    }
+
+    /// Pause from a remote control like headphones, which also cancels
+    /// the sleep timer.
+    fn remote_pause(&mut self) {
+        self.set_sleep_timer(SleepMode::Off);
+        self.pause();
+    }
+
+    fn restore_volume(&mut self) {
+        if let Some(volume) = self.sleep_volume.take() {
+            self.player.set_volume(volume);
+        }
+    }
+
+    /// Playing time left in the episode, up to the outro when it's skipped.
+    fn until_end(&self, position: u64) -> Option<std::time::Duration> {
+        let duration = self.player.duration()?.seconds();
+        let left = duration.saturating_sub(position + u64::from(self.info.skip_outro));
+        let rate = self.player.rate();
+        (rate > 0.0).then(|| std::time::Duration::from_secs_f64(left as f64 / rate))
+    }
+
+    /// Mark the current episode as played and stop the player.
+    ///
+    /// Playback continues with the next episode of the queue, if any,
+    /// unless the sleep timer was waiting for the end of the episode.
+    fn finish_episode(&mut self, sender: &Sender<Action>) {
+        if let Some(ep) = self.info.ep.as_mut() {
+            let res = ep.set_play_position(0).and_then(|_| ep.set_played_now());
+            if let Err(err) = res {
+                error!("Failed to mark {} as played: {}", ep.title(), err);
+            }
+            send!(sender, Action::RefreshEpisodesViewBGR);
+            send!(sender, Action::RefreshWidgetIfSame(ep.show_id()));
+        }
+
+        let sleep = self.sleep_timer.mode() == SleepMode::EndOfEpisode;
+        if sleep {
+            self.set_sleep_timer(SleepMode::Off);
+        }
+
+        self.stop();
+        if !sleep {
+            self.play_next(sender);
+        }
+    }
+
     pub fn set_small(&self, small: bool) {
         if small {
             self.stack.set_visible_child(&self.small);
@@ -693,6 +1021,7 @@ impl PlayerExt for PlayerWidget {
         self.info.ep = None;
         self.info.restore_position = 0;
         self.player.stop();
+        self.update_chapter_label(0);
         crate::MAINCONTEXT.spawn_local_with_priority(
             glib::source::Priority::LOW,
             clone!(@weak self.info.mpris as mpris => async move {
@@ -785,6 +1114,22 @@ impl PlayerWrapper {
     pub(crate) fn borrow_mut(&self) -> RefMut<'_, PlayerWidget> {
         self.0.borrow_mut()
     }
+    /// The rowid of the episode currently loaded in the player.
+    ///
+    /// Returns `None` while the player is mutably borrowed, since this is
+    /// queried from signal handlers that can fire mid-update.
+    pub(crate) fn playing_episode(&self) -> Option<i32> {
+        self.0
+            .try_borrow()
+            .ok()
+            .and_then(|widget| *widget.info.episode_id.borrow())
+    }
+
+    /// The `gtk::Adjustment` tracking the playback position in seconds.
+    pub(crate) fn position_adjustment(&self) -> gtk::Adjustment {
+        self.borrow().timer.slider.adjustment()
+    }
+
     pub(crate) fn new(sender: &Sender<Action>) -> Self {
         let w = PlayerWrapper::default();
         w.init(sender);
@@ -795,6 +1140,7 @@ impl PlayerWrapper {
         self.borrow_mut().sender = Some(sender.clone());
         self.connect_control_buttons();
         self.connect_rate_buttons();
+        self.borrow().dialog.sleep.connect_signals(self.deref());
         self.connect_mpris_buttons(sender);
         self.connect_gst_signals(sender);
         self.connect_dialog();
@@ -925,6 +1271,25 @@ impl PlayerWrapper {
             .connect_clicked(clone!(@weak this => move |_| {
                 this.borrow().fast_forward();
             }));
+
+        // Connect the chapter buttons to the gst Player.
+        widget
+            .dialog
+            .previous_chapter
+            .connect_clicked(clone!(@weak this => move |_| {
+                if this.borrow().seek_chapter(SeekDirection::Backwards).is_none() {
+                    warn!("Failed to seek to the previous chapter");
+                }
+            }));
+
+        widget
+            .dialog
+            .next_chapter
+            .connect_clicked(clone!(@weak this => move |_| {
+                if this.borrow().seek_chapter(SeekDirection::Forward).is_none() {
+                    warn!("Failed to seek to the next chapter");
+                }
+            }));
     }
 
     fn connect_gst_signals(&self, sender: &Sender<Action>) {
@@ -962,38 +1327,34 @@ impl PlayerWrapper {
         }));
 
         // Update the position label and the slider
-        signal_adapter.connect_position_updated(clone!(@strong weak => move |_, clock| {
-            if let Some(player_widget) = weak.get().upgrade() {
-                // write to db
-                if let Some(c) = clock {
-                    let pos = Position(c);
-                    let finished_restore = player_widget.borrow().info.finished_restore;
-                    player_widget.borrow_mut().info.ep.as_mut().map(|ep| {
-                        if finished_restore {
-                            ep.set_play_position_if_divergent(pos.seconds() as i32)
-                        } else {
-                            Ok(())
-                        }
-                    });
-                    player_widget.borrow().timer.on_position_updated(pos)
+        signal_adapter.connect_position_updated(
+            clone!(@strong sender, @strong weak => move |_, clock| {
+                if let Some(player_widget) = weak.get().upgrade() {
+                    // write to db
+                    if let Some(c) = clock {
+                        let pos = Position(c);
+                        let finished_restore = player_widget.borrow().info.finished_restore;
+                        player_widget.borrow_mut().info.ep.as_mut().map(|ep| {
+                            if finished_restore {
+                                ep.set_play_position_if_divergent(pos.seconds() as i32)
+                            } else {
+                                Ok(())
+                            }
+                        });
+                        player_widget.borrow().timer.on_position_updated(pos);
+                        player_widget.borrow().update_chapter_label(pos.seconds());
+                        player_widget.borrow_mut().update_sleep_timer(pos);
+                        player_widget.borrow_mut().check_outro(pos, &sender);
+                    }
                 }
-            }
-        }));
+            }),
+        );
 
         // Reset the slider to 0 and show a play button
         signal_adapter.connect_end_of_stream(clone!(@strong sender, @strong weak => move |_| {
             if let Some(player_widget) = weak.get().upgrade() {
                 // write postion to db
-                player_widget.borrow_mut().info.ep.as_mut().map(|ep| {
-                    ep.set_play_position(0)?;
-                    ep.set_played_now()?;
-                    send!(sender, Action::RefreshEpisodesViewBGR);
-                    send!(sender, Action::RefreshWidgetIfSame(ep.show_id()));
-                    let ok : Result<(), podcasts_data::errors::DataError> = Ok(());
-                    ok
-                });
-
-                player_widget.borrow_mut().stop()
+                player_widget.borrow_mut().finish_episode(&sender);
             }
         }));
     }
@@ -1013,11 +1374,11 @@ impl PlayerWrapper {
         widget
             .info
             .mpris
-            .connect_play_pause(clone!(@strong self as player => move |mpris| {
-                match mpris.playback_status() {
+            .connect_play_pause(clone!(@strong self as player => move |status| {
+                match status {
                     PlaybackStatus::Paused => player.borrow().play(),
                     PlaybackStatus::Stopped => player.borrow().play(),
-                    _ => player.borrow_mut().pause(),
+                    _ => player.borrow_mut().remote_pause(),
                 };
             }));
         widget
@@ -1031,11 +1392,11 @@ impl PlayerWrapper {
             .info
             .mpris
             .connect_pause(clone!(@strong self as player => move |_| {
-                player.borrow_mut().pause();
+                player.borrow_mut().remote_pause();
             }));
 
         widget.info.mpris.connect_seek(
-            clone!(@strong self as player => move |_, offset: mpris_server::Time| {
+            clone!(@strong self as player => move |offset: mpris_server::Time| {
                 let direction = if offset.is_positive() {
                     SeekDirection::Forward
                 } else {
@@ -1054,5 +1415,21 @@ impl PlayerWrapper {
             .connect_raise(clone!(@strong sender => move |_| {
                 send!(sender, Action::RaiseWindow);
             }));
+
+        widget
+            .info
+            .mpris
+            .connect_next(clone!(@strong self as player, @strong sender => move |_| {
+                if !player.borrow().play_next(&sender) {
+                    info!("There is nothing up next");
+                }
+            }));
+
+        widget
+            .info
+            .mpris
+            .connect_go_to(clone!(@strong sender => move |rowid| {
+                send!(sender, Action::InitEpisode(rowid));
+            }));
     }
 }
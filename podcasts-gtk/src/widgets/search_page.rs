@@ -0,0 +1,196 @@
+// search_page.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::clone;
+use glib::subclass::InitializingObject;
+use glib::Sender;
+use gtk::{gio, glib, CompositeTemplate};
+
+use podcasts_data::{dbqueries, EpisodeWidgetModel, SearchHit, Show};
+
+use crate::app::Action;
+use crate::utils;
+
+use std::cell::{Cell, OnceCell};
+use std::sync::Arc;
+
+/// How many shows and how many episodes to list.
+const MAX_RESULTS: u32 = 50;
+
+#[derive(Debug, CompositeTemplate, Default)]
+#[template(resource = "/org/gnome/Podcasts/gtk/search_page.ui")]
+pub struct SearchPagePriv {
+    #[template_child]
+    search_entry: TemplateChild<gtk::SearchEntry>,
+    #[template_child]
+    stack: TemplateChild<gtk::Stack>,
+    #[template_child]
+    shows_label: TemplateChild<gtk::Label>,
+    #[template_child]
+    shows_list: TemplateChild<gtk::ListBox>,
+    #[template_child]
+    episodes_label: TemplateChild<gtk::Label>,
+    #[template_child]
+    episodes_list: TemplateChild<gtk::ListBox>,
+    sender: OnceCell<Sender<Action>>,
+    /// Bumped on every search, so that stale results can be dropped.
+    generation: Cell<u32>,
+}
+
+impl SearchPagePriv {
+    fn init(&self, sender: Sender<Action>) {
+        self.sender.set(sender).unwrap();
+        let obj = self.obj();
+        self.search_entry
+            .connect_search_changed(clone!(@weak obj => move |entry| {
+                obj.imp().search(entry.text().to_string());
+            }));
+    }
+
+    fn search(&self, text: String) {
+        let generation = self.generation.get().wrapping_add(1);
+        self.generation.set(generation);
+        if text.trim().is_empty() {
+            self.stack.set_visible_child_name("initial");
+            return;
+        }
+
+        let obj = self.obj();
+        crate::MAINCONTEXT.spawn_local(clone!(@weak obj => async move {
+            let results =
+                gio::spawn_blocking(move || dbqueries::search(&text, MAX_RESULTS)).await;
+            let this = obj.imp();
+            if this.generation.get() != generation {
+                return;
+            }
+            match results {
+                Ok(Ok(results)) => this.show_results(results.shows(), results.episodes()),
+                Ok(Err(err)) => error!("Search failed: {}", err),
+                Err(_) => error!("Search thread panicked"),
+            }
+        }));
+    }
+
+    fn show_results(&self, shows: &[SearchHit<Show>], episodes: &[SearchHit<EpisodeWidgetModel>]) {
+        clear_list(&self.shows_list);
+        clear_list(&self.episodes_list);
+        if shows.is_empty() && episodes.is_empty() {
+            self.stack.set_visible_child_name("empty");
+            return;
+        }
+
+        for hit in shows {
+            self.shows_list.append(&self.show_row(hit));
+        }
+        for hit in episodes {
+            self.episodes_list.append(&episode_row(hit));
+        }
+        self.shows_label.set_visible(!shows.is_empty());
+        self.shows_list.set_visible(!shows.is_empty());
+        self.episodes_label.set_visible(!episodes.is_empty());
+        self.episodes_list.set_visible(!episodes.is_empty());
+        self.stack.set_visible_child_name("results");
+    }
+
+    fn show_row(&self, hit: &SearchHit<Show>) -> adw::ActionRow {
+        let show = Arc::new(hit.item().clone());
+        let row = result_row(show.title(), hit.snippet());
+
+        let cover = gtk::Image::new();
+        cover.set_pixel_size(34);
+        utils::set_image_from_path(&cover, show.id(), 34)
+            .map_err(|err| error!("Failed to set a cover: {}", err))
+            .ok();
+        row.add_prefix(&cover);
+
+        // The show is shown in the main page, underneath this one
+        let sender = self.sender.get().unwrap().clone();
+        row.connect_activated(move |row| {
+            row.activate_action("navigation.pop", None)
+                .map_err(|err| error!("Failed to leave the search: {}", err))
+                .ok();
+            send!(sender, Action::GoToShow(show.clone()));
+        });
+        row
+    }
+}
+
+fn clear_list(list: &gtk::ListBox) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+}
+
+fn result_row(title: &str, snippet: &str) -> adw::ActionRow {
+    let row = adw::ActionRow::builder()
+        .title(glib::markup_escape_text(title).as_str())
+        .subtitle(snippet)
+        .subtitle_lines(2)
+        .activatable(true)
+        .build();
+    row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+    row
+}
+
+fn episode_row(hit: &SearchHit<EpisodeWidgetModel>) -> adw::ActionRow {
+    let episode = hit.item();
+    let row = result_row(episode.title(), hit.snippet());
+    row.set_action_name(Some("app.go-to-episode"));
+    row.set_action_target_value(Some(&episode.rowid().to_variant()));
+    row
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SearchPagePriv {
+    const NAME: &'static str = "PdSearchPage";
+    type Type = SearchPage;
+    type ParentType = adw::NavigationPage;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl WidgetImpl for SearchPagePriv {}
+impl ObjectImpl for SearchPagePriv {}
+impl NavigationPageImpl for SearchPagePriv {
+    fn shown(&self) {
+        self.search_entry.grab_focus();
+    }
+}
+
+glib::wrapper! {
+    pub struct SearchPage(ObjectSubclass<SearchPagePriv>)
+        @extends adw::NavigationPage, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl SearchPage {
+    pub(crate) fn new(sender: Sender<Action>) -> Self {
+        let widget: Self = glib::Object::new();
+        widget.imp().init(sender);
+        widget
+    }
+}
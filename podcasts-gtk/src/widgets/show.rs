@@ -171,7 +171,9 @@ fn populate_listbox(show: &ShowWidget, pd: Arc<Show>, sender: Sender<Action>) ->
                 dbqueries::get_pd_episodeswidgets(&pd)
             }));
 
-            if let Ok(Ok(episodes)) = episodes.await {
+            if let Ok(Ok(mut episodes)) = episodes.await {
+                // Episodes pulled from the feed can only be played if downloaded
+                episodes.retain(|ep| ep.removed_upstream().is_none() || ep.local_uri().is_some());
                 let _ = lazy_load(episodes, listbox, constructor).await;
             }
         },
@@ -31,6 +31,7 @@ use podcasts_data::Show;
 
 use crate::app::Action;
 use crate::utils;
+use crate::widgets::ShowSettingsWindow;
 
 use std::sync::Arc;
 
@@ -41,6 +42,7 @@ pub(crate) struct ShowMenu {
     pub(crate) menu: gio::MenuModel,
     website: gio::SimpleAction,
     played: gio::SimpleAction,
+    settings: gio::SimpleAction,
     unsub: gio::SimpleAction,
     group: gio::SimpleActionGroup,
 }
@@ -51,17 +53,20 @@ impl Default for ShowMenu {
         let menu = builder.object("show_menu").unwrap();
         let website = gio::SimpleAction::new("open-website", None);
         let played = gio::SimpleAction::new("mark-played", None);
+        let settings = gio::SimpleAction::new("settings", None);
         let unsub = gio::SimpleAction::new("unsubscribe", None);
         let group = gio::SimpleActionGroup::new();
 
         group.add_action(&website);
         group.add_action(&played);
+        group.add_action(&settings);
         group.add_action(&unsub);
 
         ShowMenu {
             menu,
             website,
             played,
+            settings,
             unsub,
             group,
         }
@@ -78,6 +83,7 @@ impl ShowMenu {
     fn init(&self, pd: &Arc<Show>, episodes: &gtk::ListBox, sender: &Sender<Action>) {
         self.connect_website(pd);
         self.connect_played(pd, episodes, sender);
+        self.connect_settings(pd);
         self.connect_unsub(pd, sender);
 
         let app = gio::Application::default()
@@ -110,6 +116,25 @@ impl ShowMenu {
         );
     }
 
+    fn connect_settings(&self, pd: &Arc<Show>) {
+        self.settings
+            .connect_activate(clone!(@strong pd => move |_, _| {
+                let window = match ShowSettingsWindow::new(&pd) {
+                    Ok(window) => window,
+                    Err(err) => {
+                        error!("Failed to open the settings of {}: {}", pd.title(), err);
+                        return;
+                    }
+                };
+                let app = gio::Application::default()
+                    .expect("Could not get default application")
+                    .downcast::<gtk::Application>()
+                    .unwrap();
+                window.set_transient_for(app.active_window().as_ref());
+                window.present();
+            }));
+    }
+
     fn connect_unsub(&self, pd: &Arc<Show>, sender: &Sender<Action>) {
         self.unsub
             .connect_activate(clone!(@strong pd, @strong sender => move |unsub, _| {
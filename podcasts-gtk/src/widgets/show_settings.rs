@@ -0,0 +1,188 @@
+// show_settings.rs
+//
+// Copyright 2017 Jordan Petridis <jpetridis@gnome.org>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use anyhow::Result;
+use glib::clone;
+use gtk::{glib, CompositeTemplate};
+
+use podcasts_data::dbqueries;
+use podcasts_data::{AutoDownload, Retention, Save, Show, ShowSettings};
+
+use std::cell::RefCell;
+
+/// The playback rates offered, in the order of `rate_row`, after "Default".
+const RATES: [f64; 7] = [0.75, 0.9, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+#[derive(Debug, Default, CompositeTemplate)]
+#[template(resource = "/org/gnome/Podcasts/gtk/show_settings.ui")]
+pub struct ShowSettingsWindowPriv {
+    #[template_child]
+    rate_row: TemplateChild<adw::ComboRow>,
+    #[template_child]
+    skip_intro_row: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    skip_outro_row: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    auto_download_row: TemplateChild<adw::ComboRow>,
+    #[template_child]
+    auto_download_limit_row: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    retention_row: TemplateChild<adw::ComboRow>,
+    #[template_child]
+    retention_days_row: TemplateChild<adw::SpinRow>,
+    settings: RefCell<Option<ShowSettings>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ShowSettingsWindowPriv {
+    const NAME: &'static str = "PdShowSettings";
+    type Type = ShowSettingsWindow;
+    type ParentType = adw::PreferencesWindow;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for ShowSettingsWindowPriv {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        for row in [&*self.auto_download_row, &*self.retention_row] {
+            row.connect_selected_notify(clone!(@weak obj => move |_| {
+                obj.imp().update_sensitivity();
+            }));
+        }
+    }
+}
+
+impl WidgetImpl for ShowSettingsWindowPriv {}
+
+impl WindowImpl for ShowSettingsWindowPriv {
+    fn close_request(&self) -> glib::Propagation {
+        self.save();
+        self.parent_close_request()
+    }
+}
+
+impl AdwWindowImpl for ShowSettingsWindowPriv {}
+impl PreferencesWindowImpl for ShowSettingsWindowPriv {}
+
+impl ShowSettingsWindowPriv {
+    fn load(&self, settings: &ShowSettings) {
+        let rate = settings
+            .playback_rate()
+            .and_then(|rate| RATES.iter().position(|r| (r - rate).abs() < 0.01))
+            .map_or(0, |i| i + 1);
+        self.rate_row.set_selected(rate as u32);
+        self.skip_intro_row
+            .set_value(f64::from(settings.skip_intro()));
+        self.skip_outro_row
+            .set_value(f64::from(settings.skip_outro()));
+
+        let auto_download = match settings.auto_download() {
+            AutoDownload::Off => 0,
+            AutoDownload::Latest(limit) => {
+                self.auto_download_limit_row.set_value(f64::from(limit));
+                1
+            }
+            AutoDownload::AllNew => 2,
+        };
+        self.auto_download_row.set_selected(auto_download);
+
+        let retention = match settings.retention() {
+            Retention::Default => 0,
+            Retention::Forever => 1,
+            Retention::Days(days) => {
+                self.retention_days_row.set_value(f64::from(days));
+                2
+            }
+        };
+        self.retention_row.set_selected(retention);
+        self.update_sensitivity();
+    }
+
+    fn update_sensitivity(&self) {
+        self.auto_download_limit_row
+            .set_sensitive(self.auto_download_row.selected() == 1);
+        self.retention_days_row
+            .set_sensitive(self.retention_row.selected() == 2);
+    }
+
+    /// Read the rows back into the settings and store them.
+    fn save(&self) {
+        let mut guard = self.settings.borrow_mut();
+        let settings = match guard.as_mut() {
+            Some(settings) => settings,
+            None => return,
+        };
+
+        let rate = (self.rate_row.selected() as usize)
+            .checked_sub(1)
+            .and_then(|i| RATES.get(i));
+        settings.set_playback_rate(rate.copied());
+        settings.set_skip_intro(self.skip_intro_row.value() as u32);
+        settings.set_skip_outro(self.skip_outro_row.value() as u32);
+
+        let limit = self.auto_download_limit_row.value() as u32;
+        settings.set_auto_download(match self.auto_download_row.selected() {
+            1 => AutoDownload::Latest(limit),
+            2 => AutoDownload::AllNew,
+            _ => AutoDownload::Off,
+        });
+
+        let days = self.retention_days_row.value() as u32;
+        settings.set_retention(match self.retention_row.selected() {
+            1 => Retention::Forever,
+            2 => Retention::Days(days),
+            _ => Retention::Default,
+        });
+
+        if let Err(err) = settings.save() {
+            error!("Failed to save the show settings: {}", err);
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct ShowSettingsWindow(ObjectSubclass<ShowSettingsWindowPriv>)
+        @extends gtk::Widget, gtk::Window, adw::Window, adw::PreferencesWindow,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl ShowSettingsWindow {
+    /// Create a window that edits the `ShowSettings` of `show`.
+    ///
+    /// Changes are stored when the window is closed.
+    pub(crate) fn new(show: &Show) -> Result<Self> {
+        let settings = dbqueries::get_show_settings(show.id())?;
+        let this: Self = glib::Object::new();
+        this.set_title(Some(show.title()));
+        this.imp().load(&settings);
+        this.imp().settings.replace(Some(settings));
+        Ok(this)
+    }
+}
@@ -34,6 +34,7 @@ use crate::stacks::Content;
 use crate::utils;
 use crate::widgets::about_dialog;
 use crate::widgets::player;
+use crate::widgets::{FeedHealthPage, SearchPage};
 
 use std::cell::{Cell, OnceCell, RefCell};
 use std::ops::Deref;
@@ -111,6 +112,26 @@ impl ObjectSubclass for MainWindowPriv {
             let sender = win.sender();
             utils::on_export_clicked(win.upcast_ref(), sender);
         });
+        klass.install_action("win.import-library", None, move |win, _, _| {
+            let sender = win.sender();
+            utils::on_import_library_clicked(win.upcast_ref(), sender);
+        });
+        klass.install_action("win.export-library", None, move |win, _, _| {
+            let sender = win.sender();
+            utils::on_export_library_clicked(win.upcast_ref(), sender);
+        });
+        klass.install_action("win.backup", None, move |win, _, _| {
+            let sender = win.sender();
+            utils::on_backup_clicked(win.upcast_ref(), sender);
+        });
+        klass.install_action("win.search", None, move |win, _, _| {
+            let page = SearchPage::new(win.sender().clone());
+            win.push_page(&page);
+        });
+        klass.install_action("win.feed-health", None, move |win, _, _| {
+            let page = FeedHealthPage::new(win.sender().clone());
+            win.push_page(&page);
+        });
         klass.install_action("win.about", None, move |win, _, _| {
             about_dialog(win.upcast_ref());
         });
@@ -203,7 +224,7 @@ impl MainWindow {
         // Update the feeds right after the Window is initialized.
         if imp.settings.boolean("refresh-on-startup") {
             info!("Refresh on startup.");
-            utils::schedule_refresh(None, sender.clone());
+            utils::schedule_due_refresh(sender.clone());
         }
 
         let refresh_interval = settings::get_refresh_interval(&imp.settings).num_seconds() as u32;
@@ -212,7 +233,7 @@ impl MainWindow {
         glib::timeout_add_seconds_local(
             refresh_interval,
             clone!(@strong sender => move || {
-                    utils::schedule_refresh(None, sender.clone());
+                    utils::schedule_due_refresh(sender.clone());
                     glib::ControlFlow::Continue
             }),
         );
@@ -242,6 +263,19 @@ impl MainWindow {
             .initialize_episode(rowid, second)
     }
 
+    pub(crate) fn init_episode_chapters(&self, rowid: i32) -> anyhow::Result<()> {
+        self.imp()
+            .player
+            .get()
+            .unwrap()
+            .borrow_mut()
+            .initialize_chapters(rowid)
+    }
+
+    pub(crate) fn player(&self) -> player::PlayerWrapper {
+        self.imp().player.get().unwrap().clone()
+    }
+
     pub(crate) fn add_toast(&self, toast: adw::Toast) {
         self.imp().toast_overlay.add_toast(toast);
     }